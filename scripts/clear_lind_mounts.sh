@@ -3,7 +3,8 @@ set -u
 
 # clear_lindy.sh
 # Safely remove all lindy blocks from /etc/fstab, attempt to unmount bind targets
-# and remove metadata under /var/lib/lindy.
+# and remove their metadata from the invoking user's store
+# (~/.local/share/lindy) and the legacy /var/lib/lindy.
 # Usage:
 #  sudo ./scripts/clear_lind_mounts.sh [--dry-run] [--force]
 # --dry-run: only print what would be done
@@ -30,7 +31,9 @@ if [[ $(id -u) -ne 0 ]]; then
 fi
 
 FSTAB=/etc/fstab
-META_DIR=/var/lib/lindy
+META_USER=${SUDO_USER:-$(id -un)}
+META_DIR="$(getent passwd "$META_USER" | cut -d: -f6)/.local/share/lindy"
+LEGACY_META_DIR=/var/lib/lindy
 TMP_NEW=/tmp/lindy-newfst-$$.tmp
 NOW=$(date +%s)
 BACKUP=/etc/fstab.lindy.bak.${NOW}
@@ -58,8 +61,9 @@ for id in "${BLOCK_IDS[@]}"; do
   targets=()
   part_mp=""
   while IFS= read -r line; do
-    # detect bind lines: <src> <target> none bind ...
-    if [[ "$line" =~ [[:space:]]+none[[:space:]]+bind ]]; then
+    # detect bind lines: `bind` or `rbind` anywhere in the options column
+    opts=$(echo "$line" | awk '$1 !~ /^#/ {print $4}')
+    if [[ ",$opts," == *,bind,* || ",$opts," == *,rbind,* ]]; then
       # extract second column
       tgt=$(echo "$line" | awk '{print $2}')
       targets+=("$tgt")
@@ -144,12 +148,13 @@ for id in "${BLOCK_IDS[@]}"; do
     fi
   fi
 
-  # remove metadata file if exists
-  meta="$META_DIR/$id.json"
-  if [[ -f "$meta" ]]; then
-    echo "Removing metadata $meta"
-    rm -f "$meta" || echo "  failed to remove metadata $meta"
-  fi
+  # remove metadata files if they exist
+  for meta in "$META_DIR/$id.json" "$LEGACY_META_DIR/$id.json"; do
+    if [[ -f "$meta" ]]; then
+      echo "Removing metadata $meta"
+      rm -f "$meta" || echo "  failed to remove metadata $meta"
+    fi
+  done
 done
 
 # Backup fstab and write new fstab without lindy blocks
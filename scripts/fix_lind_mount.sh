@@ -44,20 +44,29 @@ else
   fi
 fi
 
-# write metadata
-sudo mkdir -p /var/lib/lindy
+# write metadata to the invoking user's store (src-tauri/src/metadata.rs)
+META_USER=${SUDO_USER:-$(id -un)}
+META_DIR="$(getent passwd "$META_USER" | cut -d: -f6)/.local/share/lindy"
+META="$META_DIR/${ID}.json"
 NOW=$(date +%s)
-sudo sh -c "cat > /var/lib/lindy/${ID}.json <<'JSON'
+mkdir -p "$META_DIR"
+cat > "$META" <<JSON
 {
-  \"id\": \"${ID}\",
-  \"block\": \"# lindy BEGIN: ${ID}\\n${CORRECT_SRC} ${TARGET} none bind 0 0\\n# lindy END: ${ID}\\n\",
-  \"targets\": [\"${TARGET}\"],
-  \"installed_at\": ${NOW},
-  \"persisted\": true
+  "version": 1,
+  "id": "${ID}",
+  "block": "# lindy BEGIN: ${ID}\n${CORRECT_SRC} ${TARGET} none bind 0 0\n# lindy END: ${ID}\n",
+  "targets": ["${TARGET}"],
+  "partition_uuid": null,
+  "persisted": true,
+  "installed_at": ${NOW},
+  "persisted_at": ${NOW}
 }
-JSON"
+JSON
+if [[ $(id -u) -eq 0 && -n "${SUDO_USER:-}" ]]; then
+  chown -R "$SUDO_USER": "$META_DIR"
+fi
 
-echo "WROTE /var/lib/lindy/${ID}.json"
+echo "WROTE $META"
 
 # verification
 echo
@@ -66,7 +75,7 @@ sudo awk "/# lindy BEGIN: ${ID}/,/# lindy END: ${ID}/" /etc/fstab || true
 
 echo
 echo "--- metadata ---"
-sudo cat /var/lib/lindy/${ID}.json || true
+cat "$META" || true
 
 echo
 echo "--- findmnt for $TARGET ---"
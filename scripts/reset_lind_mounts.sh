@@ -2,7 +2,8 @@
 set -euo pipefail
 
 # Reset all lindy managed blocks from /etc/fstab and optionally unmount targets and
-# remove metadata under /var/lib/lindy.
+# remove metadata from the invoking user's store (~/.local/share/lindy) and the
+# legacy /var/lib/lindy.
 #
 # Usage:
 #   ./scripts/reset_lind_mounts.sh        # dry-run, shows what would be removed
@@ -31,14 +32,16 @@ echo "Scanning /etc/fstab for '# lindy BEGIN:' blocks (dry-run)..."
 
 # Extract cleaned fstab (removing lindy blocks) and collect bind targets from removed blocks
 # Note: avoid using the awk keyword `in` as a variable name for portability.
+# Bind lines carry `bind` or `rbind` anywhere in the options column (same rule as the app).
 awk -v targets_file="$TMP_TARGETS" '
+  function is_bind(opts,    n, i, o) { n = split(opts, o, ","); for (i = 1; i <= n; i++) if (o[i] == "bind" || o[i] == "rbind") return 1; return 0 }
   BEGIN { in_block = 0 }
   /^# lindy BEGIN:/ { in_block = 1; next }
   /^# lindy END:/ { in_block = 0; next }
   {
     if (in_block) {
-      # look for bind lines: <src> <target> none bind 0 0
-      if (NF >= 4 && $3 == "none" && $4 == "bind") {
+      # look for bind lines: <src> <target> <type> <...,bind,...> 0 0
+      if (NF >= 4 && $1 !~ /^#/ && is_bind($4)) {
         print $2 >> targets_file
       }
     } else {
@@ -70,7 +73,7 @@ if [[ "$APPLY" != true ]]; then
   echo "The script will:"
   echo "  - attempt to unmount detected bind targets (try normal umount, then lazy umount),"
   echo "  - back up /etc/fstab to /etc/fstab.lind-reset.bak.<ts>, replace /etc/fstab atomically, run sync and mount -a,"
-  echo "  - remove metadata files under /var/lib/lindy/*.json"
+  echo "  - remove metadata files under ~/.local/share/lindy/*.json and /var/lib/lindy/*.json"
   echo
   # leave temp files for inspection
   echo "Temporary files retained for inspection:" 
@@ -126,13 +129,17 @@ else
   echo "  sudo cp $BACKUP /etc/fstab && sudo sync && sudo mount -a"
 fi
 
-# Remove metadata files under /var/lib/lindy
-if [[ -d /var/lib/lindy ]]; then
-  echo "Removing metadata files under /var/lib/lindy"
-  rm -f /var/lib/lindy/*.json || true
-else
-  echo "/var/lib/lindy does not exist; skipping metadata removal"
-fi
+# Remove metadata files from the user's store and the legacy location
+META_USER=${SUDO_USER:-$(id -un)}
+META_DIR="$(getent passwd "$META_USER" | cut -d: -f6)/.local/share/lindy"
+for dir in "$META_DIR" /var/lib/lindy; do
+  if [[ -d "$dir" ]]; then
+    echo "Removing metadata files under $dir"
+    rm -f "$dir"/*.json || true
+  else
+    echo "$dir does not exist; skipping metadata removal"
+  fi
+done
 
 echo "Cleanup: removing temporary targets file"
 rm -f "$TMP_TARGETS"
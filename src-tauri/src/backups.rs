@@ -0,0 +1,173 @@
+// Backups of /etc/fstab left behind by privileged operations.
+//
+// Every helper request that modifies /etc/fstab first copies it to
+// `/etc/fstab.lindy.bak.<unix seconds>`. This module lists those copies and
+// decides which of them the retention policy allows us to delete. The
+// deletion itself is `Request::PruneBackups`: the app sends only the policy,
+// and the privileged helper (helper.rs) lists the backups and applies it.
+
+use crate::fstab::FstabFile;
+use std::path::{Path, PathBuf};
+
+pub const BACKUP_DIR: &str = "/etc";
+pub const BACKUP_PREFIX: &str = "fstab.lindy.bak.";
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Path of the backup taken at `ts` (seconds since the epoch) in `dir`.
+pub fn path_in(dir: &Path, ts: u64) -> PathBuf {
+    dir.join(format!("{}{}", BACKUP_PREFIX, ts))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, ts_rs::TS)]
+#[ts(export)]
+pub struct FstabBackup {
+    #[ts(type = "number")]
+    pub ts: u64,
+    pub path: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    /// Ids of the lindy blocks in the backup, or None when it is not readable.
+    pub blocks: Option<Vec<String>>,
+}
+
+/// Backups found in `dir`, newest first.
+pub fn list(dir: &Path) -> Vec<FstabBackup> {
+    let mut backups: Vec<FstabBackup> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name();
+            let ts = parse_name(name.to_str()?)?;
+            let meta = e.metadata().ok().filter(|m| m.is_file())?;
+            let path = e.path();
+            let blocks = path
+                .to_str()
+                .and_then(|p| FstabFile::read(p).ok())
+                .map(|f| f.blocks().into_iter().map(|b| b.id).collect());
+            Some(FstabBackup {
+                ts,
+                path: path.display().to_string(),
+                size: meta.len(),
+                blocks,
+            })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.ts));
+    backups
+}
+
+/// The backup taken at `ts`, if it exists in `dir`.
+pub fn find(dir: &Path, ts: u64) -> Option<FstabBackup> {
+    list(dir).into_iter().find(|b| b.ts == ts)
+}
+
+/// Timestamp of a backup file name, or None for any other file.
+pub fn parse_name(name: &str) -> Option<u64> {
+    let ts = name.strip_prefix(BACKUP_PREFIX)?;
+    if ts.is_empty() || !ts.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    ts.parse().ok()
+}
+
+/// Which backups may be deleted. A backup is kept while it is one of the
+/// `keep_last` newest ones or younger than `max_age_days`; only backups that
+/// fall outside both limits are pruned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub keep_last: usize,
+    pub max_age_days: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_last: 10,
+            max_age_days: 30,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Backups (from a newest-first list) that the policy lets us delete.
+    pub fn expired<'a>(&self, backups: &'a [FstabBackup], now: u64) -> Vec<&'a FstabBackup> {
+        let max_age = self.max_age_days.saturating_mul(DAY_SECS);
+        backups
+            .iter()
+            .skip(self.keep_last)
+            .filter(|b| now.saturating_sub(b.ts) > max_age)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup(ts: u64) -> FstabBackup {
+        FstabBackup {
+            ts,
+            path: path_in(Path::new(BACKUP_DIR), ts).display().to_string(),
+            size: 0,
+            blocks: None,
+        }
+    }
+
+    #[test]
+    fn parses_backup_names_only() {
+        assert_eq!(parse_name("fstab.lindy.bak.1700000000"), Some(1700000000));
+        assert_eq!(parse_name("fstab.lindy.bak."), None);
+        assert_eq!(parse_name("fstab.lindy.bak.17x"), None);
+        assert_eq!(parse_name("fstab.lindy.manual.bak.1700000000"), None);
+        assert_eq!(parse_name("fstab"), None);
+    }
+
+    #[test]
+    fn lists_newest_first_with_block_ids() {
+        let dir = std::env::temp_dir().join(format!("lindy_backups_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("fstab.lindy.bak.100"), "/a /b none bind 0 0\n").unwrap();
+        std::fs::write(
+            dir.join("fstab.lindy.bak.200"),
+            "# lindy BEGIN: x\n/a /b none bind 0 0\n# lindy END: x\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("fstab"), "").unwrap();
+
+        let backups = list(&dir);
+        assert_eq!(
+            backups.iter().map(|b| b.ts).collect::<Vec<_>>(),
+            vec![200, 100]
+        );
+        assert_eq!(backups[0].blocks, Some(vec!["x".to_string()]));
+        assert_eq!(backups[1].blocks, Some(vec![]));
+        assert!(find(&dir, 100).is_some());
+        assert!(find(&dir, 300).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn retention_keeps_newest_and_recent() {
+        let now = 100 * DAY_SECS;
+        // newest first: 1, 2, 40, 50 and 60 days old
+        let backups: Vec<FstabBackup> = [1, 2, 40, 50, 60]
+            .iter()
+            .map(|d| backup(now - d * DAY_SECS))
+            .collect();
+        let policy = RetentionPolicy {
+            keep_last: 3,
+            max_age_days: 30,
+        };
+        let expired: Vec<u64> = policy.expired(&backups, now).iter().map(|b| b.ts).collect();
+        assert_eq!(expired, vec![now - 50 * DAY_SECS, now - 60 * DAY_SECS]);
+
+        let recent = RetentionPolicy {
+            keep_last: 0,
+            max_age_days: 45,
+        };
+        assert_eq!(recent.expired(&backups, now).len(), 2);
+    }
+}
@@ -0,0 +1,6 @@
+// Privileged helper launched by the app through pkexec; reads one JSON
+// request on stdin and prints one JSON response on stdout.
+
+fn main() {
+    std::process::exit(lindy_lib::run_helper())
+}
@@ -0,0 +1,261 @@
+// Portable bundles of the managed mappings.
+//
+// A reinstalled system keeps its partitions but loses /etc/fstab and the
+// metadata store. A bundle holds every managed block with partitions named
+// by UUID (device names like /dev/sdb3 can change between installs) and the
+// exporting user's home, so an import can point the bind targets at the
+// importing user's home and refuse blocks whose partitions are not present.
+
+use crate::fstab::{FstabFile, FstabLine, LineKind};
+use crate::response::LindyError;
+use crate::PartitionInfo;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Version written by this build; newer bundles are refused.
+pub const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct MappingBundle {
+    pub version: u32,
+    #[ts(type = "number")]
+    pub exported_at: u64,
+    /// Home directory of the exporting user.
+    pub home: String,
+    pub blocks: Vec<BundledBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct BundledBlock {
+    pub id: String,
+    /// Block text with its markers; partitions are given as `UUID=`.
+    pub block: String,
+    /// UUIDs of the partitions the block mounts.
+    pub partitions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Restored,
+    /// A block with the same id is already in /etc/fstab.
+    AlreadyPresent,
+    /// A partition the block mounts is not on this system.
+    MissingPartition,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ImportOutcome {
+    pub id: String,
+    pub status: ImportStatus,
+    pub message: String,
+    pub error: Option<LindyError>,
+}
+
+impl ImportOutcome {
+    pub fn new(id: &str, status: ImportStatus, message: impl Into<String>) -> ImportOutcome {
+        ImportOutcome {
+            id: id.to_string(),
+            status,
+            message: message.into(),
+            error: None,
+        }
+    }
+}
+
+// UUID of the partition a spec names by device or label, if known.
+fn uuid_for_spec(spec: &str, partitions: &[PartitionInfo]) -> Option<String> {
+    let found = if let Some(name) = spec.strip_prefix("/dev/") {
+        partitions.iter().find(|p| p.name == name)
+    } else if let Some(label) = spec.strip_prefix("LABEL=") {
+        partitions
+            .iter()
+            .find(|p| p.label.as_deref() == Some(label))
+    } else {
+        None
+    };
+    found.and_then(|p| p.uuid.clone())
+}
+
+// Rewrite lines of `block` through `f`, which returns None to keep a line.
+fn rewrite(block: &str, f: impl Fn(&crate::fstab::FstabEntry) -> Option<String>) -> String {
+    let lines: Vec<String> = FstabFile::parse(block)
+        .lines
+        .iter()
+        .map(|l| match &l.kind {
+            LineKind::Entry(e) => f(e).unwrap_or_else(|| l.raw.clone()),
+            _ => l.raw.clone(),
+        })
+        .collect();
+    lines.join("\n") + "\n"
+}
+
+/// Bundle the blocks of `fstab` whose id is in `managed` (has metadata),
+/// as they are written there. Metadata without a block, such as a mapping
+/// that failed to persist, is left out.
+pub fn export(
+    fstab: &FstabFile,
+    managed: &[String],
+    partitions: &[PartitionInfo],
+    home: &str,
+    now: u64,
+) -> MappingBundle {
+    let blocks = fstab
+        .blocks()
+        .into_iter()
+        .filter(|b| managed.contains(&b.id))
+        .map(|b| {
+            let block = rewrite(&b.text(), |e| {
+                let uuid = uuid_for_spec(&e.spec, partitions)?;
+                let mut e = e.clone();
+                e.spec = format!("UUID={}", uuid);
+                Some(e.to_string())
+            });
+            let partitions = FstabFile::parse(&block)
+                .entries()
+                .filter_map(|e| e.spec.strip_prefix("UUID=").map(str::to_string))
+                .collect();
+            BundledBlock {
+                id: b.id,
+                block,
+                partitions,
+            }
+        })
+        .collect();
+    MappingBundle {
+        version: BUNDLE_VERSION,
+        exported_at: now,
+        home: home.to_string(),
+        blocks,
+    }
+}
+
+/// A bundled block ready to apply: its text with targets moved from
+/// `from_home` to `home`, and those targets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prepared {
+    pub block: String,
+    pub targets: Vec<String>,
+}
+
+/// Check a bundled block against this system. Errors are the outcome to
+/// report instead of applying it.
+pub fn prepare(
+    b: &BundledBlock,
+    from_home: &str,
+    home: &str,
+    partitions: &[PartitionInfo],
+    fstab: &FstabFile,
+) -> Result<Prepared, Box<ImportOutcome>> {
+    if fstab.block(&b.id).is_some() {
+        return Err(Box::new(ImportOutcome::new(
+            &b.id,
+            ImportStatus::AlreadyPresent,
+            format!("block {} is already in /etc/fstab", b.id),
+        )));
+    }
+    let missing: Vec<&str> = b
+        .partitions
+        .iter()
+        .filter(|u| !partitions.iter().any(|p| p.uuid.as_ref() == Some(*u)))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(Box::new(ImportOutcome::new(
+            &b.id,
+            ImportStatus::MissingPartition,
+            format!(
+                "partition UUID {} is not on this system",
+                missing.join(", ")
+            ),
+        )));
+    }
+    let from_home = from_home.trim_end_matches('/');
+    let block = rewrite(&b.block, |e| {
+        let rest = e.file.strip_prefix(from_home)?;
+        if from_home.is_empty() || from_home == home || !(rest.is_empty() || rest.starts_with('/'))
+        {
+            return None;
+        }
+        let mut e = e.clone();
+        e.file = format!("{}{}", home, rest);
+        Some(e.to_string())
+    });
+    let lines: Vec<FstabLine> = FstabFile::parse(&block).lines;
+    let targets = lines
+        .iter()
+        .filter_map(FstabLine::entry)
+        .filter(|e| e.is_bind())
+        .map(|e| e.file.clone())
+        .collect();
+    Ok(Prepared { block, targets })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(name: &str, uuid: &str, label: Option<&str>) -> PartitionInfo {
+        PartitionInfo {
+            name: name.into(),
+            fstype: Some("ntfs".into()),
+            uuid: Some(uuid.into()),
+            label: label.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exports_blocks_by_uuid() {
+        let parts = [part("sdb3", "AAAA-1111", Some("Windows"))];
+        let fstab = FstabFile::parse(
+            "UUID=root / ext4 defaults 0 1\n\
+             # lindy BEGIN: w\n/dev/sdb3 /mnt/win ntfs defaults 0 2\n\
+             /mnt/win/Users/Ann/Documents /home/ann/Documents none bind 0 0\n# lindy END: w\n\
+             # lindy BEGIN: unmanaged\n/a /b none bind 0 0\n# lindy END: unmanaged\n",
+        );
+        // "failed" has metadata only: its block never made it into fstab
+        let bundle = export(
+            &fstab,
+            &["failed".into(), "w".into()],
+            &parts,
+            "/home/ann",
+            7,
+        );
+        assert_eq!(bundle.blocks.len(), 1);
+        let b = &bundle.blocks[0];
+        assert!(b
+            .block
+            .contains("\nUUID=AAAA-1111 /mnt/win ntfs defaults 0 2\n"));
+        assert!(b.block.ends_with("# lindy END: w\n"));
+        assert_eq!(b.partitions, vec!["AAAA-1111"]);
+    }
+
+    #[test]
+    fn prepares_blocks_for_this_system() {
+        let b = BundledBlock {
+            id: "w".into(),
+            block: "# lindy BEGIN: w\nUUID=AAAA-1111 /mnt/win ntfs defaults 0 2\n\
+                    /mnt/win/Users/Ann/Documents /home/ann/Documents none bind 0 0\n\
+                    /mnt/win/Shared /home/annex none bind 0 0\n# lindy END: w\n"
+                .into(),
+            partitions: vec!["AAAA-1111".into()],
+        };
+        let empty = FstabFile::parse("");
+        let parts = [part("nvme0n1p3", "AAAA-1111", None)];
+
+        let p = prepare(&b, "/home/ann", "/home/anna", &parts, &empty).unwrap();
+        assert_eq!(p.targets, vec!["/home/anna/Documents", "/home/annex"]);
+        assert!(p.block.contains("/home/anna/Documents none bind 0 0"));
+
+        let err = prepare(&b, "/home/ann", "/home/anna", &[], &empty).unwrap_err();
+        assert_eq!(err.status, ImportStatus::MissingPartition);
+
+        let present = FstabFile::parse(&b.block);
+        let err = prepare(&b, "/home/ann", "/home/anna", &parts, &present).unwrap_err();
+        assert_eq!(err.status, ImportStatus::AlreadyPresent);
+    }
+}
@@ -0,0 +1,967 @@
+// Headless mode: `lindy <command> [options]`.
+//
+// Lab machines are managed over SSH without a display, so the binary also
+// takes subcommands. They call the same functions as the Tauri commands and
+// print a short summary, or with `--json` the same JSON the frontend gets.
+// Privileged steps still go through pkexec, which asks for the password on
+// the terminal (and does not ask at all when run under sudo).
+
+use crate::bundle::{ImportStatus, MappingBundle};
+use crate::consistency::{ConsistencyEntry, ConsistencyState};
+use crate::fstab::{self, FstabFile};
+use crate::helper::Persist;
+use crate::lsblk::PartitionKind;
+use crate::metadata::MetadataStore;
+use crate::mountinfo::BindState;
+use crate::mountopts::{self, DriverInfo};
+use crate::plan::Operation;
+use crate::profile::{PlanAction, Profile, ProfilePlan};
+use crate::response::{LindyError, LindyResponse};
+use crate::validate::{self, Diagnostic};
+use crate::{FolderMapping, FstabBlock, WindowsPartition};
+use serde::Serialize;
+
+const USAGE: &str = "\
+usage: lindy [--json] <command> [options]
+
+commands:
+  list                      lindy blocks in /etc/fstab and their mount state
+  detect                    Windows (NTFS/exFAT/BitLocker) partitions and
+                            their users
+  unlock <device> [--persist prompt|key-file]
+                            open a BitLocker volume; the password or recovery
+                            key is read from stdin. --persist also opens it
+                            at boot, asking for the key or using a stored one
+  drivers                   filesystem drivers this system has and their
+                            mount options
+  automap [--user NAME] [--mount-base DIR] [--driver NAME]
+          [--partition UUID] [--dry-run]
+                            bind the Windows user's folders onto $HOME;
+                            --driver picks ntfs3 or ntfs-3g for NTFS,
+                            --partition the Windows partition when detection
+                            finds several equally likely ones
+  apply <file> [--id ID] [--dry-run]
+                            append the fstab block in <file> and mount it
+  adopt <id>                manage a lindy block that has no metadata
+  apply-profile <file> [--dry-run]
+                            create or update the block described by a TOML
+                            profile; --dry-run shows the changes only
+  remove <id> [--force] [--dry-run]
+                            unmount and remove a block (--force: lazy unmount)
+  export                    print every managed mapping as a JSON bundle
+  import <file>             re-create the mappings of an exported bundle
+  status                    exit 1 unless every bind is mounted and in sync
+
+--dry-run prints the change to /etc/fstab and the mount operations without
+running them. --json prints results and errors as JSON. Without a command the app opens.
+";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    List,
+    Detect,
+    Drivers,
+    AutoMap {
+        user: Option<String>,
+        mount_base: Option<String>,
+        driver: Option<String>,
+        partition: Option<String>,
+        dry_run: bool,
+    },
+    Apply {
+        file: String,
+        id: Option<String>,
+        dry_run: bool,
+    },
+    Adopt {
+        id: String,
+    },
+    ApplyProfile {
+        file: String,
+        dry_run: bool,
+    },
+    Remove {
+        id: String,
+        force: bool,
+        dry_run: bool,
+    },
+    Unlock {
+        device: String,
+        persist: Option<String>,
+    },
+    Export,
+    Import {
+        file: String,
+    },
+    Status,
+    Help,
+}
+
+// Returns the command and whether `--json` was given.
+fn parse(args: &[String]) -> Result<(Command, bool), String> {
+    let (mut json, mut dry_run, mut force) = (false, false, false);
+    let (mut user, mut mount_base, mut id, mut driver) = (None, None, None, None);
+    let (mut persist, mut partition) = (None, None);
+    let mut words = Vec::new();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let (name, inline) = match arg.split_once('=') {
+            Some((n, v)) if n.starts_with("--") => (n, Some(v.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| it.next().cloned())
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+        match name {
+            "--json" => json = true,
+            "--dry-run" => dry_run = true,
+            "--force" => force = true,
+            "--user" => user = Some(value()?),
+            "--mount-base" => mount_base = Some(value()?),
+            "--id" => id = Some(value()?),
+            "--driver" => driver = Some(value()?),
+            "--persist" => persist = Some(value()?),
+            "--partition" => partition = Some(value()?),
+            "-h" | "--help" => return Ok((Command::Help, json)),
+            n if n.starts_with('-') => return Err(format!("unknown option {}", n)),
+            _ => words.push(arg.clone()),
+        }
+    }
+
+    let operand = |what: &str| {
+        words
+            .get(1)
+            .cloned()
+            .ok_or_else(|| format!("missing {}", what))
+    };
+    let (command, operands) = match words.first().map(String::as_str) {
+        None | Some("help") => (Command::Help, 0),
+        Some("list") => (Command::List, 0),
+        Some("detect") => (Command::Detect, 0),
+        Some("drivers") => (Command::Drivers, 0),
+        Some("status") => (Command::Status, 0),
+        Some("export") => (Command::Export, 0),
+        Some("unlock") => (
+            Command::Unlock {
+                device: operand("<device>")?,
+                persist,
+            },
+            1,
+        ),
+        Some("import") => (
+            Command::Import {
+                file: operand("<file>")?,
+            },
+            1,
+        ),
+        Some("automap") => (
+            Command::AutoMap {
+                user,
+                mount_base,
+                driver,
+                partition,
+                dry_run,
+            },
+            0,
+        ),
+        Some("apply") => (
+            Command::Apply {
+                file: operand("<file>")?,
+                id,
+                dry_run,
+            },
+            1,
+        ),
+        Some("apply-profile") => (
+            Command::ApplyProfile {
+                file: operand("<file>")?,
+                dry_run,
+            },
+            1,
+        ),
+        Some("adopt") => (
+            Command::Adopt {
+                id: operand("<id>")?,
+            },
+            1,
+        ),
+        Some("remove") => (
+            Command::Remove {
+                id: operand("<id>")?,
+                force,
+                dry_run,
+            },
+            1,
+        ),
+        Some(other) => return Err(format!("unknown command {}", other)),
+    };
+    if let Some(extra) = words.get(operands + 1) {
+        return Err(format!("unexpected argument {}", extra));
+    }
+    Ok((command, json))
+}
+
+/// Run one command; returns the process exit code.
+pub fn main(args: Vec<String>) -> i32 {
+    let (command, json) = match parse(&args) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("lindy: {}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+    match command {
+        Command::Help => {
+            print!("{}", USAGE);
+            0
+        }
+        Command::List => finish(json, crate::list_fstab_blocks()),
+        Command::Detect => finish(json, crate::detect_windows_partitions()),
+        Command::Drivers => finish(json, Ok(crate::filesystem_drivers())),
+        Command::AutoMap {
+            user,
+            mount_base,
+            driver,
+            partition,
+            dry_run,
+        } => finish(json, automap(user, mount_base, driver, partition, dry_run)),
+        Command::Apply { file, id, dry_run } => finish(json, apply(&file, id, dry_run)),
+        Command::Adopt { id } => finish(json, crate::adopt_block(&id)),
+        Command::ApplyProfile { file, dry_run } => finish(json, apply_profile(&file, dry_run)),
+        Command::Remove { id, force, dry_run } => {
+            finish(json, crate::remove_fstab_block(&id, force, Some(dry_run)))
+        }
+        Command::Unlock { device, persist } => finish(json, unlock(device, persist)),
+        Command::Export => finish(true, crate::export_mappings()),
+        Command::Import { file } => finish(json, crate::import_mappings(&file)),
+        Command::Status => finish(json, status()),
+    }
+}
+
+/// A command result that can be printed for a person.
+trait Report: Serialize {
+    fn print(&self);
+
+    /// Whether the exit code should be 0.
+    fn success(&self) -> bool {
+        true
+    }
+}
+
+fn finish<T: Report>(json: bool, result: Result<T, LindyError>) -> i32 {
+    match result {
+        Ok(value) => {
+            if json {
+                println!("{}", serde_json::to_string_pretty(&value).unwrap());
+            } else {
+                value.print();
+            }
+            if value.success() {
+                0
+            } else {
+                1
+            }
+        }
+        Err(err) => {
+            if json {
+                println!("{}", serde_json::to_string_pretty(&err).unwrap());
+            } else {
+                eprintln!("lindy: {}", err);
+                for line in error_details(&err) {
+                    eprintln!("  {}", line);
+                }
+            }
+            1
+        }
+    }
+}
+
+// Helper log, diagnostics or pkexec output attached to an error.
+fn error_details(err: &LindyError) -> Vec<String> {
+    match err {
+        LindyError::ValidationFailed { diagnostics, .. } => diagnostics
+            .iter()
+            .map(|d| match d.line {
+                Some(line) => format!("line {}: {}", line, d.message),
+                None => d.message.clone(),
+            })
+            .collect(),
+        LindyError::PkexecFailed { stderr, .. } => stderr.lines().map(str::to_string).collect(),
+        LindyError::BitlockerLocked { partition, .. } => {
+            vec![format!("run `lindy unlock {}`", partition.device)]
+        }
+        LindyError::AmbiguousWindowsPartition { candidates, .. } => candidates
+            .iter()
+            .map(|p| {
+                format!(
+                    "--partition {}  {} (score {}: {})",
+                    p.uuid,
+                    p.device,
+                    p.score,
+                    p.reasons.join(", ")
+                )
+            })
+            .collect(),
+        LindyError::Busy { log, .. }
+        | LindyError::UnlockFailed { log, .. }
+        | LindyError::RolledBack { log, .. }
+        | LindyError::MountFailed { log, .. }
+        | LindyError::UmountFailed { log, .. } => log.clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn state_label(state: Option<&BindState>) -> String {
+    match state {
+        Some(BindState::Mounted) => "mounted".into(),
+        Some(BindState::NotMounted) => "not mounted".into(),
+        Some(BindState::Shadowed { source, .. }) => format!("covered by {}", source),
+        Some(BindState::Mismatch { source, root, .. }) => {
+            format!("mounted from {}:{}", source, root)
+        }
+        Some(BindState::Stale { reason }) => format!("stale: {}", reason),
+        None => "unknown".into(),
+    }
+}
+
+fn operation_label(op: &Operation) -> String {
+    match op {
+        Operation::Mkdir { path } => format!("mkdir -p {}", path),
+        Operation::Mount {
+            source,
+            target,
+            read_only,
+        } => {
+            let ro = if *read_only { " -o ro" } else { "" };
+            format!("mount{} {} {}", ro, source, target)
+        }
+        Operation::Umount {
+            target,
+            lazy_if_busy,
+        } => {
+            let lazy = if *lazy_if_busy { " (lazy if busy)" } else { "" };
+            format!("umount {}{}", target, lazy)
+        }
+        Operation::MountAll { mounts } if mounts.is_empty() => "mount -a".into(),
+        Operation::MountAll { mounts } => format!("mount -a ({})", mounts.join(", ")),
+    }
+}
+
+impl Report for Vec<FstabBlock> {
+    fn print(&self) {
+        if self.is_empty() {
+            println!("no lindy blocks in /etc/fstab");
+        }
+        for b in self {
+            let note = if b.managed { "" } else { " (no metadata)" };
+            println!("{}{}", b.id, note);
+            for bind in &b.binds {
+                println!(
+                    "  {} -> {}  [{}]",
+                    bind.src,
+                    bind.target,
+                    state_label(bind.mount.as_ref())
+                );
+            }
+        }
+    }
+}
+
+const HIBERNATED_NOTE: &str =
+    "Windows is hibernated (Fast Startup) or was not shut down cleanly: read-only until it is shut down fully";
+
+impl Report for Vec<WindowsPartition> {
+    fn print(&self) {
+        if self.is_empty() {
+            println!("no Windows partitions (NTFS/exFAT/BitLocker) found");
+        }
+        for p in self {
+            println!(
+                "{}  UUID={}  {}  {}",
+                p.device,
+                p.uuid,
+                p.label.as_deref().unwrap_or("-"),
+                p.size.as_deref().unwrap_or("-")
+            );
+            let disk: Vec<&str> = [p.model.as_deref(), p.transport.as_deref()]
+                .into_iter()
+                .flatten()
+                .chain(p.removable.then_some("removable"))
+                .collect();
+            if !disk.is_empty() {
+                println!("  disk: {}", disk.join(", "));
+            }
+            println!("  score {}: {}", p.score, p.reasons.join(", "));
+            if p.kind == PartitionKind::Recovery {
+                println!("  Windows recovery partition (not used by automap)");
+            }
+            match (&p.mount_point, &p.fsused, &p.fsavail) {
+                (Some(mp), Some(used), Some(avail)) => {
+                    println!("  mounted at {} ({} used, {} free)", mp, used, avail)
+                }
+                (Some(mp), _, _) => println!("  mounted at {}", mp),
+                (None, _, _) => println!("  not mounted"),
+            }
+            if !p.profiles.is_empty() {
+                println!("  users:");
+                for u in &p.profiles {
+                    let folder = u.folder.as_deref().unwrap_or("not on this partition");
+                    println!("    {}  {}  ({})", u.name(), u.sid, folder);
+                }
+            } else if !p.detected_users.is_empty() {
+                println!("  users: {}", p.detected_users.join(", "));
+            }
+            if p.hibernated == Some(true) {
+                println!("  {}", HIBERNATED_NOTE);
+            }
+            if p.locked {
+                println!("  BitLocker, locked: run `lindy unlock {}`", p.device);
+            } else if p.encrypted {
+                println!("  BitLocker, unlocked");
+            }
+        }
+    }
+}
+
+impl Report for Vec<DriverInfo> {
+    fn print(&self) {
+        for d in self {
+            let kind = if d.fuse { "FUSE" } else { "kernel" };
+            let state = if d.available {
+                "available"
+            } else {
+                "not installed"
+            };
+            println!("{} ({}, {})", d.driver.fstab_type(), kind, state);
+            if !d.options.is_empty() {
+                println!("  {}", d.options);
+            }
+        }
+    }
+}
+
+impl Report for LindyResponse {
+    fn print(&self) {
+        println!("{}", self.message());
+        match self {
+            LindyResponse::AdoptableExistingBlock { id, .. } => {
+                println!("run `lindy adopt {}` to manage it", id);
+            }
+            LindyResponse::Imported { outcomes, .. } => {
+                for o in outcomes {
+                    println!("  {}: {}", o.id, o.message);
+                }
+            }
+            LindyResponse::Planned { plan, .. } => {
+                print!("{}", plan.fstab_diff);
+                for op in &plan.operations {
+                    println!("  {}", operation_label(op));
+                }
+                for b in &plan.backups {
+                    println!("  backup {}", b);
+                }
+                for c in &plan.conflicts {
+                    println!("  conflict: {}", c);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn success(&self) -> bool {
+        match self {
+            LindyResponse::AdoptableExistingBlock { .. } => false,
+            LindyResponse::Planned { plan, .. } => plan.conflicts.is_empty(),
+            LindyResponse::Imported { outcomes, .. } => {
+                outcomes.iter().all(|o| o.status != ImportStatus::Failed)
+            }
+            _ => true,
+        }
+    }
+}
+
+// `export` always prints JSON: the bundle is what gets saved.
+impl Report for MappingBundle {
+    fn print(&self) {}
+}
+
+#[derive(Serialize)]
+struct AutoMap {
+    partition: WindowsPartition,
+    mount_point: String,
+    /// False when a dry run found the partition unmounted; users and
+    /// folders cannot be detected until it is mounted.
+    mounted: bool,
+    username: Option<String>,
+    mappings: Vec<FolderMapping>,
+    id: Option<String>,
+    block: Option<String>,
+    /// What applying the block returned; None for a dry run.
+    applied: Option<LindyResponse>,
+}
+
+impl Report for AutoMap {
+    fn print(&self) {
+        let p = &self.partition;
+        println!(
+            "partition: {} {} UUID={}",
+            p.device,
+            p.label.as_deref().unwrap_or(""),
+            p.uuid
+        );
+        println!("score {}: {}", p.score, p.reasons.join(", "));
+        if self.mounted {
+            println!("mount point: {}", self.mount_point);
+        } else {
+            println!("would mount at {}", self.mount_point);
+        }
+        if p.hibernated == Some(true) {
+            println!("{}", HIBERNATED_NOTE);
+        }
+        if let Some(user) = &self.username {
+            println!("user: {}", user);
+        }
+        for m in &self.mappings {
+            println!("  {} -> {}", m.windows_path, m.linux_path);
+        }
+        if let Some(block) = &self.block {
+            print!("{}", block);
+        }
+        match &self.applied {
+            Some(resp) => resp.print(),
+            None => println!("dry run: nothing was changed"),
+        }
+    }
+
+    fn success(&self) -> bool {
+        self.applied.as_ref().is_none_or(|r| r.success())
+    }
+}
+
+fn automap(
+    user: Option<String>,
+    mount_base: Option<String>,
+    driver: Option<String>,
+    partition: Option<String>,
+    dry_run: bool,
+) -> Result<AutoMap, LindyError> {
+    if let Some(name) = &driver {
+        mountopts::Driver::parse(name)
+            .ok_or_else(|| LindyError::invalid(format!("unknown filesystem driver: {}", name)))?;
+    }
+    let (partition, mount_point, username, mappings) = if dry_run {
+        let partitions = crate::detect_windows_partitions()?;
+        let partition = crate::best_windows_partition(&partitions, partition.as_deref())?.clone();
+        let Some(mount_point) = partition.mount_point.clone() else {
+            return Ok(AutoMap {
+                mount_point: crate::windows_mount_path(&partition, mount_base),
+                partition,
+                mounted: false,
+                username: user,
+                mappings: Vec::new(),
+                id: None,
+                block: None,
+                applied: None,
+            });
+        };
+        let username = match user {
+            Some(u) => u,
+            None => crate::check_for_windows_users(&mount_point)
+                .1
+                .into_iter()
+                .next()
+                .ok_or_else(|| LindyError::NoUsersDetected {
+                    message: "No Windows users detected; pass --user".into(),
+                    mount_point: mount_point.clone(),
+                    partition_info: Box::new(partition.clone()),
+                })?,
+        };
+        let mappings = crate::suggest_folder_mappings(&mount_point, Some(username.clone()))?;
+        (partition, mount_point, username, mappings)
+    } else {
+        match crate::auto_mount_and_map(mount_base, user, partition, None)? {
+            LindyResponse::AutoMapSuccess {
+                windows_partition,
+                mappings,
+                mount_point,
+                username,
+                ..
+            } => (*windows_partition, mount_point, username, mappings),
+            other => return Err(LindyError::io(other.message())),
+        }
+    };
+
+    let id = format!("automap_{}", crate::unix_now());
+    let fstab = FstabFile::read("/etc/fstab").unwrap_or_default();
+    let driver = crate::partition_driver(&partition.uuid, driver.as_deref())?;
+    let block = mapping_block(&id, &fstab, &partition, &mount_point, driver, &mappings);
+    let applied = if dry_run {
+        None
+    } else {
+        let targets = mappings.iter().map(|m| m.linux_path.clone()).collect();
+        Some(crate::apply_fstab_block(&block, &id, targets, None)?)
+    };
+    Ok(AutoMap {
+        partition,
+        mount_point,
+        mounted: true,
+        username: Some(username),
+        mappings,
+        id: Some(id),
+        block: Some(block),
+        applied,
+    })
+}
+
+// The block for a set of folder mappings. The partition itself gets a line
+// too unless /etc/fstab already mounts it, so the binds survive a reboot.
+fn mapping_block(
+    id: &str,
+    fstab: &FstabFile,
+    partition: &WindowsPartition,
+    mount_point: &str,
+    driver: Option<mountopts::Driver>,
+    mappings: &[FolderMapping],
+) -> String {
+    let spec = format!("UUID={}", partition.uuid);
+    let mut lines = vec![format!("{} {}", fstab::BEGIN_MARKER, id)];
+    if !fstab
+        .entries()
+        .any(|e| e.spec == spec || e.file == mount_point)
+    {
+        lines.push(crate::partition_line(
+            &partition.uuid,
+            mount_point,
+            driver,
+            None,
+        ));
+    }
+    for m in mappings {
+        lines.push(crate::bind_line(&m.windows_path, &m.linux_path, None));
+    }
+    lines.push(format!("{} {}", fstab::END_MARKER, id));
+    lines.join("\n") + "\n"
+}
+
+fn unlock(device: String, persist: Option<String>) -> Result<LindyResponse, LindyError> {
+    let persist = match persist.as_deref() {
+        None => None,
+        Some("prompt") => Some(Persist::Prompt),
+        Some("key-file") => Some(Persist::KeyFile),
+        Some(other) => {
+            return Err(LindyError::invalid(format!(
+                "--persist takes prompt or key-file, not {}",
+                other
+            )))
+        }
+    };
+    let key = read_key(&format!("Password or recovery key for {}: ", device))?;
+    crate::unlock_bitlocker(device, key, persist)
+}
+
+// One line from stdin, without echo when it is a terminal.
+fn read_key(prompt: &str) -> Result<String, LindyError> {
+    use std::io::{BufRead, IsTerminal};
+
+    let stdin = std::io::stdin();
+    let mut saved = None;
+    if stdin.is_terminal() {
+        eprint!("{}", prompt);
+        // SAFETY: termios is plain data filled in by tcgetattr.
+        unsafe {
+            let mut t: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut t) == 0 {
+                saved = Some(t);
+                t.c_lflag &= !libc::ECHO;
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &t);
+            }
+        }
+    }
+    let mut line = String::new();
+    let read = stdin.lock().read_line(&mut line);
+    if let Some(t) = saved {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &t) };
+        eprintln!();
+    }
+    read.map_err(|e| LindyError::io(format!("failed to read the key: {}", e)))?;
+    // only the line ending; spaces can be part of the password
+    let key = line.strip_suffix('\n').unwrap_or(&line);
+    Ok(key.strip_suffix('\r').unwrap_or(key).to_string())
+}
+
+// `file` holds either one lindy block or bare fstab lines, which are wrapped
+// in markers for `id` (or a generated id).
+fn apply(file: &str, id: Option<String>, dry_run: bool) -> Result<LindyResponse, LindyError> {
+    let text = std::fs::read_to_string(file)
+        .map_err(|e| LindyError::io(format!("failed to read {}: {}", file, e)))?;
+    let id = id
+        .or_else(|| {
+            FstabFile::parse(&text)
+                .blocks()
+                .first()
+                .map(|b| b.id.clone())
+        })
+        .unwrap_or_else(|| format!("cli_{}", crate::unix_now()));
+    let lines = fstab::block_lines(&id, &text).map_err(LindyError::invalid)?;
+    let block = FstabFile {
+        lines,
+        trailing_newline: true,
+    };
+    let targets = block
+        .entries()
+        .filter(|e| e.is_bind())
+        .map(|e| e.file.clone())
+        .collect();
+    crate::apply_fstab_block(&block.to_string(), &id, targets, Some(dry_run))
+}
+
+#[derive(Serialize)]
+struct ProfileOutcome {
+    #[serde(flatten)]
+    plan: ProfilePlan,
+    /// Validation of /etc/fstab with the block added (new blocks) or
+    /// rewritten in place (updates); empty when nothing changes.
+    diagnostics: Vec<Diagnostic>,
+    /// What applying the plan returned; None for a dry run.
+    applied: Option<LindyResponse>,
+}
+
+impl Report for ProfileOutcome {
+    fn print(&self) {
+        let what = match self.plan.action {
+            PlanAction::Create => "create",
+            PlanAction::Update => "update",
+            PlanAction::Unchanged => "unchanged",
+        };
+        println!("block {}: {}", self.plan.id, what);
+        for l in &self.plan.added {
+            println!("  + {}", l);
+        }
+        for l in &self.plan.removed {
+            println!("  - {}", l);
+        }
+        for d in &self.diagnostics {
+            let severity = serde_json::to_value(d.severity).unwrap_or_default();
+            println!("  {}: {}", severity.as_str().unwrap_or("?"), d.message);
+        }
+        match &self.applied {
+            Some(resp) => resp.print(),
+            None => println!("dry run: nothing was changed"),
+        }
+    }
+
+    fn success(&self) -> bool {
+        !validate::has_errors(&self.diagnostics)
+    }
+}
+
+fn apply_profile(file: &str, dry_run: bool) -> Result<ProfileOutcome, LindyError> {
+    let text = std::fs::read_to_string(file)
+        .map_err(|e| LindyError::io(format!("failed to read {}: {}", file, e)))?;
+    let profile =
+        Profile::parse(&text).map_err(|e| LindyError::invalid(format!("{}: {}", file, e)))?;
+    let partitions = match profile.partition.label {
+        Some(_) => crate::list_partitions()?,
+        None => Vec::new(),
+    };
+    let home =
+        std::env::var("HOME").map_err(|_| LindyError::not_found("Could not get HOME directory"))?;
+    let uuid_for_label = |label: &str| {
+        partitions
+            .iter()
+            .find(|p| p.label.as_deref() == Some(label))
+            .and_then(|p| p.uuid.clone())
+    };
+    let rendered = profile
+        .render(uuid_for_label, &home)
+        .map_err(|problems| LindyError::invalid(format!("{}: {}", file, problems.join("; "))))?;
+    let fstab = FstabFile::read("/etc/fstab")
+        .map_err(|e| LindyError::io(format!("failed reading /etc/fstab: {}", e)))?;
+    let plan = crate::profile::plan(&profile.id, rendered, &fstab);
+
+    let diagnostics = match plan.action {
+        PlanAction::Create => crate::validate_fstab_block(&plan.block)?,
+        PlanAction::Update => crate::check_update(&fstab, &plan.id, &plan.block)?,
+        PlanAction::Unchanged => Vec::new(),
+    };
+    if dry_run {
+        return Ok(ProfileOutcome {
+            plan,
+            diagnostics,
+            applied: None,
+        });
+    }
+    if validate::has_errors(&diagnostics) {
+        return Err(crate::validation_failed(diagnostics));
+    }
+
+    let id = &plan.id;
+    let applied = match plan.action {
+        PlanAction::Create => crate::perform_mounts(
+            &plan.block,
+            id,
+            plan.targets.clone(),
+            None,
+            None,
+            false,
+            None,
+            None,
+            None,
+        )?,
+        PlanAction::Update => crate::update_fstab_block(id, &plan.block)?,
+        // the block is there; make sure the app manages it too
+        PlanAction::Unchanged if MetadataStore::open().load(id).is_none() => {
+            crate::adopt_block(id)?
+        }
+        PlanAction::Unchanged => LindyResponse::Unchanged {
+            message: format!("block {} already matches the profile", id),
+            id: id.clone(),
+        },
+    };
+    Ok(ProfileOutcome {
+        plan,
+        diagnostics,
+        applied: Some(applied),
+    })
+}
+
+#[derive(Serialize)]
+struct Status {
+    healthy: bool,
+    blocks: Vec<FstabBlock>,
+    /// Ids where /etc/fstab and the metadata store disagree.
+    inconsistent: Vec<ConsistencyEntry>,
+}
+
+impl Report for Status {
+    fn print(&self) {
+        let binds: Vec<_> = self.blocks.iter().flat_map(|b| &b.binds).collect();
+        let mounted = binds
+            .iter()
+            .filter(|b| b.mount == Some(BindState::Mounted))
+            .count();
+        println!("{} of {} binds mounted", mounted, binds.len());
+        for b in binds.iter().filter(|b| b.mount != Some(BindState::Mounted)) {
+            println!("  {}: {}", b.target, state_label(b.mount.as_ref()));
+        }
+        for e in &self.inconsistent {
+            let what = match e.state {
+                ConsistencyState::FstabOnly => "in /etc/fstab without metadata",
+                ConsistencyState::MetadataOnly => "in metadata but not in /etc/fstab",
+                _ => "differs between /etc/fstab and metadata",
+            };
+            println!("  block {} {}", e.id, what);
+        }
+    }
+
+    fn success(&self) -> bool {
+        self.healthy
+    }
+}
+
+fn status() -> Result<Status, LindyError> {
+    let blocks = crate::list_fstab_blocks()?;
+    let inconsistent: Vec<_> = crate::check_consistency()?
+        .into_iter()
+        .filter(|e| e.state != ConsistencyState::InSync)
+        .collect();
+    let healthy = inconsistent.is_empty()
+        && blocks
+            .iter()
+            .flat_map(|b| &b.binds)
+            .all(|b| b.mount == Some(BindState::Mounted));
+    Ok(Status {
+        healthy,
+        blocks,
+        inconsistent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn parses_commands_and_options() {
+        assert_eq!(parse(&args("list")), Ok((Command::List, false)));
+        assert_eq!(
+            parse(&args(
+                "--json automap --user=Ann --driver ntfs-3g --partition AAAA --dry-run"
+            )),
+            Ok((
+                Command::AutoMap {
+                    user: Some("Ann".into()),
+                    mount_base: None,
+                    driver: Some("ntfs-3g".into()),
+                    partition: Some("AAAA".into()),
+                    dry_run: true,
+                },
+                true
+            ))
+        );
+        assert_eq!(
+            parse(&args("remove abc --force --json")),
+            Ok((
+                Command::Remove {
+                    id: "abc".into(),
+                    force: true,
+                    dry_run: false,
+                },
+                true
+            ))
+        );
+        assert_eq!(
+            parse(&args("apply block.txt --id lab --dry-run")),
+            Ok((
+                Command::Apply {
+                    file: "block.txt".into(),
+                    id: Some("lab".into()),
+                    dry_run: true,
+                },
+                false
+            ))
+        );
+        assert_eq!(
+            parse(&args("apply-profile lab.toml --dry-run")),
+            Ok((
+                Command::ApplyProfile {
+                    file: "lab.toml".into(),
+                    dry_run: true,
+                },
+                false
+            ))
+        );
+        assert_eq!(
+            parse(&args("unlock /dev/nvme0n1p3 --persist prompt")),
+            Ok((
+                Command::Unlock {
+                    device: "/dev/nvme0n1p3".into(),
+                    persist: Some("prompt".into()),
+                },
+                false
+            ))
+        );
+        assert_eq!(
+            parse(&args("import mappings.json")),
+            Ok((
+                Command::Import {
+                    file: "mappings.json".into(),
+                },
+                false
+            ))
+        );
+        assert!(parse(&args("remove")).is_err());
+        assert!(parse(&args("import")).is_err());
+        assert!(parse(&args("list extra")).is_err());
+        assert!(parse(&args("automap --user")).is_err());
+        assert!(parse(&args("list --verbose")).is_err());
+        assert!(parse(&args("frobnicate")).is_err());
+    }
+}
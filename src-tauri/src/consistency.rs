@@ -0,0 +1,274 @@
+// Agreement between the lindy blocks in /etc/fstab and the metadata store.
+//
+// The two drift apart when persisting fails after metadata was written, when
+// a block is edited or removed outside the app, or when metadata is lost.
+// `check` classifies every id found on either side and `plan` turns a chosen
+// resolution into the single step that brings that id back in sync.
+
+use crate::fstab::{FstabFile, LindyBlock};
+use crate::helper::Request;
+use crate::metadata::BlockMetadata;
+use crate::response::LindyError;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsistencyState {
+    /// Block and metadata describe the same entries.
+    InSync,
+    /// Block without metadata; it can be adopted.
+    FstabOnly,
+    /// Metadata without a block (an orphan).
+    MetadataOnly,
+    /// Both exist but their entries differ.
+    Divergent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+#[ts(export)]
+pub struct ConsistencyEntry {
+    pub id: String,
+    pub state: ConsistencyState,
+    /// Block text in /etc/fstab, when present.
+    pub fstab_block: Option<String>,
+    /// Block text recorded in metadata, when present.
+    pub metadata_block: Option<String>,
+    /// Whether metadata says the block was persisted.
+    pub persisted: Option<bool>,
+    /// Resolutions `plan` accepts for this state.
+    pub resolutions: Vec<Resolution>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum Resolution {
+    /// Write metadata from the block in /etc/fstab.
+    Adopt,
+    /// Delete the metadata record.
+    DropMetadata,
+    /// Remove the block from /etc/fstab (privileged).
+    RemoveBlock,
+    /// Write the block recorded in metadata to /etc/fstab (privileged).
+    Reapply,
+}
+
+impl ConsistencyState {
+    pub fn resolutions(self) -> Vec<Resolution> {
+        use Resolution::*;
+        match self {
+            ConsistencyState::InSync => vec![],
+            ConsistencyState::FstabOnly => vec![Adopt, RemoveBlock],
+            ConsistencyState::MetadataOnly => vec![Reapply, DropMetadata],
+            ConsistencyState::Divergent => vec![Adopt, Reapply, RemoveBlock],
+        }
+    }
+}
+
+/// A resolution chosen for one id.
+#[derive(Debug, Clone, Deserialize, TS)]
+#[ts(export)]
+pub struct RepairAction {
+    pub id: String,
+    pub resolution: Resolution,
+}
+
+/// How applying a `RepairAction` went.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct RepairOutcome {
+    pub id: String,
+    pub resolution: Resolution,
+    pub message: String,
+    /// Why the action failed; None when it was applied.
+    pub error: Option<LindyError>,
+}
+
+/// What carrying out a resolution takes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    SaveMetadata(BlockMetadata),
+    DeleteMetadata,
+    /// Write the recorded block back to /etc/fstab, checked and validated
+    /// like any new block (`apply_fstab_block`).
+    Apply {
+        block: String,
+        targets: Vec<String>,
+    },
+    /// Rewrite the block in /etc/fstab to the recorded text, validated like
+    /// any update (`update_fstab_block`).
+    Update {
+        block: String,
+    },
+    /// Run a helper request; the metadata of a removed block is dropped.
+    Privileged(Request),
+}
+
+fn same_entries(block: &LindyBlock, recorded: &str) -> bool {
+    let recorded = FstabFile::parse(recorded);
+    block.entries().eq(recorded.entries())
+}
+
+/// Classify every id that appears in `fstab` or in `records`, ordered by id.
+pub fn check(fstab: &FstabFile, records: &[BlockMetadata]) -> Vec<ConsistencyEntry> {
+    let blocks = fstab.blocks();
+    let mut ids: Vec<&str> = blocks
+        .iter()
+        .map(|b| b.id.as_str())
+        .chain(records.iter().map(|m| m.id.as_str()))
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    ids.into_iter()
+        .map(|id| {
+            let block = blocks.iter().find(|b| b.id == id);
+            let meta = records.iter().find(|m| m.id == id);
+            let state = match (block, meta) {
+                (Some(b), Some(m)) if same_entries(b, &m.block) => ConsistencyState::InSync,
+                (Some(_), Some(_)) => ConsistencyState::Divergent,
+                (Some(_), None) => ConsistencyState::FstabOnly,
+                (None, _) => ConsistencyState::MetadataOnly,
+            };
+            ConsistencyEntry {
+                id: id.to_string(),
+                state,
+                fstab_block: block.map(|b| b.text() + "\n"),
+                metadata_block: meta.map(|m| m.block.clone()),
+                persisted: meta.map(|m| m.persisted),
+                resolutions: state.resolutions(),
+            }
+        })
+        .collect()
+}
+
+/// The step that applies `resolution` to `entry`, or why it does not apply.
+pub fn plan(
+    entry: &ConsistencyEntry,
+    resolution: Resolution,
+    fstab: &FstabFile,
+    now: u64,
+) -> Result<Step, String> {
+    if !entry.resolutions.contains(&resolution) {
+        return Err(format!(
+            "{:?} does not apply to {} ({:?})",
+            resolution, entry.id, entry.state
+        ));
+    }
+    let id = entry.id.clone();
+    Ok(match resolution {
+        Resolution::Adopt => {
+            let block = fstab
+                .block(&id)
+                .ok_or_else(|| format!("block {} is not in /etc/fstab", id))?;
+            let mut meta = BlockMetadata::new(&id, &(block.text() + "\n"), block.targets(), now);
+            meta.persisted = true;
+            meta.persisted_at = Some(now);
+            Step::SaveMetadata(meta)
+        }
+        Resolution::DropMetadata => Step::DeleteMetadata,
+        Resolution::RemoveBlock => Step::Privileged(Request::RemoveBlock {
+            id: Some(id),
+            target: None,
+            force: false,
+        }),
+        Resolution::Reapply => {
+            let block = entry.metadata_block.clone().unwrap_or_default();
+            let targets: Vec<String> = FstabFile::parse(&block)
+                .entries()
+                .filter(|e| e.is_bind())
+                .map(|e| e.file.clone())
+                .collect();
+            if entry.state == ConsistencyState::Divergent {
+                Step::Update { block }
+            } else {
+                Step::Apply { block, targets }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(id: &str, block: &str) -> BlockMetadata {
+        BlockMetadata::new(id, block, vec![], 1)
+    }
+
+    const FSTAB: &str = "UUID=root / ext4 defaults 0 1\n\
+        # lindy BEGIN: same\n/a /b none bind 0 0\n# lindy END: same\n\
+        # lindy BEGIN: edited\n/c /d none bind 0 0\n# lindy END: edited\n\
+        # lindy BEGIN: bare\n/e /f none bind 0 0\n# lindy END: bare\n";
+
+    #[test]
+    fn classifies_each_id() {
+        let fstab = FstabFile::parse(FSTAB);
+        let records = vec![
+            // whitespace differences do not count
+            meta(
+                "same",
+                "# lindy BEGIN: same\n/a   /b  none bind\n# lindy END: same\n",
+            ),
+            meta(
+                "edited",
+                "# lindy BEGIN: edited\n/c /x none bind 0 0\n# lindy END: edited\n",
+            ),
+            meta(
+                "gone",
+                "# lindy BEGIN: gone\n/g /h none bind 0 0\n# lindy END: gone\n",
+            ),
+        ];
+        let states: Vec<(String, ConsistencyState)> = check(&fstab, &records)
+            .into_iter()
+            .map(|e| (e.id, e.state))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                ("bare".into(), ConsistencyState::FstabOnly),
+                ("edited".into(), ConsistencyState::Divergent),
+                ("gone".into(), ConsistencyState::MetadataOnly),
+                ("same".into(), ConsistencyState::InSync),
+            ]
+        );
+    }
+
+    #[test]
+    fn plans_only_applicable_resolutions() {
+        let fstab = FstabFile::parse(FSTAB);
+        let records = vec![
+            meta(
+                "edited",
+                "# lindy BEGIN: edited\n/c /x none bind 0 0\n# lindy END: edited\n",
+            ),
+            meta(
+                "gone",
+                "# lindy BEGIN: gone\n/g /h none bind 0 0\n# lindy END: gone\n",
+            ),
+        ];
+        let entries = check(&fstab, &records);
+        let entry = |id: &str| entries.iter().find(|e| e.id == id).unwrap();
+
+        match plan(entry("bare"), Resolution::Adopt, &fstab, 9).unwrap() {
+            Step::SaveMetadata(m) => {
+                assert_eq!(m.targets, vec!["/f".to_string()]);
+                assert!(m.persisted);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(plan(entry("bare"), Resolution::DropMetadata, &fstab, 9).is_err());
+        assert_eq!(
+            plan(entry("gone"), Resolution::Reapply, &fstab, 9).unwrap(),
+            Step::Apply {
+                block: "# lindy BEGIN: gone\n/g /h none bind 0 0\n# lindy END: gone\n".into(),
+                targets: vec!["/h".into()],
+            }
+        );
+        assert!(matches!(
+            plan(entry("edited"), Resolution::Reapply, &fstab, 9).unwrap(),
+            Step::Update { .. }
+        ));
+    }
+}
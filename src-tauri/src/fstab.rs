@@ -0,0 +1,496 @@
+// Typed model of /etc/fstab.
+//
+// Every line is kept verbatim next to its parsed form so that serializing an
+// unmodified `FstabFile` reproduces the input byte-for-byte (comments, blank
+// lines and column alignment included). Entry fields are decoded, so a target
+// written as `/home/me/My\040Files` compares equal to `/home/me/My Files`.
+
+use std::fmt;
+
+pub const BEGIN_MARKER: &str = "# lindy BEGIN:";
+pub const END_MARKER: &str = "# lindy END:";
+
+/// Mount propagation flags that may accompany a bind option.
+pub const PROPAGATION_FLAGS: [&str; 8] = [
+    "shared",
+    "slave",
+    "private",
+    "unbindable",
+    "rshared",
+    "rslave",
+    "rprivate",
+    "runbindable",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, ts_rs::TS)]
+#[serde(rename_all = "lowercase")]
+pub enum BindKind {
+    Bind,
+    Rbind,
+}
+
+/// One mount entry: `<spec> <file> <vfstype> <mntops> <freq> <passno>`.
+/// Missing trailing fields take the defaults used by mount(8).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FstabEntry {
+    pub spec: String,
+    pub file: String,
+    pub vfstype: String,
+    pub mntops: String,
+    pub freq: u32,
+    pub passno: u32,
+}
+
+impl FstabEntry {
+    /// Parse a non-comment fstab line. Returns None when the line does not
+    /// have at least a spec and a mountpoint or a numeric column is invalid.
+    pub fn parse(line: &str) -> Option<FstabEntry> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 2 {
+            return None;
+        }
+        let freq = match fields.get(4) {
+            Some(f) => f.parse().ok()?,
+            None => 0,
+        };
+        let passno = match fields.get(5) {
+            Some(p) => p.parse().ok()?,
+            None => 0,
+        };
+        Some(FstabEntry {
+            spec: decode_field(fields[0]),
+            file: decode_field(fields[1]),
+            vfstype: fields
+                .get(2)
+                .map(|s| decode_field(s))
+                .unwrap_or_else(|| "auto".into()),
+            mntops: fields
+                .get(3)
+                .map(|s| decode_field(s))
+                .unwrap_or_else(|| "defaults".into()),
+            freq,
+            passno,
+        })
+    }
+
+    /// Individual mount options in declaration order.
+    pub fn options(&self) -> impl Iterator<Item = &str> {
+        self.mntops.split(',').filter(|o| !o.is_empty())
+    }
+
+    /// The bind flavour of this entry, if any. The last of `bind`/`rbind`
+    /// wins, matching how mount(8) applies repeated options.
+    pub fn bind_kind(&self) -> Option<BindKind> {
+        self.options()
+            .filter_map(|o| match o {
+                "bind" => Some(BindKind::Bind),
+                "rbind" => Some(BindKind::Rbind),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Whether this entry describes a bind mount (`bind` or `rbind` anywhere
+    /// in the option list).
+    pub fn is_bind(&self) -> bool {
+        self.bind_kind().is_some()
+    }
+
+    /// The propagation flag requested for this mount, if any.
+    pub fn propagation(&self) -> Option<&str> {
+        self.options()
+            .filter(|o| PROPAGATION_FLAGS.contains(o))
+            .last()
+    }
+}
+
+/// Classification of a single line of an fstab file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineKind {
+    Blank,
+    Comment,
+    BlockBegin(String),
+    BlockEnd(String),
+    Entry(FstabEntry),
+    Malformed,
+}
+
+/// A line of the file: the original text (without the trailing newline)
+/// together with its parsed kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FstabLine {
+    pub raw: String,
+    pub kind: LineKind,
+}
+
+impl FstabLine {
+    pub fn parse(raw: &str) -> FstabLine {
+        let trimmed = raw.trim();
+        let kind = if trimmed.is_empty() {
+            LineKind::Blank
+        } else if let Some(id) = trimmed.strip_prefix(BEGIN_MARKER) {
+            LineKind::BlockBegin(id.trim().to_string())
+        } else if let Some(id) = trimmed.strip_prefix(END_MARKER) {
+            LineKind::BlockEnd(id.trim().to_string())
+        } else if trimmed.starts_with('#') {
+            LineKind::Comment
+        } else {
+            match FstabEntry::parse(trimmed) {
+                Some(e) => LineKind::Entry(e),
+                None => LineKind::Malformed,
+            }
+        };
+        FstabLine {
+            raw: raw.to_string(),
+            kind,
+        }
+    }
+
+    pub fn entry(&self) -> Option<&FstabEntry> {
+        match &self.kind {
+            LineKind::Entry(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A `# lindy BEGIN: <id>` ... `# lindy END: <id>` span. `start` is the index
+/// of the BEGIN line and `end` is one past the last line of the block; a block
+/// without an END marker runs to the next BEGIN marker or to the end of file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LindyBlock {
+    pub id: String,
+    pub start: usize,
+    pub end: usize,
+    pub terminated: bool,
+    pub lines: Vec<FstabLine>,
+}
+
+impl LindyBlock {
+    /// Block text exactly as it appears in the file, without a trailing newline.
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|l| l.raw.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &FstabEntry> {
+        self.lines.iter().filter_map(|l| l.entry())
+    }
+
+    pub fn binds(&self) -> impl Iterator<Item = &FstabEntry> {
+        self.entries().filter(|e| e.is_bind())
+    }
+
+    /// Mountpoints of the bind entries in this block.
+    pub fn targets(&self) -> Vec<String> {
+        self.binds().map(|e| e.file.clone()).collect()
+    }
+}
+
+/// A whole fstab file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FstabFile {
+    pub lines: Vec<FstabLine>,
+    pub trailing_newline: bool,
+}
+
+impl FstabFile {
+    pub fn parse(text: &str) -> FstabFile {
+        let trailing_newline = text.ends_with('\n');
+        let body = text.strip_suffix('\n').unwrap_or(text);
+        let lines = if text.is_empty() {
+            Vec::new()
+        } else {
+            body.split('\n').map(FstabLine::parse).collect()
+        };
+        FstabFile {
+            lines,
+            trailing_newline,
+        }
+    }
+
+    pub fn read(path: &str) -> std::io::Result<FstabFile> {
+        Ok(FstabFile::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// All mount entries, inside and outside lindy blocks.
+    pub fn entries(&self) -> impl Iterator<Item = &FstabEntry> {
+        self.lines.iter().filter_map(|l| l.entry())
+    }
+
+    /// All lindy blocks in file order.
+    pub fn blocks(&self) -> Vec<LindyBlock> {
+        let mut blocks = Vec::new();
+        let mut open: Option<(String, usize)> = None;
+        for (i, line) in self.lines.iter().enumerate() {
+            match &line.kind {
+                LineKind::BlockBegin(id) => {
+                    if let Some((prev, start)) = open.take() {
+                        blocks.push(self.span(prev, start, i, false));
+                    }
+                    open = Some((id.clone(), i));
+                }
+                LineKind::BlockEnd(_) => {
+                    if let Some((id, start)) = open.take() {
+                        blocks.push(self.span(id, start, i + 1, true));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some((id, start)) = open {
+            blocks.push(self.span(id, start, self.lines.len(), false));
+        }
+        blocks
+    }
+
+    fn span(&self, id: String, start: usize, end: usize, terminated: bool) -> LindyBlock {
+        LindyBlock {
+            id,
+            start,
+            end,
+            terminated,
+            lines: self.lines[start..end].to_vec(),
+        }
+    }
+
+    /// The first block with the given id.
+    pub fn block(&self, id: &str) -> Option<LindyBlock> {
+        self.blocks().into_iter().find(|b| b.id == id)
+    }
+
+    /// The block that declares a bind mount onto `target`.
+    pub fn block_for_target(&self, target: &str) -> Option<LindyBlock> {
+        self.blocks()
+            .into_iter()
+            .find(|b| b.binds().any(|e| e.file == target))
+    }
+
+    /// Replace the first block with the given id by `lines`, keeping every
+    /// other line where it was. Returns the block that was replaced.
+    pub fn replace_block(&mut self, id: &str, lines: Vec<FstabLine>) -> Option<LindyBlock> {
+        let block = self.block(id)?;
+        self.lines.splice(block.start..block.end, lines);
+        Some(block)
+    }
+
+    /// Remove the first block with the given id, returning it.
+    pub fn remove_block(&mut self, id: &str) -> Option<LindyBlock> {
+        let block = self.block(id)?;
+        self.lines.drain(block.start..block.end);
+        Some(block)
+    }
+}
+
+/// Parse the text of a single lindy block for `id`. Text without markers is
+/// wrapped in BEGIN/END lines; text with markers must consist of exactly one
+/// terminated block carrying `id` (surrounding blank lines are dropped).
+pub fn block_lines(id: &str, text: &str) -> Result<Vec<FstabLine>, String> {
+    let mut lines = FstabFile::parse(text).lines;
+    while lines.last().is_some_and(|l| l.kind == LineKind::Blank) {
+        lines.pop();
+    }
+    let first = lines
+        .iter()
+        .position(|l| l.kind != LineKind::Blank)
+        .ok_or_else(|| "empty block".to_string())?;
+    lines.drain(..first);
+
+    let file = FstabFile {
+        lines,
+        trailing_newline: false,
+    };
+    let blocks = file.blocks();
+    match blocks.as_slice() {
+        [] => {
+            let mut out = vec![FstabLine::parse(&format!("{} {}", BEGIN_MARKER, id))];
+            out.extend(file.lines);
+            out.push(FstabLine::parse(&format!("{} {}", END_MARKER, id)));
+            Ok(out)
+        }
+        [b] if b.id == id && b.terminated && b.start == 0 && b.end == file.lines.len() => {
+            Ok(file.lines)
+        }
+        _ => Err(format!(
+            "block text must contain exactly one lindy block with id {}",
+            id
+        )),
+    }
+}
+
+/// The entry as one fstab line, fields encoded and separated by single spaces.
+impl fmt::Display for FstabEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            encode_field(&self.spec),
+            encode_field(&self.file),
+            encode_field(&self.vfstype),
+            encode_field(&self.mntops),
+            self.freq,
+            self.passno
+        )
+    }
+}
+
+impl fmt::Display for FstabFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            f.write_str(&line.raw)?;
+        }
+        if self.trailing_newline {
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode the octal escapes (`\040`, `\011`, `\012`, `\134`) fstab uses for
+/// characters that would otherwise split a field.
+pub fn decode_field(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && i + 3 < bytes.len()
+            && bytes[i + 1..i + 4]
+                .iter()
+                .all(|b| (b'0'..=b'7').contains(b))
+        {
+            let v = (bytes[i + 1] - b'0') as u32 * 64
+                + (bytes[i + 2] - b'0') as u32 * 8
+                + (bytes[i + 3] - b'0') as u32;
+            if v <= 0xff {
+                out.push(v as u8);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Encode a value for use as an fstab field (inverse of `decode_field`).
+pub fn encode_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            ' ' => out.push_str("\\040"),
+            '\t' => out.push_str("\\011"),
+            '\n' => out.push_str("\\012"),
+            '\\' => out.push_str("\\134"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "# /etc/fstab\n\
+UUID=abcd /               ext4    errors=remount-ro 0       1\n\
+\n\
+# lindy BEGIN: m1\n\
+UUID=1234 /mnt/win auto defaults,nofail 0 2\n\
+/mnt/win/Users/Me/My\\040Docs /home/me/My\\040Docs none bind 0 0\n\
+# lindy END: m1\n\
+tmpfs /tmp tmpfs defaults\n";
+
+    #[test]
+    fn round_trips_unmodified_text() {
+        let f = FstabFile::parse(SAMPLE);
+        assert_eq!(f.to_string(), SAMPLE);
+        let no_nl = SAMPLE.trim_end_matches('\n');
+        assert_eq!(FstabFile::parse(no_nl).to_string(), no_nl);
+        assert_eq!(FstabFile::parse("").to_string(), "");
+    }
+
+    #[test]
+    fn decodes_octal_escapes() {
+        assert_eq!(decode_field("/a\\040b\\011c\\134"), "/a b\tc\\");
+        assert_eq!(decode_field("/trailing\\04"), "/trailing\\04");
+        assert_eq!(encode_field("/a b\\c"), "/a\\040b\\134c");
+        assert_eq!(decode_field(&encode_field("/x y\tz")), "/x y\tz");
+        let e = FstabEntry::parse("/a\\040b   /c none bind").unwrap();
+        assert_eq!(e.to_string(), "/a\\040b /c none bind 0 0");
+    }
+
+    #[test]
+    fn finds_blocks_and_targets() {
+        let f = FstabFile::parse(SAMPLE);
+        let blocks = f.blocks();
+        assert_eq!(blocks.len(), 1);
+        let b = &blocks[0];
+        assert_eq!(b.id, "m1");
+        assert!(b.terminated);
+        assert_eq!((b.start, b.end), (3, 7));
+        assert_eq!(b.targets(), vec!["/home/me/My Docs".to_string()]);
+        assert_eq!(
+            f.block_for_target("/home/me/My Docs").map(|b| b.id),
+            Some("m1".to_string())
+        );
+    }
+
+    #[test]
+    fn classifies_bind_options_in_any_position() {
+        let bind = |l: &str| FstabEntry::parse(l).unwrap().bind_kind();
+        assert_eq!(bind("/s /d none bind 0 0"), Some(BindKind::Bind));
+        assert_eq!(bind("/s /d none defaults,bind 0 0"), Some(BindKind::Bind));
+        assert_eq!(bind("/s /d auto bind"), Some(BindKind::Bind));
+        assert_eq!(bind("/s /d none x-gvfs-hide,bind"), Some(BindKind::Bind));
+        assert_eq!(bind("/s /d none rbind,rslave 0 0"), Some(BindKind::Rbind));
+        assert_eq!(bind("/s /d none bindfs 0 0"), None);
+        assert_eq!(bind("UUID=1 /mnt auto defaults,nofail 0 2"), None);
+        let e = FstabEntry::parse("/s /d none rbind,rslave 0 0").unwrap();
+        assert_eq!(e.propagation(), Some("rslave"));
+    }
+
+    #[test]
+    fn replaces_block_in_place() {
+        let mut f = FstabFile::parse(SAMPLE);
+        let lines = block_lines("m1", "/srv/a /home/me/a none bind 0 0\n").unwrap();
+        assert!(f.replace_block("m1", lines).is_some());
+        assert_eq!(
+            f.to_string(),
+            "# /etc/fstab\n\
+UUID=abcd /               ext4    errors=remount-ro 0       1\n\
+\n\
+# lindy BEGIN: m1\n\
+/srv/a /home/me/a none bind 0 0\n\
+# lindy END: m1\n\
+tmpfs /tmp tmpfs defaults\n"
+        );
+        assert!(block_lines(
+            "m1",
+            "# lindy BEGIN: other\n/a /b none bind\n# lindy END: other"
+        )
+        .is_err());
+        assert!(block_lines("m1", "\n\n").is_err());
+    }
+
+    #[test]
+    fn removes_block_and_keeps_surroundings() {
+        let mut f = FstabFile::parse(SAMPLE);
+        assert!(f.remove_block("m1").is_some());
+        assert_eq!(
+            f.to_string(),
+            "# /etc/fstab\n\
+UUID=abcd /               ext4    errors=remount-ro 0       1\n\
+\n\
+tmpfs /tmp tmpfs defaults\n"
+        );
+        assert!(f.remove_block("m1").is_none());
+    }
+}
@@ -0,0 +1,1636 @@
+// Privileged helper.
+//
+// The app never hands a shell script to pkexec. Every privileged operation is
+// a typed `Request`, written as JSON to the stdin of `pkexec lindy-helper`
+// (src/bin/lindy-helper.rs). The helper performs it with the code below and
+// prints exactly one `Response` as JSON on stdout. Paths and mount operations
+// are injected through `Helper` and `Mounter` so the same logic runs in unit
+// tests against a temp directory without root.
+
+use crate::backups::{self, RetentionPolicy};
+use crate::fstab::{self, FstabFile};
+use crate::metadata;
+use crate::ntfs::{self, SystemMarkers, VolumeState};
+use crate::validate::Candidate;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Seconds after `now` tried for a backup name before giving up.
+const BACKUP_NAME_TRIES: u64 = 100;
+
+/// One privileged operation.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    /// Append a marked block to /etc/fstab and run `mount -a`. When
+    /// activation fails, `fuser` output for `targets` is logged and, with
+    /// `lazy_retry`, the targets are lazily unmounted and `mount -a` retried.
+    ApplyBlock {
+        id: String,
+        block: String,
+        #[serde(default)]
+        targets: Vec<String>,
+        #[serde(default)]
+        lazy_retry: bool,
+    },
+    /// Rewrite block `id` in place; see `plan_update`.
+    UpdateBlock { id: String, block: String },
+    /// Unmount and remove a block, chosen by id or by one of its bind targets.
+    RemoveBlock {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        target: Option<String>,
+        #[serde(default)]
+        force: bool,
+    },
+    /// Replace /etc/fstab with the backup taken at `ts`.
+    RestoreBackup { ts: u64 },
+    /// Delete the backups outside the retention policy.
+    PruneBackups { keep_last: usize, max_age_days: u64 },
+    /// Mount the filesystem with `uuid` on `target`, creating it if needed.
+    Mount { uuid: String, target: String },
+    Unmount {
+        target: String,
+        #[serde(default)]
+        lazy: bool,
+    },
+    /// Open the BitLocker volume on `device` as /dev/mapper/`name` with
+    /// `cryptsetup open --type bitlk`; `key` is its password or recovery
+    /// key. With `persist` a crypttab line opens it again at boot.
+    Unlock {
+        device: String,
+        name: String,
+        key: String,
+        #[serde(default)]
+        persist: Option<Persist>,
+    },
+    /// Look for the Windows system folders on the NTFS volumes with these
+    /// UUIDs without mounting them (see ntfs.rs).
+    Inspect { uuids: Vec<String> },
+}
+
+/// How a BitLocker volume unlocked by lindy is opened at boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Persist {
+    /// systemd asks for the password while booting.
+    Prompt,
+    /// The key is stored in /etc/cryptsetup-keys.d/<name>.key (root only).
+    KeyFile,
+}
+
+impl Request {
+    /// The request with secrets blanked, for logging.
+    pub fn redacted(&self) -> Request {
+        let mut r = self.clone();
+        if let Request::Unlock { key, .. } = &mut r {
+            *key = "<redacted>".into();
+        }
+        r
+    }
+}
+
+/// Result of a request. `code` names the outcome (`applied`, `busy`,
+/// `rolled_back`, ...); the optional fields are set by the operations they
+/// apply to.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    pub code: String,
+    pub message: String,
+    /// Steps taken and output of the commands run, in order.
+    #[serde(default)]
+    pub log: Vec<String>,
+    /// Backup of /etc/fstab taken before it was replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup: Option<String>,
+    /// Why a change was rolled back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Whether the rollback managed to restore the backup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored: Option<bool>,
+    /// Id of the block acted on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Text of the block as written to /etc/fstab.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    /// Backups deleted by the retention policy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pruned: Vec<String>,
+    /// Mapper device opened by `Unlock`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// What `Inspect` found, by UUID; volumes that could not be read are
+    /// left out.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub markers: BTreeMap<String, SystemMarkers>,
+}
+
+impl Response {
+    pub fn ok(code: &str, message: impl Into<String>) -> Response {
+        Response {
+            ok: true,
+            code: code.to_string(),
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn error(code: &str, message: impl Into<String>) -> Response {
+        Response {
+            ok: false,
+            code: code.to_string(),
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Why a request failed; becomes the error `Response`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub code: &'static str,
+    pub message: String,
+    pub reason: Option<String>,
+    pub restored: Option<bool>,
+    pub backup: Option<String>,
+}
+
+impl Failure {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Failure {
+        Failure {
+            code,
+            message: message.into(),
+            reason: None,
+            restored: None,
+            backup: None,
+        }
+    }
+}
+
+impl From<Failure> for Response {
+    fn from(f: Failure) -> Response {
+        Response {
+            reason: f.reason,
+            restored: f.restored,
+            backup: f.backup,
+            ..Response::error(f.code, f.message)
+        }
+    }
+}
+
+/// Mount operations, so tests can run the helper without root.
+pub trait Mounter {
+    /// `mount -a`; the error carries the command output.
+    fn mount_all(&mut self) -> Result<(), String>;
+    fn mount_uuid(&mut self, uuid: &str, target: &str, read_only: bool) -> Result<(), String>;
+    /// `mount <target>`, as /etc/fstab declares it.
+    fn mount_target(&mut self, target: &str) -> Result<(), String>;
+    fn umount(&mut self, target: &str, lazy: bool) -> Result<(), String>;
+    fn is_mounted(&self, target: &str) -> bool;
+    /// Processes keeping `target` busy (`fuser -mv` output).
+    fn holders(&self, target: &str) -> String;
+    /// Hibernation state of the NTFS volume with `uuid`; None when it is
+    /// not NTFS or cannot be read.
+    fn volume_state(&self, uuid: &str) -> Option<VolumeState>;
+    /// Windows system folders on the NTFS volume with `uuid`; None when it
+    /// is not NTFS or cannot be read.
+    fn system_markers(&self, uuid: &str) -> Option<SystemMarkers>;
+    /// Value of a blkid tag (`TYPE`, `UUID`) of `device`.
+    fn blkid(&self, device: &str, tag: &str) -> Option<String>;
+    /// `cryptsetup open --type bitlk` with `key` on stdin.
+    fn open_bitlk(&mut self, device: &str, name: &str, key: &str) -> Result<(), String>;
+    /// Whether `key` opens `device`, without opening it
+    /// (`cryptsetup open --test-passphrase`).
+    fn test_bitlk(&mut self, device: &str, key: &str) -> Result<(), String>;
+    /// Whether /dev/mapper/`name` exists.
+    fn mapper_exists(&self, name: &str) -> bool;
+}
+
+/// The real mount(8)/umount(8) tools.
+pub struct System;
+
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    run_with_input(program, args, None)
+}
+
+// `run` with `input` written to the program's stdin.
+fn run_with_input(program: &str, args: &[&str], input: Option<&[u8]>) -> Result<String, String> {
+    use std::process::Stdio;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run {}: {}", program, e))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        let _ = stdin.write_all(input);
+    }
+    let out = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run {}: {}", program, e))?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&out.stdout),
+        String::from_utf8_lossy(&out.stderr)
+    );
+    if out.status.success() {
+        Ok(text)
+    } else {
+        Err(format!(
+            "{} exited with {:?}: {}",
+            program,
+            out.status.code(),
+            text.trim()
+        ))
+    }
+}
+
+impl Mounter for System {
+    fn mount_all(&mut self) -> Result<(), String> {
+        run("mount", &["-a"]).map(|_| ())
+    }
+
+    fn mount_uuid(&mut self, uuid: &str, target: &str, read_only: bool) -> Result<(), String> {
+        if read_only {
+            run("mount", &["-U", uuid, "-o", "ro", target]).map(|_| ())
+        } else {
+            run("mount", &["-U", uuid, target]).map(|_| ())
+        }
+    }
+
+    fn mount_target(&mut self, target: &str) -> Result<(), String> {
+        run("mount", &[target]).map(|_| ())
+    }
+
+    fn umount(&mut self, target: &str, lazy: bool) -> Result<(), String> {
+        if lazy {
+            run("umount", &["-l", target]).map(|_| ())
+        } else {
+            run("umount", &[target]).map(|_| ())
+        }
+    }
+
+    fn is_mounted(&self, target: &str) -> bool {
+        run("mountpoint", &["-q", target]).is_ok()
+    }
+
+    fn holders(&self, target: &str) -> String {
+        run("fuser", &["-mv", target]).unwrap_or_else(|e| e)
+    }
+
+    fn volume_state(&self, uuid: &str) -> Option<VolumeState> {
+        ntfs::inspect_device(&format!("/dev/disk/by-uuid/{}", uuid)).ok()
+    }
+
+    fn system_markers(&self, uuid: &str) -> Option<SystemMarkers> {
+        ntfs::markers_device(&format!("/dev/disk/by-uuid/{}", uuid)).ok()
+    }
+
+    fn blkid(&self, device: &str, tag: &str) -> Option<String> {
+        let out = run("blkid", &["-o", "value", "-s", tag, device]).ok()?;
+        Some(out.trim().to_string()).filter(|v| !v.is_empty())
+    }
+
+    fn open_bitlk(&mut self, device: &str, name: &str, key: &str) -> Result<(), String> {
+        run_with_input(
+            "cryptsetup",
+            &["open", "--type", "bitlk", "--key-file=-", device, name],
+            Some(key.as_bytes()),
+        )
+        .map(|_| ())
+    }
+
+    fn test_bitlk(&mut self, device: &str, key: &str) -> Result<(), String> {
+        run_with_input(
+            "cryptsetup",
+            &[
+                "open",
+                "--test-passphrase",
+                "--type",
+                "bitlk",
+                "--key-file=-",
+                device,
+            ],
+            Some(key.as_bytes()),
+        )
+        .map(|_| ())
+    }
+
+    fn mapper_exists(&self, name: &str) -> bool {
+        Path::new("/dev/mapper").join(name).exists()
+    }
+}
+
+/// What `update_block` will do, computed on a copy of /etc/fstab.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdatePlan {
+    /// Bind targets dropped or re-pointed by the new block; unmounted first.
+    pub stale: Vec<String>,
+    pub mountpoints: Vec<String>,
+    pub block: String,
+    pub targets: Vec<String>,
+}
+
+/// Replace block `id` in `fstab` with `block`. Fails when the block does not
+/// exist or a new target is already mounted by a line outside the block.
+pub fn plan_update(fstab: &mut FstabFile, id: &str, block: &str) -> Result<UpdatePlan, Failure> {
+    check_id(id)?;
+    let lines = fstab::block_lines(id, block).map_err(|e| Failure::new("invalid_request", e))?;
+    let not_found = || Failure::new("not_found", format!("block id {} not found", id));
+    let old = fstab.replace_block(id, lines).ok_or_else(not_found)?;
+    let new = fstab.block(id).ok_or_else(not_found)?;
+
+    for t in new.targets() {
+        let clash = fstab.lines[..new.start]
+            .iter()
+            .chain(&fstab.lines[new.end..])
+            .find(|l| l.entry().is_some_and(|e| e.file == t));
+        if let Some(line) = clash {
+            return Err(Failure::new(
+                "conflict",
+                format!(
+                    "target {} already present in /etc/fstab (line: {})",
+                    t, line.raw
+                ),
+            ));
+        }
+    }
+
+    Ok(UpdatePlan {
+        stale: old
+            .binds()
+            .filter(|o| !new.binds().any(|n| n.spec == o.spec && n.file == o.file))
+            .map(|e| e.file.clone())
+            .collect(),
+        mountpoints: mountpoints(new.entries()),
+        block: new.text() + "\n",
+        targets: new.targets(),
+    })
+}
+
+/// What `remove_block` will do, computed on a copy of /etc/fstab.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemovePlan {
+    pub id: String,
+    /// Bind targets of the block, in file order.
+    pub targets: Vec<String>,
+    /// Mountpoint of the partition entry of the block, if it has one.
+    pub partition: Option<String>,
+}
+
+/// Remove the block with `id`, or the block binding onto `target`, from `fstab`.
+pub fn plan_remove(
+    fstab: &mut FstabFile,
+    id: Option<&str>,
+    target: Option<&str>,
+) -> Result<RemovePlan, Failure> {
+    let id = match (id, target) {
+        (Some(id), _) if !id.trim().is_empty() => {
+            check_id(id)?;
+            id.to_string()
+        }
+        (_, Some(t)) if !t.trim().is_empty() => match fstab.block_for_target(t) {
+            Some(b) => b.id,
+            None => {
+                return Err(Failure::new(
+                    "not_found",
+                    "no managed block found for target",
+                ))
+            }
+        },
+        _ => return Err(Failure::new("invalid_request", "missing id or target")),
+    };
+    let block = fstab
+        .remove_block(&id)
+        .ok_or_else(|| Failure::new("not_found", format!("block id {} not found", id)))?;
+    let targets = block.targets();
+    if let Some(t) = targets.iter().find(|t| !t.starts_with('/')) {
+        return Err(Failure::new(
+            "invalid_request",
+            format!("invalid target path: {}", t),
+        ));
+    }
+    let partition = block
+        .entries()
+        .find(|e| !e.is_bind() && e.file.starts_with('/'))
+        .map(|e| e.file.clone());
+    // A second set of folders from the same partition gets no partition
+    // line of its own (see `perform_mounts`); its binds would lose their
+    // source, at once and at every boot, if this block took the line away.
+    if let Some(mp) = &partition {
+        let user = fstab.blocks().into_iter().find_map(|b| {
+            b.binds()
+                .find(|e| Path::new(&e.spec).starts_with(mp))
+                .map(|e| (b.id.clone(), e.spec.clone()))
+        });
+        if let Some((other, source)) = user {
+            return Err(Failure::new(
+                "conflict",
+                format!(
+                    "block {} mounts {}, which block {} binds from ({}); remove {} first",
+                    id, mp, other, source, other
+                ),
+            ));
+        }
+    }
+    Ok(RemovePlan {
+        id,
+        targets,
+        partition,
+    })
+}
+
+/// Block ids end up in marker comments; refuse anything that could break
+/// out of one.
+pub fn check_id(id: &str) -> Result<(), Failure> {
+    if id.is_empty() {
+        return Err(Failure::new("invalid_request", "missing id"));
+    }
+    if id.len() > 128 || id.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(Failure::new(
+            "invalid_request",
+            format!("invalid block id {:?}", id),
+        ));
+    }
+    Ok(())
+}
+
+// Create `path` afresh with `mode`. An existing entry is removed first
+// (without following it if it is a symlink) and the new file is opened with
+// O_EXCL, so whatever was planted at that name is never written through.
+fn write_new(path: &Path, contents: &[u8], mode: u32) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    create_new(path, contents, mode)
+}
+
+// Create `path` with `mode`; fails with AlreadyExists if anything is there.
+fn create_new(path: &Path, contents: &[u8], mode: u32) -> std::io::Result<()> {
+    let mut f = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(path)?;
+    f.write_all(contents)?;
+    f.set_permissions(fs::Permissions::from_mode(mode))?;
+    f.sync_all()
+}
+
+// `Helper::install` for any file under /etc.
+fn install_at(path: &Path, contents: &str) -> std::io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let staged = path.with_file_name(format!(".{}.lindy.new", name));
+    write_new(&staged, contents.as_bytes(), 0o644)?;
+    fs::rename(&staged, path)?;
+    if let Some(dir) = path.parent() {
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+pub fn mountpoints<'a>(entries: impl Iterator<Item = &'a fstab::FstabEntry>) -> Vec<String> {
+    entries
+        .map(|e| e.file.clone())
+        .filter(|f| f.starts_with('/'))
+        .collect()
+}
+
+/// Executes requests against an fstab file and a backup directory.
+pub struct Helper<M: Mounter> {
+    pub fstab: PathBuf,
+    pub backup_dir: PathBuf,
+    pub mounter: M,
+    /// Timestamp used for the backup taken by this run.
+    pub now: u64,
+    /// Root-owned metadata directory of older versions; a block's record
+    /// there is deleted together with the block.
+    pub legacy_meta_dir: Option<PathBuf>,
+    /// crypttab and systemd's key directory, next to the fstab.
+    pub crypttab: PathBuf,
+    pub key_dir: PathBuf,
+    log: Vec<String>,
+}
+
+impl Helper<System> {
+    pub fn system() -> Helper<System> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Helper {
+            legacy_meta_dir: Some(PathBuf::from(metadata::LEGACY_DIR)),
+            ..Helper::new("/etc/fstab", backups::BACKUP_DIR, System, now)
+        }
+    }
+}
+
+impl<M: Mounter> Helper<M> {
+    pub fn new(
+        fstab: impl Into<PathBuf>,
+        backup_dir: impl Into<PathBuf>,
+        mounter: M,
+        now: u64,
+    ) -> Helper<M> {
+        let fstab = fstab.into();
+        Helper {
+            crypttab: fstab.with_file_name("crypttab"),
+            key_dir: fstab.with_file_name("cryptsetup-keys.d"),
+            fstab,
+            backup_dir: backup_dir.into(),
+            mounter,
+            now,
+            legacy_meta_dir: None,
+            log: Vec::new(),
+        }
+    }
+
+    pub fn handle(&mut self, request: &Request) -> Response {
+        let result = match request {
+            Request::ApplyBlock {
+                id,
+                block,
+                targets,
+                lazy_retry,
+            } => self.apply_block(id, block, targets, *lazy_retry),
+            Request::UpdateBlock { id, block } => self.update_block(id, block),
+            Request::RemoveBlock { id, target, force } => {
+                self.remove_block(id.as_deref(), target.as_deref(), *force)
+            }
+            Request::RestoreBackup { ts } => self.restore_backup(*ts),
+            Request::PruneBackups {
+                keep_last,
+                max_age_days,
+            } => {
+                let pruned = self.prune(&RetentionPolicy {
+                    keep_last: *keep_last,
+                    max_age_days: *max_age_days,
+                });
+                Ok(Response {
+                    pruned,
+                    ..Response::ok("pruned", "old fstab backups removed")
+                })
+            }
+            Request::Mount { uuid, target } => self.mount(uuid, target),
+            Request::Unmount { target, lazy } => self
+                .mounter
+                .umount(target, *lazy)
+                .map(|_| Response::ok("unmounted", format!("unmounted {}", target)))
+                .map_err(|e| Failure::new("umount_failed", e)),
+            Request::Unlock {
+                device,
+                name,
+                key,
+                persist,
+            } => self.unlock(device, name, key, *persist),
+            Request::Inspect { uuids } => self.inspect(uuids),
+        };
+        let mut resp = result.unwrap_or_else(Response::from);
+        resp.log = std::mem::take(&mut self.log);
+        resp
+    }
+
+    fn log(&mut self, line: impl Into<String>) {
+        self.log.push(line.into());
+    }
+
+    fn read_fstab(&self) -> Result<FstabFile, Failure> {
+        match fs::read_to_string(&self.fstab) {
+            Ok(s) => Ok(FstabFile::parse(&s)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(FstabFile::default()),
+            Err(e) => Err(Failure::new(
+                "io_error",
+                format!("failed reading {}: {}", self.fstab.display(), e),
+            )),
+        }
+    }
+
+    /// Atomically install `contents` as the fstab: staged next to it so the
+    /// rename stays on one filesystem, fsynced before the rename, and the
+    /// directory fsynced afterwards so a crash leaves the old or the new
+    /// file, never a truncated one.
+    fn install(&self, contents: &str) -> std::io::Result<()> {
+        install_at(&self.fstab, contents)
+    }
+
+    /// Back up the current fstab, install `file`, and run `mount -a`. If that
+    /// fails the backup is put back and mounts of `mountpoints` made by this
+    /// run are undone. Returns the backup path.
+    fn commit(
+        &mut self,
+        file: &FstabFile,
+        mountpoints: &[String],
+        busy_targets: &[String],
+        lazy_retry: bool,
+    ) -> Result<String, Failure> {
+        let was_mounted: Vec<bool> = mountpoints
+            .iter()
+            .map(|m| self.mounter.is_mounted(m))
+            .collect();
+        let io_error = |e: std::io::Error| Failure::new("io_error", e.to_string());
+        let backup = if self.fstab.exists() {
+            let current = fs::read(&self.fstab).map_err(io_error)?;
+            self.write_backup(&current).map_err(io_error)?
+        } else {
+            backups::path_in(&self.backup_dir, self.now)
+        };
+        self.install(&file.to_string()).map_err(io_error)?;
+        let backup = backup.display().to_string();
+
+        self.log("Attempting mount -a");
+        if let Err(e) = self.mounter.mount_all() {
+            self.log(e);
+            for t in busy_targets {
+                let holders = self.mounter.holders(t);
+                self.log(format!("FUSER {}:\n{}", t, holders));
+            }
+            let reason = if lazy_retry {
+                self.log("Attempting lazy unmount of targets");
+                for t in busy_targets {
+                    let _ = self.mounter.umount(t, true);
+                }
+                match self.mounter.mount_all() {
+                    Ok(()) => None,
+                    Err(e) => {
+                        self.log(e);
+                        Some("mount_failed_after_lazy")
+                    }
+                }
+            } else {
+                Some("mount_failed")
+            };
+            if let Some(reason) = reason {
+                return Err(self.rollback(reason, &backup, mountpoints, &was_mounted));
+            }
+        }
+        Ok(backup)
+    }
+
+    // Keep `contents` as the backup taken now. Backups are never replaced:
+    // one already taken in this second (import and repair send several
+    // requests in a row) moves this one to the next free second, so each
+    // state before a change can still be restored, in order.
+    fn write_backup(&self, contents: &[u8]) -> std::io::Result<PathBuf> {
+        for ts in self.now..self.now + BACKUP_NAME_TRIES {
+            let path = backups::path_in(&self.backup_dir, ts);
+            match create_new(&path, contents, 0o644) {
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                other => return other.map(|()| path),
+            }
+        }
+        Err(std::io::Error::other(format!(
+            "no free backup name after {}",
+            backups::path_in(&self.backup_dir, self.now).display()
+        )))
+    }
+
+    fn rollback(
+        &mut self,
+        reason: &str,
+        backup: &str,
+        mountpoints: &[String],
+        was_mounted: &[bool],
+    ) -> Failure {
+        self.log(format!("Rolling back: {}", reason));
+        for (mp, was) in mountpoints.iter().zip(was_mounted).rev() {
+            if !was && self.mounter.is_mounted(mp) {
+                if let Err(e) = self.mounter.umount(mp, false) {
+                    self.log(e);
+                    let _ = self.mounter.umount(mp, true);
+                }
+            }
+        }
+        let restored = match fs::read_to_string(backup).and_then(|s| self.install(&s)) {
+            Ok(()) => true,
+            Err(e) => {
+                self.log(format!("restoring {} failed: {}", backup, e));
+                false
+            }
+        };
+        if let Err(e) = self.mounter.mount_all() {
+            self.log(e);
+        }
+        let message = if restored {
+            format!(
+                "activation failed ({}); /etc/fstab was restored from {} and partial mounts were undone",
+                reason, backup
+            )
+        } else {
+            format!(
+                "activation failed ({}) and restoring {} failed; check /etc/fstab manually",
+                reason, backup
+            )
+        };
+        Failure {
+            reason: Some(reason.to_string()),
+            restored: Some(restored),
+            backup: Some(backup.to_string()),
+            ..Failure::new("rolled_back", message)
+        }
+    }
+
+    /// Only install files that are regular files owned by the owner of the
+    /// backup directory (root for /etc) and not writable by anyone else.
+    fn check_trusted(&self, path: &Path) -> Result<(), Failure> {
+        let untrusted = |why: &str| {
+            Failure::new(
+                "untrusted_file",
+                format!("refusing to use {}: {}", path.display(), why),
+            )
+        };
+        let meta = fs::symlink_metadata(path)
+            .map_err(|e| Failure::new("io_error", format!("{}: {}", path.display(), e)))?;
+        let dir = fs::metadata(&self.backup_dir).map_err(|e| {
+            Failure::new("io_error", format!("{}: {}", self.backup_dir.display(), e))
+        })?;
+        if !meta.file_type().is_file() {
+            return Err(untrusted("not a regular file"));
+        }
+        if meta.uid() != dir.uid() {
+            return Err(untrusted("wrong owner"));
+        }
+        if meta.mode() & 0o022 != 0 {
+            return Err(untrusted("writable by group or others"));
+        }
+        Ok(())
+    }
+
+    fn prune(&mut self, policy: &RetentionPolicy) -> Vec<String> {
+        let all = backups::list(&self.backup_dir);
+        let mut pruned = Vec::new();
+        for b in policy.expired(&all, self.now) {
+            match fs::remove_file(&b.path) {
+                Ok(()) => pruned.push(b.path.clone()),
+                Err(e) => self.log(format!("failed to remove {}: {}", b.path, e)),
+            }
+        }
+        pruned
+    }
+
+    /// Unmount `target` if it is mounted. Without `force` a busy target fails
+    /// the request with the `fuser` output; with it a lazy unmount is tried.
+    fn unmount_target(&mut self, target: &str, force: bool) -> Result<(), Failure> {
+        if !self.mounter.is_mounted(target) {
+            return Ok(());
+        }
+        self.log(format!("Attempting umount {}", target));
+        if let Err(e) = self.mounter.umount(target, false) {
+            self.log(e);
+            if !force {
+                let holders = self.mounter.holders(target);
+                self.log(format!("FUSER {}:\n{}", target, holders));
+                return Err(Failure::new(
+                    "busy",
+                    format!("could not unmount {}: target is busy", target),
+                ));
+            }
+            if let Err(e) = self.mounter.umount(target, true) {
+                self.log(format!("lazy unmount {} failed: {}", target, e));
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_block(
+        &mut self,
+        id: &str,
+        block: &str,
+        targets: &[String],
+        lazy_retry: bool,
+    ) -> Result<Response, Failure> {
+        check_id(id)?;
+        // Only a single well-formed block with this id is ever appended.
+        let lines =
+            fstab::block_lines(id, block).map_err(|e| Failure::new("invalid_request", e))?;
+        let block = FstabFile {
+            lines,
+            trailing_newline: true,
+        };
+        let current = self.read_fstab()?;
+        if current.block(id).is_some() {
+            return Err(Failure::new(
+                "conflict",
+                format!("block id {} already exists in /etc/fstab", id),
+            ));
+        }
+        let candidate = Candidate::with_appended(Some(&current), &block.to_string());
+        let mountpoints = mountpoints(block.entries());
+        let backup = self.commit(&candidate.file, &mountpoints, targets, lazy_retry)?;
+        let pruned = self.prune(&RetentionPolicy::default());
+        Ok(Response {
+            backup: Some(backup),
+            id: Some(id.to_string()),
+            pruned,
+            ..Response::ok("applied", "fstab block appended and mount -a executed")
+        })
+    }
+
+    fn update_block(&mut self, id: &str, block: &str) -> Result<Response, Failure> {
+        let mut fstab = self.read_fstab()?;
+        let plan = plan_update(&mut fstab, id, block)?;
+        // stale targets this run unmounts; they are mounted again if the
+        // update fails, since the old block is what stays in /etc/fstab
+        let mut unmounted = Vec::new();
+        for t in plan.stale.iter().rev() {
+            let was_mounted = self.mounter.is_mounted(t);
+            if let Err(e) = self.unmount_target(t, false) {
+                self.remount(&unmounted);
+                return Err(Failure::new(
+                    "busy",
+                    format!(
+                        "could not unmount a target removed from the block; fuser output included ({})",
+                        e.message
+                    ),
+                ));
+            }
+            if was_mounted {
+                unmounted.push(t.clone());
+            }
+        }
+        let backup = match self.commit(&fstab, &plan.mountpoints, &[], false) {
+            Ok(backup) => backup,
+            Err(e) => {
+                self.remount(&unmounted);
+                return Err(e);
+            }
+        };
+        let pruned = self.prune(&RetentionPolicy::default());
+        Ok(Response {
+            backup: Some(backup),
+            id: Some(id.to_string()),
+            block: Some(plan.block),
+            targets: plan.targets,
+            pruned,
+            ..Response::ok(
+                "updated",
+                format!(
+                    "fstab block {} rewritten in place and mount -a executed",
+                    id
+                ),
+            )
+        })
+    }
+
+    // Mount `targets` again from /etc/fstab, parents first (the reverse of
+    // the order they were unmounted in). Failures are logged only: the
+    // request has already failed for another reason.
+    fn remount(&mut self, targets: &[String]) {
+        for t in targets.iter().rev() {
+            if self.mounter.is_mounted(t) {
+                continue;
+            }
+            self.log(format!("Remounting {}", t));
+            if let Err(e) = self.mounter.mount_target(t) {
+                self.log(e);
+            }
+        }
+    }
+
+    fn remove_block(
+        &mut self,
+        id: Option<&str>,
+        target: Option<&str>,
+        force: bool,
+    ) -> Result<Response, Failure> {
+        let mut fstab = self.read_fstab()?;
+        let plan = plan_remove(&mut fstab, id, target)?;
+        // children before parents, then the partition the binds live on
+        for t in plan.targets.iter().rev().chain(&plan.partition) {
+            self.unmount_target(t, force)?;
+        }
+        let backup = self.commit(&fstab, &[], &[], false)?;
+        if let Some(dir) = &self.legacy_meta_dir {
+            let legacy = dir.join(format!("{}.json", plan.id));
+            if fs::remove_file(&legacy).is_ok() {
+                self.log(format!("Removed {}", legacy.display()));
+            }
+        }
+        let pruned = self.prune(&RetentionPolicy::default());
+        Ok(Response {
+            backup: Some(backup),
+            id: Some(plan.id),
+            targets: plan.targets,
+            pruned,
+            ..Response::ok("removed", "fstab block removed and mount -a executed")
+        })
+    }
+
+    fn restore_backup(&mut self, ts: u64) -> Result<Response, Failure> {
+        let source = backups::find(&self.backup_dir, ts).ok_or_else(|| {
+            Failure::new(
+                "not_found",
+                format!("no fstab backup with timestamp {}", ts),
+            )
+        })?;
+        self.check_trusted(Path::new(&source.path))?;
+        let contents = fs::read_to_string(&source.path)
+            .map_err(|e| Failure::new("io_error", format!("{}: {}", source.path, e)))?;
+        let backup = self.commit(&FstabFile::parse(&contents), &[], &[], false)?;
+        Ok(Response {
+            backup: Some(backup),
+            ..Response::ok(
+                "restored",
+                format!(
+                    "/etc/fstab restored from {} and mount -a executed",
+                    source.path
+                ),
+            )
+        })
+    }
+
+    fn mount(&mut self, uuid: &str, target: &str) -> Result<Response, Failure> {
+        if uuid.trim().is_empty() || uuid.contains('/') || !target.starts_with('/') {
+            return Err(Failure::new(
+                "invalid_request",
+                "mount needs a UUID and an absolute target",
+            ));
+        }
+        self.log(format!("Creating mount directory: {}", target));
+        fs::create_dir_all(target).map_err(|e| Failure::new("io_error", e.to_string()))?;
+        // Writing to a hibernated Windows volume loses data when Windows
+        // resumes; mount it read-only instead (see ntfs.rs).
+        let reason = self
+            .mounter
+            .volume_state(uuid)
+            .and_then(|s| s.read_only_reason());
+        if let Some(reason) = reason {
+            self.log(format!("{}; mounting read-only", reason));
+        }
+        self.log(format!("Mounting partition {} to {}", uuid, target));
+        self.mounter
+            .mount_uuid(uuid, target, reason.is_some())
+            .map_err(|e| Failure::new("mount_failed", e))?;
+        Ok(match reason {
+            Some(reason) => Response::ok(
+                "windows_hibernated",
+                format!(
+                    "{}; {} was mounted read-only. Shut Windows down fully (or turn off Fast Startup) to write to it.",
+                    reason, target
+                ),
+            ),
+            None => Response::ok("mounted", format!("Mount successful: {}", target)),
+        })
+    }
+
+    fn inspect(&mut self, uuids: &[String]) -> Result<Response, Failure> {
+        if uuids.iter().any(|u| u.trim().is_empty() || u.contains('/')) {
+            return Err(Failure::new("invalid_request", "inspect needs UUIDs"));
+        }
+        let mut markers = BTreeMap::new();
+        for uuid in uuids {
+            match self.mounter.system_markers(uuid) {
+                Some(m) => {
+                    markers.insert(uuid.clone(), m);
+                }
+                None => self.log(format!("Could not read the NTFS volume {}", uuid)),
+            }
+        }
+        Ok(Response {
+            markers,
+            ..Response::ok("inspected", format!("inspected {} volume(s)", uuids.len()))
+        })
+    }
+
+    fn unlock(
+        &mut self,
+        device: &str,
+        name: &str,
+        key: &str,
+        persist: Option<Persist>,
+    ) -> Result<Response, Failure> {
+        let valid_name = !name.is_empty()
+            && name.len() <= 64
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+        if !device.starts_with("/dev/") || device.contains("/../") || !valid_name {
+            return Err(Failure::new(
+                "invalid_request",
+                "unlock needs a /dev device and a mapper name",
+            ));
+        }
+        if self.mounter.blkid(device, "TYPE").as_deref() != Some("BitLocker") {
+            return Err(Failure::new(
+                "invalid_request",
+                format!("{} is not a BitLocker volume", device),
+            ));
+        }
+        let mapper = format!("/dev/mapper/{}", name);
+        if self.mounter.mapper_exists(name) {
+            self.log(format!("{} is already open", mapper));
+            // the key about to be stored for boot has not been used; a typo
+            // must not replace a working key file
+            if persist == Some(Persist::KeyFile) {
+                self.mounter
+                    .test_bitlk(device, key)
+                    .map_err(|e| Failure::new("unlock_failed", e))?;
+            }
+        } else {
+            // passed on byte for byte: spaces can be part of a password
+            if key.is_empty() {
+                return Err(Failure::new(
+                    "invalid_request",
+                    "missing password or recovery key",
+                ));
+            }
+            self.log(format!("Unlocking {} as {}", device, mapper));
+            self.mounter
+                .open_bitlk(device, name, key)
+                .map_err(|e| Failure::new("unlock_failed", e))?;
+        }
+        if let Some(persist) = persist {
+            self.persist_unlock(device, name, key, persist)?;
+        }
+        Ok(Response {
+            device: Some(mapper.clone()),
+            ..Response::ok("unlocked", format!("{} unlocked as {}", device, mapper))
+        })
+    }
+
+    // Add a crypttab line opening `device` as `name` at boot, unless one for
+    // `name` exists. systemd-cryptsetup reads the key from
+    // <key_dir>/<name>.key when there is one and asks for it otherwise.
+    fn persist_unlock(
+        &mut self,
+        device: &str,
+        name: &str,
+        key: &str,
+        persist: Persist,
+    ) -> Result<(), Failure> {
+        let io_error = |e: std::io::Error| Failure::new("io_error", e.to_string());
+        let uuid = self
+            .mounter
+            .blkid(device, "UUID")
+            .ok_or_else(|| Failure::new("io_error", format!("no UUID found for {}", device)))?;
+        if persist == Persist::KeyFile {
+            if key.is_empty() {
+                return Err(Failure::new(
+                    "invalid_request",
+                    "storing the key needs the password or recovery key",
+                ));
+            }
+            fs::create_dir_all(&self.key_dir).map_err(io_error)?;
+            fs::set_permissions(&self.key_dir, fs::Permissions::from_mode(0o700))
+                .map_err(io_error)?;
+            let key_file = self.key_dir.join(format!("{}.key", name));
+            write_new(&key_file, key.as_bytes(), 0o400).map_err(io_error)?;
+            self.log(format!("Stored the key in {}", key_file.display()));
+        }
+        let current = match fs::read_to_string(&self.crypttab) {
+            Ok(s) => s,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(io_error(e)),
+        };
+        if current
+            .lines()
+            .any(|l| l.split_whitespace().next() == Some(name))
+        {
+            self.log(format!("{} already has a crypttab entry", name));
+            return Ok(());
+        }
+        let mut contents = current;
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&crypttab_line(name, &uuid));
+        install_at(&self.crypttab, &contents).map_err(io_error)?;
+        self.log(format!("Added {} to {}", name, self.crypttab.display()));
+        Ok(())
+    }
+}
+
+/// crypttab entry opening the BitLocker volume `uuid` as `name`; `nofail`
+/// keeps a failed unlock from stopping the boot.
+pub fn crypttab_line(name: &str, uuid: &str) -> String {
+    format!(
+        "# lindy: BitLocker volume\n{} UUID={} none bitlk,nofail\n",
+        name, uuid
+    )
+}
+
+/// Entry point of the `lindy-helper` binary: one request on stdin, one
+/// response on stdout. Returns the process exit status.
+pub fn main() -> i32 {
+    let mut input = String::new();
+    let response = match std::io::stdin().read_to_string(&mut input) {
+        Err(e) => Response::error("invalid_request", format!("failed to read request: {}", e)),
+        Ok(_) => match serde_json::from_str::<Request>(&input) {
+            Ok(request) => Helper::system().handle(&request),
+            Err(e) => Response::error("invalid_request", format!("invalid request: {}", e)),
+        },
+    };
+    println!("{}", serde_json::to_string(&response).unwrap());
+    if response.ok {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Records calls; `mount -a` mounts every absolute fstab target unless
+    /// `fail_mount_all` is set, in which case it mounts them and then fails.
+    #[derive(Default)]
+    struct FakeMounter {
+        fstab: PathBuf,
+        mounted: HashSet<String>,
+        busy: HashSet<String>,
+        fail_mount_all: bool,
+        hibernated: bool,
+        /// Open mapper names; /dev/bitlk is BitLocker with key "secret".
+        opened: HashSet<String>,
+        calls: Vec<String>,
+    }
+
+    impl Mounter for FakeMounter {
+        fn mount_all(&mut self) -> Result<(), String> {
+            self.calls.push("mount -a".into());
+            let file = FstabFile::read(self.fstab.to_str().unwrap()).unwrap();
+            for e in file.entries() {
+                self.mounted.insert(e.file.clone());
+            }
+            if self.fail_mount_all {
+                Err("mount: failed".into())
+            } else {
+                Ok(())
+            }
+        }
+
+        fn mount_uuid(&mut self, uuid: &str, target: &str, read_only: bool) -> Result<(), String> {
+            let ro = if read_only { " -o ro" } else { "" };
+            self.calls
+                .push(format!("mount -U {}{} {}", uuid, ro, target));
+            self.mounted.insert(target.to_string());
+            Ok(())
+        }
+
+        fn mount_target(&mut self, target: &str) -> Result<(), String> {
+            self.calls.push(format!("mount {}", target));
+            self.mounted.insert(target.to_string());
+            Ok(())
+        }
+
+        fn umount(&mut self, target: &str, lazy: bool) -> Result<(), String> {
+            self.calls.push(format!(
+                "umount{} {}",
+                if lazy { " -l" } else { "" },
+                target
+            ));
+            if self.busy.contains(target) && !lazy {
+                return Err(format!("umount: {}: target is busy", target));
+            }
+            self.mounted.remove(target);
+            Ok(())
+        }
+
+        fn is_mounted(&self, target: &str) -> bool {
+            self.mounted.contains(target)
+        }
+
+        fn holders(&self, target: &str) -> String {
+            format!("{}: 1234 bash", target)
+        }
+
+        fn volume_state(&self, _uuid: &str) -> Option<VolumeState> {
+            Some(VolumeState {
+                hibernated: self.hibernated,
+                dirty: false,
+            })
+        }
+
+        fn system_markers(&self, uuid: &str) -> Option<SystemMarkers> {
+            (uuid == "C").then_some(SystemMarkers {
+                system32: true,
+                users: true,
+                pagefile: true,
+            })
+        }
+
+        fn blkid(&self, device: &str, tag: &str) -> Option<String> {
+            match (device, tag) {
+                ("/dev/bitlk", "TYPE") => Some("BitLocker".into()),
+                ("/dev/bitlk", "UUID") => Some("B1".into()),
+                _ => None,
+            }
+        }
+
+        fn open_bitlk(&mut self, device: &str, name: &str, key: &str) -> Result<(), String> {
+            self.calls
+                .push(format!("cryptsetup open {} {}", device, name));
+            if key != "secret" {
+                return Err("No key available with this passphrase.".into());
+            }
+            self.opened.insert(name.to_string());
+            Ok(())
+        }
+
+        fn test_bitlk(&mut self, device: &str, key: &str) -> Result<(), String> {
+            self.calls
+                .push(format!("cryptsetup open --test-passphrase {}", device));
+            if key != "secret" {
+                return Err("No key available with this passphrase.".into());
+            }
+            Ok(())
+        }
+
+        fn mapper_exists(&self, name: &str) -> bool {
+            self.opened.contains(name)
+        }
+    }
+
+    fn helper(name: &str, fstab: &str) -> Helper<FakeMounter> {
+        let dir =
+            std::env::temp_dir().join(format!("lindy_helper_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("fstab");
+        fs::write(&path, fstab).unwrap();
+        let mounter = FakeMounter {
+            fstab: path.clone(),
+            ..Default::default()
+        };
+        Helper::new(path, dir, mounter, 1000)
+    }
+
+    fn read(h: &Helper<FakeMounter>) -> String {
+        fs::read_to_string(&h.fstab).unwrap()
+    }
+
+    const BLOCK: &str = "# lindy BEGIN: x\n/src /home/me/Docs none bind 0 0\n# lindy END: x\n";
+
+    #[test]
+    fn request_json_round_trips() {
+        let req: Request =
+            serde_json::from_str(r#"{"op":"remove_block","target":"/home/me/Docs"}"#).unwrap();
+        assert_eq!(
+            req,
+            Request::RemoveBlock {
+                id: None,
+                target: Some("/home/me/Docs".into()),
+                force: false
+            }
+        );
+        let back: Request = serde_json::from_str(&serde_json::to_string(&req).unwrap()).unwrap();
+        assert_eq!(back, req);
+    }
+
+    #[test]
+    fn apply_appends_block_and_keeps_backup() {
+        let mut h = helper("apply", "UUID=root / ext4 defaults 0 1\n");
+        let resp = h.handle(&Request::ApplyBlock {
+            id: "x".into(),
+            block: BLOCK.into(),
+            targets: vec![],
+            lazy_retry: false,
+        });
+        assert!(resp.ok, "{:?}", resp);
+        assert_eq!(
+            read(&h),
+            format!("UUID=root / ext4 defaults 0 1\n{}", BLOCK)
+        );
+        let backup = resp.backup.unwrap();
+        assert_eq!(
+            fs::read_to_string(backup).unwrap(),
+            "UUID=root / ext4 defaults 0 1\n"
+        );
+        assert!(h.mounter.is_mounted("/home/me/Docs"));
+    }
+
+    #[test]
+    fn backups_taken_in_the_same_second_are_all_kept() {
+        let mut h = helper("backups", "UUID=root / ext4 defaults 0 1\n");
+        let first = h.handle(&Request::ApplyBlock {
+            id: "x".into(),
+            block: BLOCK.into(),
+            targets: vec![],
+            lazy_retry: false,
+        });
+        let second = h.handle(&Request::RemoveBlock {
+            id: Some("x".into()),
+            target: None,
+            force: false,
+        });
+        assert!(first.ok && second.ok, "{:?} {:?}", first, second);
+        let (first, second) = (first.backup.unwrap(), second.backup.unwrap());
+        assert_eq!(
+            first,
+            backups::path_in(&h.backup_dir, 1000).display().to_string()
+        );
+        assert_eq!(
+            second,
+            backups::path_in(&h.backup_dir, 1001).display().to_string()
+        );
+        // the state before the first change is still there
+        assert_eq!(
+            fs::read_to_string(first).unwrap(),
+            "UUID=root / ext4 defaults 0 1\n"
+        );
+        assert_eq!(
+            fs::read_to_string(second).unwrap(),
+            format!("UUID=root / ext4 defaults 0 1\n{}", BLOCK)
+        );
+    }
+
+    #[test]
+    fn failed_mount_rolls_back_and_unmounts_new_targets() {
+        let mut h = helper("rollback", "UUID=root / ext4 defaults 0 1\n");
+        h.mounter.mounted.insert("/".into());
+        h.mounter.fail_mount_all = true;
+        let resp = h.handle(&Request::ApplyBlock {
+            id: "x".into(),
+            block: BLOCK.into(),
+            targets: vec!["/home/me/Docs".into()],
+            lazy_retry: false,
+        });
+        assert_eq!(resp.code, "rolled_back");
+        assert_eq!(resp.reason.as_deref(), Some("mount_failed"));
+        assert_eq!(resp.restored, Some(true));
+        assert_eq!(read(&h), "UUID=root / ext4 defaults 0 1\n");
+        assert!(h
+            .mounter
+            .calls
+            .contains(&"umount /home/me/Docs".to_string()));
+        assert!(!h.mounter.calls.contains(&"umount /".to_string()));
+        assert!(resp
+            .log
+            .iter()
+            .any(|l| l.starts_with("FUSER /home/me/Docs")));
+    }
+
+    #[test]
+    fn remove_by_target_unmounts_then_removes_block() {
+        let mut h = helper(
+            "remove",
+            &format!("UUID=root / ext4 defaults 0 1\n{}", BLOCK),
+        );
+        h.mounter.mounted.insert("/home/me/Docs".into());
+        let legacy = h.backup_dir.join("legacy");
+        fs::create_dir(&legacy).unwrap();
+        fs::write(legacy.join("x.json"), r#"{"id":"x"}"#).unwrap();
+        h.legacy_meta_dir = Some(legacy.clone());
+        let resp = h.handle(&Request::RemoveBlock {
+            id: None,
+            target: Some("/home/me/Docs".into()),
+            force: false,
+        });
+        assert!(resp.ok, "{:?}", resp);
+        assert_eq!(resp.id.as_deref(), Some("x"));
+        assert_eq!(read(&h), "UUID=root / ext4 defaults 0 1\n");
+        assert_eq!(h.mounter.calls[0], "umount /home/me/Docs");
+        assert!(!legacy.join("x.json").exists());
+    }
+
+    #[test]
+    fn keeps_a_partition_other_blocks_bind_from() {
+        const OWNER: &str = "# lindy BEGIN: win\nUUID=AAAA /mnt/win ntfs3 defaults 0 0\n/mnt/win/Docs /home/me/Docs none bind 0 0\n# lindy END: win\n";
+        const USER: &str =
+            "# lindy BEGIN: pics\n/mnt/win/Pics /home/me/Pics none bind 0 0\n# lindy END: pics\n";
+        let fstab = format!("UUID=root / ext4 defaults 0 1\n{}{}", OWNER, USER);
+        let mut h = helper("remove_shared", &fstab);
+        h.mounter.mounted.extend([
+            "/mnt/win".into(),
+            "/home/me/Docs".into(),
+            "/home/me/Pics".into(),
+        ]);
+        let remove = |id: &str| Request::RemoveBlock {
+            id: Some(id.into()),
+            target: None,
+            force: false,
+        };
+
+        let resp = h.handle(&remove("win"));
+        assert_eq!(resp.code, "conflict");
+        assert!(
+            resp.message.contains("remove pics first"),
+            "{}",
+            resp.message
+        );
+        assert_eq!(read(&h), fstab);
+        assert!(h.mounter.calls.is_empty());
+
+        // once the other block is gone the partition can go too
+        assert!(h.handle(&remove("pics")).ok);
+        let resp = h.handle(&remove("win"));
+        assert!(resp.ok, "{:?}", resp);
+        assert_eq!(read(&h), "UUID=root / ext4 defaults 0 1\n");
+        assert!(!h.mounter.is_mounted("/mnt/win"));
+    }
+
+    #[test]
+    fn busy_target_blocks_removal_unless_forced() {
+        let mut h = helper("busy", BLOCK);
+        h.mounter.mounted.insert("/home/me/Docs".into());
+        h.mounter.busy.insert("/home/me/Docs".into());
+        let remove = |force| Request::RemoveBlock {
+            id: Some("x".into()),
+            target: None,
+            force,
+        };
+        let resp = h.handle(&remove(false));
+        assert_eq!(resp.code, "busy");
+        assert_eq!(read(&h), BLOCK);
+        let resp = h.handle(&remove(true));
+        assert!(resp.ok, "{:?}", resp);
+        assert!(h
+            .mounter
+            .calls
+            .contains(&"umount -l /home/me/Docs".to_string()));
+        assert_eq!(read(&h).trim(), "");
+    }
+
+    #[test]
+    fn apply_rejects_bad_ids_and_foreign_blocks() {
+        let mut h = helper("apply_bad", "");
+        let apply = |id: &str, block: &str| Request::ApplyBlock {
+            id: id.into(),
+            block: block.into(),
+            targets: vec![],
+            lazy_retry: false,
+        };
+        let resp = h.handle(&apply("x\n/evil /etc none bind", BLOCK));
+        assert_eq!(resp.code, "invalid_request");
+        let resp = h.handle(&apply("y", BLOCK));
+        assert_eq!(resp.code, "invalid_request");
+        assert_eq!(read(&h), "");
+    }
+
+    #[test]
+    fn hibernated_volume_is_mounted_read_only() {
+        let mut h = helper("hibernated", "");
+        let target = h.backup_dir.join("win").display().to_string();
+        let resp = h.handle(&Request::Mount {
+            uuid: "AAAA".into(),
+            target: target.clone(),
+        });
+        assert_eq!(resp.code, "mounted");
+        h.mounter.hibernated = true;
+        let resp = h.handle(&Request::Mount {
+            uuid: "AAAA".into(),
+            target: target.clone(),
+        });
+        assert!(resp.ok, "{:?}", resp);
+        assert_eq!(resp.code, "windows_hibernated");
+        assert_eq!(
+            h.mounter.calls,
+            vec![
+                format!("mount -U AAAA {}", target),
+                format!("mount -U AAAA -o ro {}", target)
+            ]
+        );
+    }
+
+    #[test]
+    fn inspects_volumes_it_can_read() {
+        let mut h = helper("inspect", "");
+        let resp = h.handle(&Request::Inspect {
+            uuids: vec!["C".into(), "D".into()],
+        });
+        assert_eq!(resp.code, "inspected");
+        assert_eq!(resp.markers.keys().collect::<Vec<_>>(), vec!["C"]);
+        assert!(resp.markers["C"].system32);
+        assert_eq!(resp.log.len(), 1);
+        let resp = h.handle(&Request::Inspect {
+            uuids: vec!["../sda1".into()],
+        });
+        assert_eq!(resp.code, "invalid_request");
+    }
+
+    #[test]
+    fn unlocks_bitlocker_and_persists_it_once() {
+        let mut h = helper("unlock", "");
+        let unlock = |key: &str, persist| Request::Unlock {
+            device: "/dev/bitlk".into(),
+            name: "lindy-bitlk".into(),
+            key: key.into(),
+            persist,
+        };
+        let resp = h.handle(&unlock("wrong", None));
+        assert_eq!(resp.code, "unlock_failed");
+        assert!(!format!("{:?}", unlock("wrong", None).redacted()).contains("wrong"));
+
+        // the key is not trimmed
+        let resp = h.handle(&unlock(" secret\n", None));
+        assert_eq!(resp.code, "unlock_failed");
+
+        let resp = h.handle(&unlock("secret", Some(Persist::KeyFile)));
+        assert!(resp.ok, "{:?}", resp);
+        assert_eq!(resp.device.as_deref(), Some("/dev/mapper/lindy-bitlk"));
+        let key_file = h.key_dir.join("lindy-bitlk.key");
+        assert_eq!(fs::read_to_string(&key_file).unwrap(), "secret");
+        assert_eq!(
+            fs::metadata(&key_file).unwrap().permissions().mode() & 0o777,
+            0o400
+        );
+
+        // already open: nothing is run and crypttab keeps one entry
+        let resp = h.handle(&unlock("", Some(Persist::Prompt)));
+        assert!(resp.ok, "{:?}", resp);
+        assert_eq!(
+            fs::read_to_string(&h.crypttab).unwrap(),
+            crypttab_line("lindy-bitlk", "B1")
+        );
+        assert_eq!(h.mounter.calls.len(), 3);
+
+        // already open: a key to store is tested first, and a wrong one
+        // leaves the stored key alone
+        let resp = h.handle(&unlock("typo", Some(Persist::KeyFile)));
+        assert_eq!(resp.code, "unlock_failed");
+        assert_eq!(fs::read_to_string(&key_file).unwrap(), "secret");
+        assert_eq!(
+            h.mounter.calls.last().map(String::as_str),
+            Some("cryptsetup open --test-passphrase /dev/bitlk")
+        );
+
+        let mut other = unlock("secret", None);
+        if let Request::Unlock { device, .. } = &mut other {
+            *device = "/dev/sda1".into();
+        }
+        assert_eq!(h.handle(&other).code, "invalid_request");
+    }
+
+    #[test]
+    fn restore_only_uses_trusted_backups() {
+        let mut h = helper("restore", "current\n");
+        let good = backups::path_in(&h.backup_dir, 10);
+        fs::write(&good, "UUID=old / ext4 defaults 0 1\n").unwrap();
+        fs::set_permissions(&good, fs::Permissions::from_mode(0o644)).unwrap();
+        let resp = h.handle(&Request::RestoreBackup { ts: 10 });
+        assert!(resp.ok, "{:?}", resp);
+        assert_eq!(read(&h), "UUID=old / ext4 defaults 0 1\n");
+
+        let writable = backups::path_in(&h.backup_dir, 20);
+        fs::write(&writable, "x\n").unwrap();
+        fs::set_permissions(&writable, fs::Permissions::from_mode(0o666)).unwrap();
+        let resp = h.handle(&Request::RestoreBackup { ts: 20 });
+        assert_eq!(resp.code, "untrusted_file");
+        assert_eq!(read(&h), "UUID=old / ext4 defaults 0 1\n");
+    }
+
+    #[test]
+    fn update_reports_stale_targets_and_conflicts() {
+        let mut file = FstabFile::parse(&format!("/a /taken none bind 0 0\n{}", BLOCK));
+        let plan = plan_update(&mut file, "x", "/other /home/me/Pics none bind 0 0\n").unwrap();
+        assert_eq!(plan.stale, vec!["/home/me/Docs".to_string()]);
+        assert_eq!(plan.targets, vec!["/home/me/Pics".to_string()]);
+
+        let mut file = FstabFile::parse(&format!("/a /taken none bind 0 0\n{}", BLOCK));
+        let err = plan_update(&mut file, "x", "/src /taken none bind 0 0\n").unwrap_err();
+        assert_eq!(err.code, "conflict");
+    }
+
+    #[test]
+    fn failed_update_mounts_stale_targets_again() {
+        const TWO: &str = "# lindy BEGIN: x\n/src /home/me/Docs none bind 0 0\n/pics /home/me/Pics none bind 0 0\n# lindy END: x\n";
+        let update = Request::UpdateBlock {
+            id: "x".into(),
+            block: "/music /home/me/Music none bind 0 0\n".into(),
+        };
+
+        // Pics is unmounted first, then Docs turns out to be busy
+        let mut h = helper("update_busy", TWO);
+        h.mounter
+            .mounted
+            .extend(["/home/me/Docs".into(), "/home/me/Pics".into()]);
+        h.mounter.busy.insert("/home/me/Docs".into());
+        let resp = h.handle(&update);
+        assert_eq!(resp.code, "busy");
+        assert_eq!(read(&h), TWO);
+        assert!(h.mounter.calls.contains(&"mount /home/me/Pics".to_string()));
+        assert!(h.mounter.is_mounted("/home/me/Pics"));
+
+        // mount -a fails and the old fstab is put back
+        let mut h = helper("update_rollback", TWO);
+        h.mounter
+            .mounted
+            .extend(["/home/me/Docs".into(), "/home/me/Pics".into()]);
+        h.mounter.fail_mount_all = true;
+        let resp = h.handle(&update);
+        assert_eq!(resp.code, "rolled_back");
+        assert_eq!(read(&h), TWO);
+        assert!(h.mounter.is_mounted("/home/me/Docs"));
+        assert!(h.mounter.is_mounted("/home/me/Pics"));
+        assert!(!h.mounter.is_mounted("/home/me/Music"));
+    }
+}
@@ -0,0 +1,146 @@
+// Where a user's Desktop, Documents, ... really are.
+//
+// Windows lets these Known Folders be moved: OneDrive backup moves them to
+// %USERPROFILE%\OneDrive\Documents, and users move them to another drive
+// (D:\Documents) from the folder's Location tab. The user's own hive keeps
+// the new place under User Shell Folders, written as Windows sees it. These
+// paths are turned into paths under the mounted partitions: the profile
+// folder for %USERPROFILE%, the Windows partition for the system drive, and
+// the partition behind any other drive letter. A folder on a drive that is
+// not mounted (or on a network share) cannot be mapped, and is reported
+// rather than mapped to the unused folder left in the profile.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Linux folder name and its value name under User Shell Folders.
+pub const SHELL_FOLDERS: &[(&str, &str)] = &[
+    ("Desktop", "Desktop"),
+    ("Documents", "Personal"),
+    ("Downloads", "{374DE290-123F-4565-9164-39C4925E467B}"),
+    ("Music", "My Music"),
+    ("Pictures", "My Pictures"),
+    ("Videos", "My Video"),
+];
+
+/// Where the drives of a Windows installation are on Linux.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drives {
+    /// The user's profile folder.
+    pub profile: PathBuf,
+    /// The Windows partition.
+    pub system: PathBuf,
+    /// Mount point of each other drive letter that is mounted.
+    pub letters: Vec<(char, PathBuf)>,
+}
+
+/// The Linux path of `raw`, a User Shell Folders value such as
+/// `%USERPROFILE%\OneDrive\Documents` or `D:\Pictures`. Existing folders
+/// are matched case-insensitively, as Windows does. None when it is not on
+/// a mounted drive.
+pub fn resolve(raw: &str, drives: &Drives) -> Option<PathBuf> {
+    let raw = raw.trim();
+    let (base, rest) = if let Some(rest) = strip_var(raw, "%HOMEDRIVE%%HOMEPATH%") {
+        (drives.profile.clone(), rest)
+    } else if let Some(rest) = strip_var(raw, "%USERPROFILE%") {
+        (drives.profile.clone(), rest)
+    } else if let Some(rest) =
+        strip_var(raw, "%SystemDrive%").or_else(|| strip_var(raw, "%HOMEDRIVE%"))
+    {
+        (drives.system.clone(), rest)
+    } else {
+        // `D:\...`; anything else is a share (`\\server\...`) or a variable
+        // that only exists on the running system
+        let (drive, rest) = raw.split_at_checked(2)?;
+        let letter = drive.strip_suffix(':')?.chars().next()?;
+        let (_, mount) = drives
+            .letters
+            .iter()
+            .find(|(l, _)| l.eq_ignore_ascii_case(&letter))?;
+        (mount.clone(), rest)
+    };
+    Some(locate(base, rest))
+}
+
+// Case-insensitive `raw` without the `var` prefix.
+fn strip_var<'a>(raw: &'a str, var: &str) -> Option<&'a str> {
+    let (prefix, rest) = raw.split_at_checked(var.len())?;
+    prefix.eq_ignore_ascii_case(var).then_some(rest)
+}
+
+// `base` joined with the `\`-separated `rest`, each part named as it is on
+// disk when a folder of that name exists in any case.
+fn locate(mut base: PathBuf, rest: &str) -> PathBuf {
+    for part in rest.split('\\').filter(|p| !p.is_empty() && *p != ".") {
+        let found = fs::read_dir(&base).ok().and_then(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.file_name())
+                .find(|n| n.to_string_lossy().eq_ignore_ascii_case(part))
+        });
+        match found {
+            Some(name) => base.push(name),
+            None => base.push(part),
+        }
+    }
+    base
+}
+
+/// Whether `raw` is where the folder is when it was never moved.
+pub fn is_default(raw: &str, value_name: &str) -> bool {
+    let Some(rest) = strip_var(raw.trim(), "%USERPROFILE%") else {
+        return false;
+    };
+    let default = match value_name {
+        "Personal" => "Documents",
+        "My Music" => "Music",
+        "My Pictures" => "Pictures",
+        "My Video" => "Videos",
+        "{374DE290-123F-4565-9164-39C4925E467B}" => "Downloads",
+        other => other,
+    };
+    rest.trim_matches('\\').eq_ignore_ascii_case(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_redirected_folders() {
+        let dir = std::env::temp_dir().join(format!("lindy_knownfolders_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let profile = dir.join("c/Users/ann");
+        fs::create_dir_all(profile.join("OneDrive/Documents")).unwrap();
+        fs::create_dir_all(dir.join("d/Pictures")).unwrap();
+        let drives = Drives {
+            profile: profile.clone(),
+            system: dir.join("c"),
+            letters: vec![('D', dir.join("d"))],
+        };
+
+        assert_eq!(
+            resolve("%USERPROFILE%\\onedrive\\Documents", &drives),
+            Some(profile.join("OneDrive/Documents"))
+        );
+        assert_eq!(
+            resolve("d:\\pictures\\", &drives),
+            Some(dir.join("d/Pictures"))
+        );
+        assert_eq!(
+            resolve("%SystemDrive%\\Users\\ann\\Music", &drives),
+            Some(profile.join("Music"))
+        );
+        // not mounted, a share, and a variable of the running system
+        assert_eq!(resolve("E:\\Videos", &drives), None);
+        assert_eq!(resolve("\\\\nas\\home\\Documents", &drives), None);
+        assert_eq!(resolve("%OneDrive%\\Desktop", &drives), None);
+
+        assert!(is_default("%USERPROFILE%\\Documents", "Personal"));
+        assert!(!is_default(
+            "%USERPROFILE%\\OneDrive\\Documents",
+            "Personal"
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,38 +1,188 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod backups;
+mod bundle;
+mod cli;
+mod consistency;
+mod fstab;
+mod helper;
+mod knownfolders;
+mod lsblk;
+mod metadata;
+mod mountinfo;
+mod mountopts;
+mod ntfs;
+mod plan;
+mod profile;
+mod registry;
+mod response;
+mod score;
+mod tmpfile;
+mod validate;
+mod watcher;
+
+use fstab::FstabFile;
+use metadata::{BlockMetadata, MetadataStore};
+use response::{LindyError, LindyResponse};
 use std::io::Write;
-use std::os::unix::fs::PermissionsExt;
 use std::process::Command;
+use tauri::Emitter;
+
+/// Installed location of the privileged helper. The polkit policy
+/// (polkit/com.dovndev.lindy.policy) maps this exact path to the
+/// `com.dovndev.lindy.manage-mounts` action, which allows `auth_admin_keep`.
+const HELPER_PATH: &str = "/usr/lib/lindy/lindy-helper";
+
+// Locate the privileged helper binary: next to the app binary in development
+// builds (authorized through pkexec's generic action), HELPER_PATH otherwise.
+fn helper_path() -> std::path::PathBuf {
+    let sibling = std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name("lindy-helper"));
+    match sibling {
+        Some(p) if p.exists() => p,
+        _ => std::path::PathBuf::from(HELPER_PATH),
+    }
+}
 
-// Helper to run a constructed shell script via pkexec using a temp file.
-// This avoids complex shell-quoting issues when passing a big script to
-// `sh -c '...'` and is more portable across distros / shells.
-fn run_pkexec_with_script(script: &str) -> Result<std::process::Output, std::io::Error> {
-    use std::fs;
-    // removed unused import
-    use std::time::{SystemTime, UNIX_EPOCH};
+// Run one request through `pkexec lindy-helper`, passing it as JSON on stdin.
+// Fails when pkexec cannot be spawned or the helper produced no response
+// (polkit prompt dismissed, helper missing).
+fn run_privileged(request: &helper::Request) -> Result<helper::Response, LindyError> {
+    use std::process::Stdio;
+
+    let input = serde_json::to_string(request).unwrap();
+    let child = Command::new("pkexec")
+        .arg(helper_path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(LindyError::SpawnPkexecFailed {
+                message: format!("failed to spawn pkexec: {}", e),
+            })
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // pkexec may exit without reading (e.g. authentication dismissed)
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| LindyError::io(format!("failed to wait for pkexec: {}", e)))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let pid = std::process::id();
-    let path = format!("/tmp/lindy-pkexec-{}-{}.sh", pid, now);
-    fs::write(&path, script)?;
-    // make it executable (0700)
-    let mut perms = fs::metadata(&path)?.permissions();
-    perms.set_mode(0o700);
-    fs::set_permissions(&path, perms)?;
-
-    let out = Command::new("pkexec").arg("sh").arg(&path).output();
-
-    // best-effort remove the temporary script
-    let _ = fs::remove_file(&path); // best-effort cleanup, ignore result
-    out
+    if let Some(mut f) = helper_log() {
+        let _ = writeln!(
+            f,
+            "helper request={} exit={:?} stdout=---\n{}--- stderr=---\n{}---",
+            serde_json::to_string(&request.redacted()).unwrap(),
+            output.status.code(),
+            stdout,
+            stderr
+        );
+    }
+
+    serde_json::from_str::<helper::Response>(stdout.trim()).map_err(|_| LindyError::PkexecFailed {
+        message: format!("pkexec exited with code {:?}", output.status.code()),
+        stdout,
+        stderr,
+    })
+}
+
+// The per-user log of helper runs, `$XDG_STATE_HOME/lindy/helper.log`
+// (`~/.local/state` by default), opened for appending. It is private to the
+// user and never opened through a symlink.
+fn helper_log() -> Option<std::fs::File> {
+    use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .map(std::path::PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| {
+            std::env::var_os("HOME").map(|h| std::path::Path::new(&h).join(".local/state"))
+        })?;
+    let dir = state.join("lindy");
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .ok()?;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(dir.join("helper.log"))
+        .ok()
+}
+
+// Error returned when a candidate fstab fails pre-flight validation.
+fn validation_failed(diagnostics: Vec<validate::Diagnostic>) -> LindyError {
+    LindyError::ValidationFailed {
+        message: "candidate fstab failed validation; /etc/fstab was not modified".into(),
+        diagnostics,
+    }
+}
+
+// Result of a dry run.
+fn planned(plan: plan::Plan) -> LindyResponse {
+    LindyResponse::Planned {
+        message: format!(
+            "dry run: {} operation(s), {} conflict(s); nothing was changed",
+            plan.operations.len(),
+            plan.conflicts.len()
+        ),
+        plan,
+    }
+}
+
+// Dry run of appending `block`: the helper's plan, with validation errors
+// and the caller's `conflicts` added to the predicted conflicts.
+fn plan_append(block: &str, id: &str, conflicts: Vec<String>) -> Result<LindyResponse, LindyError> {
+    let current = FstabFile::read("/etc/fstab").unwrap_or_default();
+    let mounts = mountinfo::MountTable::read().unwrap_or_default();
+    let mut plan = plan::Plan::apply(&current, id, block, unix_now(), |p| {
+        mounts.is_mount_point(p)
+    });
+    let candidate = validate::Candidate::with_appended(Some(&current), block);
+    let diagnostics = validate::check(&candidate)
+        .map_err(|e| LindyError::io(format!("failed to write temp file: {}", e)))?;
+    let errors = diagnostics
+        .into_iter()
+        .filter(|d| d.severity == validate::Severity::Error)
+        .map(|d| match d.line {
+            Some(line) => format!("line {}: {}", line, d.message),
+            None => d.message,
+        });
+    plan.conflicts
+        .splice(0..0, conflicts.into_iter().chain(errors));
+    Ok(planned(plan))
+}
+
+// Dry run of removing a block by id or target.
+fn plan_removal(
+    id: Option<&str>,
+    target: Option<&str>,
+    force: bool,
+) -> Result<LindyResponse, LindyError> {
+    let fstab = FstabFile::read("/etc/fstab")
+        .map_err(|e| LindyError::io(format!("failed reading /etc/fstab: {}", e)))?;
+    let mounts = mountinfo::MountTable::read().unwrap_or_default();
+    let plan = plan::Plan::remove(&fstab, id, target, force, unix_now(), |p| {
+        mounts.is_mount_point(p)
+    })?;
+    Ok(planned(plan))
 }
 
-fn get_meta_dir() -> String {
-    let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
-    format!("{}/.local/share/lindy", home)
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
 }
 
 #[tauri::command]
@@ -45,17 +195,46 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .setup(|app| {
+            // push /etc/fstab and mount table changes made outside the app
+            let handle = app.handle().clone();
+            let spawned = watcher::spawn(move |event| {
+                let sent = match event {
+                    watcher::Event::Fstab(change) => handle.emit(watcher::FSTAB_CHANGED, change),
+                    watcher::Event::Mount(change) => handle.emit(watcher::MOUNT_CHANGED, change),
+                };
+                if let Err(e) = sent {
+                    eprintln!("lindy: failed to emit watcher event: {}", e);
+                }
+            });
+            if let Err(e) = spawned {
+                eprintln!("lindy: could not start the fstab watcher: {}", e);
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             generate_fstab_line,
             list_partitions,
+            filesystem_drivers,
             apply_fstab_block,
+            update_fstab_block,
+            validate_fstab_block,
             list_fstab_blocks,
             remove_fstab_block,
             perform_mounts,
             adopt_block,
             find_block_for_target,
             remove_block_for_target,
+            unlock_bitlocker,
+            check_consistency,
+            repair_consistency,
+            export_mappings,
+            import_mappings,
+            list_fstab_backups,
+            diff_fstab_backup,
+            restore_fstab_backup,
+            prune_fstab_backups,
             detect_user_folders,
             suggest_folder_mappings,
             detect_windows_partitions,
@@ -65,9 +244,21 @@ pub fn run() {
         .expect("error while running tauri application");
 }
 
+/// Entry point of the headless `lindy <command>` mode; returns the exit code.
+pub fn run_cli(args: Vec<String>) -> i32 {
+    cli::main(args)
+}
+
+/// Entry point of the `lindy-helper` binary that pkexec runs as root.
+pub fn run_helper() -> i32 {
+    helper::main()
+}
+
 /// High-level command that performs the privileged sequence to apply a block and activate mounts.
 /// This currently wraps `apply_fstab_block` to reuse its logic, but exists as a dedicated
 /// entrypoint for the frontend to call when it wants a single in-app privileged operation.
+/// With `dry_run` it returns the plan (`LindyResponse::Planned`) and changes nothing.
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
 fn perform_mounts(
     block: &str,
@@ -77,11 +268,11 @@ fn perform_mounts(
     base_mount: Option<String>,
     add_partition_line: bool,
     force: Option<bool>,
-) -> Result<String, String> {
-    use std::fs;
-    use std::time::{SystemTime, UNIX_EPOCH};
-
+    driver: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<LindyResponse, LindyError> {
     let do_force = force.unwrap_or(false);
+    let current = FstabFile::read("/etc/fstab").ok();
 
     // Prepare block text, possibly inserting partition line
     let mut new_block = block.to_string();
@@ -89,12 +280,19 @@ fn perform_mounts(
         if let Some(uuid) = partition_uuid {
             let uuid_trim = uuid.trim();
             if !uuid_trim.is_empty() {
+                let base = base_mount.unwrap_or_else(|| String::from("/mnt/shared"));
                 let has_partition_line = new_block.lines().any(|l| {
                     l.trim_start().starts_with("UUID=") || l.contains(" x-systemd.automount ")
                 });
-                if !has_partition_line {
-                    let base = base_mount.unwrap_or_else(|| String::from("/mnt/shared"));
-                    let partition_line = format!("UUID={} {} auto defaults,noatime,nofail,x-systemd.automount,x-systemd.device-timeout=10 0 2", uuid_trim, base);
+                // a second set of folders from a partition that is already
+                // mounted there by /etc/fstab only needs its binds
+                let spec = format!("UUID={}", uuid_trim);
+                let already_mounted = current
+                    .as_ref()
+                    .is_some_and(|f| f.entries().any(|e| e.spec == spec && e.file == base));
+                if !has_partition_line && !already_mounted {
+                    let driver = partition_driver(uuid_trim, driver.as_deref())?;
+                    let partition_line = partition_line(uuid_trim, &base, driver, None);
                     if let Some(first_nl) = new_block.find('\n') {
                         let first = &new_block[..first_nl];
                         let rest = &new_block[first_nl + 1..];
@@ -111,511 +309,319 @@ fn perform_mounts(
         }
     }
 
-    // write temp file
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let tmp_path = format!("/tmp/lindy-fstab-{}-{}.tmp", id, now);
-    match fs::File::create(&tmp_path) {
-        Ok(mut f) => {
-            if let Err(e) = f.write_all(new_block.as_bytes()) {
-                let _ = fs::remove_file(&tmp_path);
-                return Err(format!("failed to write temp file: {}", e));
-            }
-        }
-        Err(e) => return Err(format!("failed to create temp file: {}", e)),
+    if dry_run.unwrap_or(false) {
+        return plan_append(&new_block, id, Vec::new());
+    }
+
+    // Validate the candidate /etc/fstab (current file + new block) before
+    // anything is recorded or persisted.
+    let candidate = validate::Candidate::with_appended(current.as_ref(), &new_block);
+    let diagnostics = validate::check(&candidate)
+        .map_err(|e| LindyError::io(format!("failed to write temp file: {}", e)))?;
+    if validate::has_errors(&diagnostics) {
+        return Err(validation_failed(diagnostics));
     }
 
     // Proactively write metadata so the app keeps track of the mapping even if
     // the privileged persistence step (pkexec append to /etc/fstab) fails.
     // We'll update this metadata after attempting persistence to mark whether
     // it was actually written to /etc/fstab (persisted=true).
-    let meta_dir = get_meta_dir();
-    let _ = fs::create_dir_all(&meta_dir); // best-effort, ignore result
-    let meta_path = format!("{}/{}.json", meta_dir, id);
-    let initial_meta = serde_json::json!({
-        "id": id,
-        "block": new_block,
-        "targets": targets,
-        "installed_at": now,
-        "persisted": false,
-        "note": "pending persistence to /etc/fstab",
-    });
-    let _ = fs::write(
-        &meta_path,
-        serde_json::to_string_pretty(&initial_meta).unwrap_or_default(),
-    ); // best-effort
-
-    // local helper to shell-escape a path for single-quoted or double-quoted use
-    fn shell_escape_single_local(s: &str) -> String {
-        if !s.contains('\'') {
-            return format!("'{}'", s);
-        }
-        let mut out = String::new();
-        out.push('"');
-        for c in s.chars() {
-            if c == '"' || c == '\\' || c == '$' || c == '`' || c == '\n' {
-                out.push('\\');
-            }
-            out.push(c);
+    let store = MetadataStore::open();
+    let mut meta = BlockMetadata::new(id, &new_block, targets.clone(), unix_now());
+    meta.persist_error = Some("pending persistence to /etc/fstab".into());
+    let _ = store.save(&meta); // best-effort
+
+    // Persist and activate through the privileged helper. If activation fails
+    // the helper restores the backup and undoes partial mounts; with `force`
+    // it first retries after lazily unmounting the targets.
+    let request = helper::Request::ApplyBlock {
+        id: id.to_string(),
+        block: new_block.clone(),
+        targets: targets.clone(),
+        lazy_retry: do_force,
+    };
+    let result = run_privileged(&request);
+
+    match &result {
+        Ok(resp) if resp.ok => {
+            // Update metadata to mark persistence succeeded
+            meta.persisted = true;
+            meta.persisted_at = Some(unix_now());
+            meta.persist_error = None;
+            meta.persist_log = Some(resp.log.join("\n"));
         }
-        out.push('"');
-        out
-    }
-
-    // build privileged shell command
-    let backup = format!("/etc/fstab.lindy.bak.{}", now);
-    // We'll attempt mount -a; on failure we'll collect fuser output for each target and optionally retry with lazy unmount
-    let mut shell = String::new();
-    shell.push_str("set -e\n");
-    shell.push_str(&format!(
-        "cp /etc/fstab {backup} && cat {tmp} >> /etc/fstab && sync\n",
-        backup = backup,
-        tmp = tmp_path
-    ));
-
-    shell.push_str("echo 'Attempting mount -a'\n");
-    shell.push_str("if mount -a; then echo 'MOUNT_OK'; else\n");
-    // collect fuser output for targets
-    for t in &targets {
-        let esc = shell_escape_single_local(t);
-        shell.push_str(&format!(
-            "echo 'FUSER {t}:'; fuser -mv {esc} || true;\n",
-            t = t,
-            esc = esc
-        ));
-    }
-    if do_force {
-        shell.push_str("echo 'Attempting lazy unmount of targets'\n");
-        for t in &targets {
-            let esc = shell_escape_single_local(t);
-            shell.push_str(&format!("umount -l {esc} || true;\n", esc = esc));
+        Ok(resp) => {
+            // Record the failure but keep the record so the app knows there is
+            // an attempted mapping (not persisted).
+            meta.persist_error = Some(resp.message.clone());
+            meta.persist_log = Some(resp.log.join("\n"));
+            meta.rolled_back = resp.code == "rolled_back";
         }
-        shell.push_str("if mount -a; then echo 'MOUNT_OK_AFTER_LAZY'; else echo 'MOUNT_FAILED_AFTER_LAZY'; exit 4; fi\n");
-    } else {
-        shell.push_str("echo 'MOUNT_FAILED_DUE_TO_BUSY'; exit 3; fi\n");
-    }
-
-    // execute via pkexec using a temporary script file to avoid shell quoting pitfalls
-    let output = match run_pkexec_with_script(&shell) {
-        Ok(o) => o,
-        Err(e) => {
-            let resp = serde_json::json!({
-                "status": "error",
-                "code": "spawn_pkexec_failed",
-                "message": format!("failed to spawn pkexec: {}", e),
-                "stdout": "",
-                "stderr": "",
-            });
-            let _ = fs::remove_file(&tmp_path);
-            return Ok(serde_json::to_string(&resp).unwrap());
+        Err(_) => {
+            meta.persist_error = Some("privileged helper did not run".into());
         }
-    };
-
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-    // Clean up temp file
-    let _ = fs::remove_file(&tmp_path); // best-effort cleanup
-
-    if output.status.success()
-        || stdout.contains("MOUNT_OK")
-        || stdout.contains("MOUNT_OK_AFTER_LAZY")
-    {
-        // Update metadata to mark persistence succeeded
-        let now2 = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let meta_path = format!("{}/{}.json", meta_dir, id);
-        let mut meta_obj = serde_json::json!({
-            "id": id,
-            "block": new_block,
-            "targets": targets,
-            "installed_at": now,
-        });
-        meta_obj["persisted"] = serde_json::Value::Bool(true);
-        meta_obj["persisted_at"] = serde_json::json!(now2);
-        meta_obj["persist_stdout"] = serde_json::json!(stdout.clone());
-        meta_obj["persist_stderr"] = serde_json::json!(stderr.clone());
-        let _ = fs::write(
-            &meta_path,
-            serde_json::to_string_pretty(&meta_obj).unwrap_or_default(),
-        ); // best-effort
-        let resp = serde_json::json!({
-            "status": "ok",
-            "code": "applied",
-            "message": "fstab block appended and mount -a executed",
-            "stdout": stdout,
-            "stderr": stderr,
-        });
-        Ok(serde_json::to_string(&resp).unwrap())
-    } else {
-        // Update metadata to record persistence failure but keep the record so the app
-        // knows there is an attempted mapping (not persisted).
-        let meta_path = format!("{}/{}.json", meta_dir, id);
-        let mut meta_obj = serde_json::json!({
-            "id": id,
-            "block": new_block,
-            "targets": targets,
-            "installed_at": now,
-        });
-        meta_obj["persisted"] = serde_json::Value::Bool(false);
-        meta_obj["persist_error"] =
-            serde_json::json!(format!("pkexec exit code: {:?}", output.status.code()));
-        meta_obj["persist_stdout"] = serde_json::json!(stdout.clone());
-        meta_obj["persist_stderr"] = serde_json::json!(stderr.clone());
-        let _ = fs::write(
-            &meta_path,
-            serde_json::to_string_pretty(&meta_obj).unwrap_or_default(),
-        ); // best-effort
-
-        // check exit code to determine busy vs other failure
-        let code = output.status.code();
-        let resp = if code == Some(3) || stdout.contains("MOUNT_FAILED_DUE_TO_BUSY") {
-            serde_json::json!({
-                "status": "error",
-                "code": "busy",
-                "message": "mount failed due to busy targets; fuser output included",
-                "stdout": stdout,
-                "stderr": stderr,
-            })
-        } else if code == Some(4) || stdout.contains("MOUNT_FAILED_AFTER_LAZY") {
-            serde_json::json!({
-                "status": "error",
-                "code": "mount_failed_after_lazy",
-                "message": "mount -a failed even after lazy unmount attempts",
-                "stdout": stdout,
-                "stderr": stderr,
-            })
-        } else {
-            serde_json::json!({
-                "status": "error",
-                "code": "pkexec_failed",
-                "message": format!("pkexec exited with code {:?}", code),
-                "stdout": stdout,
-                "stderr": stderr,
-            })
-        };
-        Ok(serde_json::to_string(&resp).unwrap())
     }
+    let _ = store.save(&meta); // best-effort
+    result.and_then(response::from_helper)
 }
 
 #[tauri::command]
-fn adopt_block(id: &str) -> Result<String, String> {
-    use std::fs;
+fn adopt_block(id: &str) -> Result<LindyResponse, LindyError> {
     // read /etc/fstab and find the block text and targets for the given id
-    let content = fs::read_to_string("/etc/fstab")
-        .map_err(|e| format!("failed reading /etc/fstab: {}", e))?;
-    let mut in_block = false;
-    let mut block_lines: Vec<String> = Vec::new();
-    let mut targets: Vec<String> = Vec::new();
-    for line in content.lines() {
-        if line.contains(&format!("# lindy BEGIN: {}", id)) {
-            in_block = true;
-            block_lines.push(line.to_string());
-            continue;
-        }
-        if in_block {
-            block_lines.push(line.to_string());
-            if line.contains(&format!("# lindy END: {}", id)) {
-                break;
-            }
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 6 && parts[2] == "none" && parts[3] == "bind" {
-                targets.push(parts[1].to_string());
-            }
-        }
-    }
-    // removed unused found check
-    let block_text = block_lines.join("\n") + "\n";
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let meta = serde_json::json!({
-        "id": id,
-        "block": block_text,
-        "targets": targets,
-        "installed_at": now,
-        "installed_at": now,
-    });
-    let meta_dir = get_meta_dir();
-    let _ = fs::create_dir_all(&meta_dir); // best-effort
-    let meta_path = format!("{}/{}.json", meta_dir, id);
-    fs::write(
-        &meta_path,
-        serde_json::to_string_pretty(&meta).unwrap_or_default(),
-    )
-    .map_err(|e| format!("failed to write metadata: {}", e))?;
-    let resp = serde_json::json!({
-        "status": "ok",
-        "code": "adopted",
-        "message": format!("adopted block {}", id),
-    });
-    Ok(serde_json::to_string(&resp).unwrap())
+    let fstab = FstabFile::read("/etc/fstab")
+        .map_err(|e| LindyError::io(format!("failed reading /etc/fstab: {}", e)))?;
+    let block = fstab
+        .block(id)
+        .ok_or_else(|| LindyError::not_found(format!("block {} is not in /etc/fstab", id)))?;
+    let (block_text, targets) = (block.text() + "\n", block.targets());
+    let mut meta = BlockMetadata::new(id, &block_text, targets, unix_now());
+    meta.persisted = true;
+    MetadataStore::open()
+        .save(&meta)
+        .map_err(|e| LindyError::io(format!("failed to write metadata: {}", e)))?;
+    Ok(LindyResponse::Adopted {
+        message: format!("adopted block {}", id),
+        id: id.to_string(),
+    })
 }
 
 /// Append a marked fstab block to /etc/fstab (requires elevation via pkexec).
 /// The frontend should send a full block including BEGIN/END markers. This command:
-/// - writes the block to a secure temp file
-/// - runs pkexec to back up /etc/fstab and append the block, then runs `mount -a`
-/// - returns stdout/stderr or an error string
+/// - validates the candidate /etc/fstab
+/// - asks the privileged helper (via pkexec) to back up /etc/fstab, append the block and run `mount -a`
+/// - returns the helper's result, or the reason nothing was changed
+///
+/// With `dry_run` it returns the plan instead, listing every target conflict.
 #[tauri::command]
-fn apply_fstab_block(block: &str, id: &str, targets: Vec<String>) -> Result<String, String> {
-    use std::fs;
-    use std::time::{SystemTime, UNIX_EPOCH};
+fn apply_fstab_block(
+    block: &str,
+    id: &str,
+    targets: Vec<String>,
+    dry_run: Option<bool>,
+) -> Result<LindyResponse, LindyError> {
+    let store = MetadataStore::open();
+
+    if dry_run.unwrap_or(false) {
+        let conflicts = targets
+            .iter()
+            .filter_map(
+                |t| match check_targets(&store, id, std::slice::from_ref(t)) {
+                    Ok(None) => None,
+                    Ok(Some(resp)) => Some(resp.message().to_string()),
+                    Err(e) => Some(e.message().to_string()),
+                },
+            )
+            .collect();
+        return plan_append(block, id, conflicts);
+    }
+    if let Some(resp) = check_targets(&store, id, &targets)? {
+        return Ok(resp);
+    }
+
+    // Sanity-check inputs
+    if block.trim().is_empty() {
+        return Err(LindyError::invalid("empty block"));
+    }
+    if id.trim().is_empty() {
+        return Err(LindyError::invalid("missing id"));
+    }
+
+    // Build the candidate /etc/fstab and validate it
+    let current = FstabFile::read("/etc/fstab").ok();
+    let candidate = validate::Candidate::with_appended(current.as_ref(), block);
+    let now = unix_now();
+    let diagnostics = validate::check(&candidate)
+        .map_err(|e| LindyError::io(format!("failed to write temp file: {}", e)))?;
+    if validate::has_errors(&diagnostics) {
+        return Err(validation_failed(diagnostics));
+    }
+
+    // Persist and activate through the privileged helper
+    let request = helper::Request::ApplyBlock {
+        id: id.to_string(),
+        block: block.to_string(),
+        targets: targets.clone(),
+        lazy_retry: false,
+    };
+    let resp = run_privileged(&request)?;
 
-    // Prevent accidental duplicate target mountpoints: ensure none of the requested
-    // targets are already present in /etc/fstab or in existing metadata entries.
-    // This avoids creating duplicate mountpoints which cause duplicate icons.
+    if resp.ok {
+        // Write metadata for this install so we can manage it later
+        let mut meta = BlockMetadata::new(id, block, targets, now);
+        meta.persisted = true;
+        meta.persisted_at = Some(unix_now());
+        let _ = store.save(&meta);
+    }
+    response::from_helper(resp)
+}
+
+// Prevent accidental duplicate target mountpoints: ensure none of the requested
+// targets are already present in /etc/fstab or in existing metadata entries.
+// This avoids creating duplicate mountpoints which cause duplicate icons.
+// The metadata of block `id` itself (a failed earlier attempt, or the record
+// a repair writes back) does not count. Returns the response to give instead
+// of applying, if any.
+fn check_targets(
+    store: &MetadataStore,
+    id: &str,
+    targets: &[String],
+) -> Result<Option<LindyResponse>, LindyError> {
     if !targets.is_empty() {
         // Read /etc/fstab once and scan for any existing references to the requested targets.
         // If a matching line belongs to a lindy marked block, and metadata for that block
         // is missing, automatically create metadata (adopt) instead of failing. If the matching
         // line is not inside a lindy block, reject to avoid creating duplicate targets.
-        if let Ok(fstab_str) = std::fs::read_to_string("/etc/fstab") {
-            // We'll iterate lines and keep track of whether we're inside a lindy block
-            // and what the current block id is so we can adopt it if needed.
-            let mut current_block_id: Option<String> = None;
-            // Map of block id -> Vec<target lines> (for later metadata creation)
-            use std::collections::HashMap;
-            let mut block_targets: HashMap<String, Vec<String>> = HashMap::new();
-            let mut block_texts: HashMap<String, Vec<String>> = HashMap::new();
-
-            for line in fstab_str.lines() {
-                if let Some(pos) = line.find("# lindy BEGIN:") {
-                    let id = line[pos + "# lindy BEGIN:".len()..].trim().to_string();
-                    current_block_id = Some(id.clone());
-                    block_texts
-                        .entry(id.clone())
-                        .or_default()
-                        .push(line.to_string());
-                    continue;
-                }
-                if let Some(ref id) = current_block_id {
-                    block_texts
-                        .entry(id.clone())
-                        .or_default()
-                        .push(line.to_string());
-                    if line.contains("# lindy END:") {
-                        current_block_id = None;
-                        continue;
-                    }
-                    // inspect bind lines inside block
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 6 && parts[2] == "none" && parts[3] == "bind" {
-                        let target = parts[1].to_string();
-                        block_targets.entry(id.clone()).or_default().push(target);
-                    }
-                    continue;
-                }
-                // not inside a block; nothing to record
-            }
+        if let Ok(fstab) = FstabFile::read("/etc/fstab") {
+            let blocks = fstab.blocks();
 
             // Now check each requested target against blocks and non-block lines.
-            for t in &targets {
+            for t in targets {
                 // first, check whether any known block already contains this target
-                let mut found_in_block: Option<String> = None;
-                for (bid, tvec) in &block_targets {
-                    if tvec.iter().any(|x| x == t) {
-                        found_in_block = Some(bid.clone());
-                        break;
-                    }
-                }
-                if let Some(block_id) = found_in_block {
+                if let Some(block) = blocks.iter().find(|b| b.binds().any(|e| &e.file == t)) {
                     // If metadata already exists for this block, treat as duplicate/managed
-                    let meta_dir = get_meta_dir();
-                    let meta_path = format!("{}/{}.json", meta_dir, block_id);
-                    if std::path::Path::new(&meta_path).exists() {
-                        return Err(format!(
-                            "target {} already managed by app (metadata {}).",
-                            t, meta_path
-                        ));
-                    }
-                    // Metadata missing: adopt the block by writing metadata derived from the block text
-                    if let Some(text_lines) = block_texts.get(&block_id) {
-                        let block_text = text_lines.join("\n") + "\n";
-                        // Metadata is missing: inform the caller that an existing managed block could be adopted.
-                        let resp = serde_json::json!({
-                            "status": "adoptable",
-                            "code": "adoptable_existing_block",
-                            "message": format!("target {} already present in /etc/fstab inside block {}; adopt to let app manage it.", t, block_id),
-                            "id": block_id,
-                            "block": block_text,
-                            "targets": block_targets.get(&block_id).cloned().unwrap_or_default(),
+                    if store.load(&block.id).is_some() {
+                        return Err(LindyError::AlreadyManaged {
+                            message: format!(
+                                "target {} already managed by app (block {}).",
+                                t, block.id
+                            ),
+                            target: t.clone(),
                         });
-                        return Ok(serde_json::to_string(&resp).unwrap());
                     }
+                    // Metadata is missing: inform the caller that an existing managed block could be adopted.
+                    return Ok(Some(LindyResponse::AdoptableExistingBlock {
+                        message: format!("target {} already present in /etc/fstab inside block {}; adopt to let app manage it.", t, block.id),
+                        id: block.id.clone(),
+                        block: block.text() + "\n",
+                        targets: block.targets(),
+                    }));
                 }
 
-                // Not found inside a lindy block: check raw /etc/fstab lines for exact target
-                for line in fstab_str.lines() {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 2 {
-                        if parts[1] == t {
-                            return Err(format!(
+                // Not found inside a lindy block: check every /etc/fstab entry for the exact target
+                for line in &fstab.lines {
+                    if line.entry().is_some_and(|e| &e.file == t) {
+                        return Err(LindyError::TargetInFstab {
+                            message: format!(
                                 "target {} already present in /etc/fstab (line: {})",
-                                t, line
-                            ));
-                        }
+                                t, line.raw
+                            ),
+                            target: t.clone(),
+                            line: line.raw.clone(),
+                        });
                     }
                 }
             }
         }
 
-        // Check metadata dir for existing managed targets (unchanged)
-        // Check metadata dir for existing managed targets (unchanged)
-        let meta_dir = get_meta_dir();
-        if let Ok(entries) = std::fs::read_dir(&meta_dir) {
-            for e in entries.flatten() {
-                if let Ok(s) = std::fs::read_to_string(e.path()) {
-                    if let Ok(j) = serde_json::from_str::<serde_json::Value>(&s) {
-                        if let Some(jtargets) = j.get("targets") {
-                            if let Some(arr) = jtargets.as_array() {
-                                for jt in arr {
-                                    if let Some(existing) = jt.as_str() {
-                                        for t in &targets {
-                                            if existing == t {
-                                                return Err(format!("target {} already managed by app (metadata {}).", t, e.path().display()));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        // Check metadata for existing managed targets
+        let records = store.list();
+        for t in targets {
+            let managed = records
+                .iter()
+                .find(|m| m.id != id && m.targets.iter().any(|x| x == t));
+            if let Some(meta) = managed {
+                return Err(LindyError::AlreadyManaged {
+                    message: format!("target {} already managed by app (block {}).", t, meta.id),
+                    target: t.clone(),
+                });
             }
         }
     }
+    Ok(None)
+}
 
-    // Sanity-check inputs
-    if block.trim().is_empty() {
-        return Err("empty block".into());
-    }
-    if id.trim().is_empty() {
-        return Err("missing id".into());
+/// Rewrite an existing lindy block in place (requires elevation via pkexec).
+/// Only the lines of block `id` change; every other line of /etc/fstab keeps its
+/// position and bytes. The rewritten file is validated first; bind targets
+/// that the new block drops or re-points are unmounted, /etc/fstab is replaced
+/// atomically (backup kept as `/etc/fstab.lindy.bak.<ts>`) and `mount -a`
+/// activates the new entries.
+#[tauri::command]
+fn update_fstab_block(id: &str, new_block: &str) -> Result<LindyResponse, LindyError> {
+    let fstab = FstabFile::read("/etc/fstab")
+        .map_err(|e| LindyError::io(format!("failed reading /etc/fstab: {}", e)))?;
+    let diagnostics = check_update(&fstab, id, new_block)?;
+    if validate::has_errors(&diagnostics) {
+        return Err(validation_failed(diagnostics));
     }
 
-    // Create a temp file in /tmp
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let tmp_path = format!("/tmp/lindy-fstab-{}-{}.tmp", id, now);
-    match fs::File::create(&tmp_path) {
-        Ok(mut f) => {
-            if let Err(e) = f.write_all(block.as_bytes()) {
-                let _ = fs::remove_file(&tmp_path);
-                return Err(format!("failed to write temp file: {}", e));
-            }
-        }
-        Err(e) => return Err(format!("failed to create temp file: {}", e)),
+    let resp = run_privileged(&helper::Request::UpdateBlock {
+        id: id.to_string(),
+        block: new_block.to_string(),
+    })?;
+
+    if resp.ok {
+        // Keep metadata in step with the rewritten block, preserving install time.
+        let now = unix_now();
+        let store = MetadataStore::open();
+        let mut meta = store
+            .load(id)
+            .unwrap_or_else(|| BlockMetadata::new(id, "", Vec::new(), now));
+        meta.block = resp.block.clone().unwrap_or_default();
+        meta.targets = resp.targets.clone();
+        meta.partition_uuid = metadata::partition_uuid(&meta.block);
+        meta.persisted = true;
+        meta.persist_error = None;
+        meta.rolled_back = false;
+        meta.updated_at = Some(now);
+        let _ = store.save(&meta);
     }
+    response::from_helper(resp)
+}
 
-    // Build the privileged shell command: backup fstab, append temp file, run mount -a
-    let backup = format!("/etc/fstab.lindy.bak.{}", now);
-    let cmd = format!(
-        "cp /etc/fstab {backup} && cat {tmp} >> /etc/fstab && mount -a",
-        backup = backup,
-        tmp = tmp_path
-    );
-
-    // Execute via pkexec so a polkit prompt appears
-    // Log the command for debugging (append-only)
-    let _ = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("/tmp/lindy-debug.log")
-        .and_then(|mut f| {
-            let _ = writeln!(
-                f,
-                "[{}] apply_fstab_block id={} cmd=---\n{}---",
-                now, id, cmd
-            );
-            Ok(())
-        });
-
-    let output = match run_pkexec_with_script(&cmd) {
-        Ok(o) => o,
-        Err(e) => {
-            // Return structured JSON describing the failure to spawn pkexec
-            let resp = serde_json::json!({
-                "status": "error",
-                "code": "spawn_pkexec_failed",
-                "message": format!("failed to spawn pkexec: {}", e),
-                "stdout": "",
-                "stderr": "",
-            });
-            return Ok(serde_json::to_string(&resp).unwrap());
-        }
-    };
-
-    // Clean up temp file
-    let _ = fs::remove_file(&tmp_path);
-
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-    let _ = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("/tmp/lindy-debug.log")
-        .and_then(|mut f| {
-            let _ = writeln!(
-                f,
-                "[{}] apply_fstab_block id={} exit={:?} stdout=---\n{}--- stderr=---\n{}---",
-                now,
-                id,
-                output.status.code(),
-                stdout,
-                stderr
-            );
-            Ok(())
-        });
+// Validate `fstab` with block `id` rewritten to `new_block`. The rewrite is
+// planned on a copy first so obvious mistakes (unknown id, a target used
+// elsewhere) fail without an authentication prompt; the helper repeats
+// that check as root.
+fn check_update(
+    fstab: &FstabFile,
+    id: &str,
+    new_block: &str,
+) -> Result<Vec<validate::Diagnostic>, LindyError> {
+    let mut rewritten = fstab.clone();
+    helper::plan_update(&mut rewritten, id, new_block)?;
+    let candidate = validate::Candidate::with_replaced(rewritten, id)
+        .ok_or_else(|| LindyError::not_found(format!("block id {} not found", id)))?;
+    validate::check(&candidate)
+        .map_err(|e| LindyError::io(format!("failed to write temp file: {}", e)))
+}
 
-    if output.status.success() {
-        // Write metadata for this install so we can manage it later
-        let meta_dir = get_meta_dir();
-        let _ = fs::create_dir_all(&meta_dir);
-        let meta_path = format!("{}/{}.json", meta_dir, id);
-        let meta = serde_json::json!({
-            "id": id,
-            "block": block,
-            "targets": targets,
-            "installed_at": now,
-        });
-        let _ = fs::write(
-            &meta_path,
-            serde_json::to_string_pretty(&meta).unwrap_or_default(),
-        );
-        let resp = serde_json::json!({
-            "status": "ok",
-            "code": "applied",
-            "message": "fstab block appended and mount -a executed",
-            "stdout": stdout,
-            "stderr": stderr,
-        });
-        Ok(serde_json::to_string(&resp).unwrap())
-    } else {
-        let resp = serde_json::json!({
-            "status": "error",
-            "code": "pkexec_failed",
-            "message": format!("pkexec exited with code {:?}", output.status.code()),
-            "stdout": stdout,
-            "stderr": stderr,
-        });
-        Ok(serde_json::to_string(&resp).unwrap())
-    }
+/// Validate a block against the current /etc/fstab without changing anything.
+/// Returns every diagnostic (errors and warnings) for the candidate file that
+/// `apply_fstab_block` / `perform_mounts` would write.
+#[tauri::command]
+fn validate_fstab_block(block: &str) -> Result<Vec<validate::Diagnostic>, LindyError> {
+    let current = FstabFile::read("/etc/fstab").ok();
+    let candidate = validate::Candidate::with_appended(current.as_ref(), block);
+    validate::check(&candidate)
+        .map_err(|e| LindyError::io(format!("failed to write temp file: {}", e)))
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ts_rs::TS)]
+#[ts(export)]
 struct FstabBind {
     src: String,
     target: String,
+    kind: Option<fstab::BindKind>,
+    propagation: Option<String>,
+    // live state from /proc/self/mountinfo; None when it could not be read
+    mount: Option<mountinfo::BindState>,
 }
-#[derive(serde::Serialize)]
+
+impl From<&fstab::FstabEntry> for FstabBind {
+    fn from(e: &fstab::FstabEntry) -> Self {
+        FstabBind {
+            src: e.spec.clone(),
+            target: e.file.clone(),
+            kind: e.bind_kind(),
+            propagation: e.propagation().map(|p| p.to_string()),
+            mount: None,
+        }
+    }
+}
+
+#[derive(serde::Serialize, ts_rs::TS)]
+#[ts(export)]
 struct FstabBlock {
     id: String,
     text: String,
@@ -625,11 +631,10 @@ struct FstabBlock {
     managed: bool,
 }
 
-/// Scan /etc/fstab for lindy marked blocks and return them.
+/// Scan /etc/fstab for lindy marked blocks and return them, with the live
+/// mount state of every bind.
 #[tauri::command]
-fn list_fstab_blocks() -> Result<Vec<FstabBlock>, String> {
-    use std::fs;
-
+fn list_fstab_blocks() -> Result<Vec<FstabBlock>, LindyError> {
     let mut blocks = Vec::new();
 
     // Try to read /etc/fstab directly. If we cannot read it (permission denied)
@@ -637,113 +642,58 @@ fn list_fstab_blocks() -> Result<Vec<FstabBlock>, String> {
     // the fstab at app startup would cause a polkit prompt (double prompts
     // when the app also performs a privileged operation). Instead, if reading
     // fails, return only metadata-managed blocks below.
-    let content_opt = match fs::read_to_string("/etc/fstab") {
-        Ok(s) => Some(s),
-        Err(_) => None,
-    };
-
-    if let Some(content) = content_opt {
-        let mut lines = content.lines();
-
+    if let Ok(fstab) = FstabFile::read("/etc/fstab") {
         // First collect blocks found in /etc/fstab
-        while let Some(line) = lines.next() {
-            if let Some(pos) = line.find("# lindy BEGIN:") {
-                // extract id
-                let id = line[pos + "# lindy BEGIN:".len()..].trim().to_string();
-                let mut block_lines = Vec::new();
-                block_lines.push(line.to_string());
-                let mut targets = Vec::new();
-                // collect until END
-                for l in &mut lines {
-                    block_lines.push(l.to_string());
-                    if l.contains("# lindy END:") {
-                        break;
-                    }
-                    // detect bind lines: format: <src> <target> none bind 0 0
-                    let parts: Vec<&str> = l.split_whitespace().collect();
-                    if parts.len() >= 6 && parts[2] == "none" && parts[3] == "bind" {
-                        let target = parts[1].to_string();
-                        targets.push(target);
-                    }
-                }
-
-                // Parse binds from the collected text for frontend restoration
-                let mut binds = Vec::new();
-                for l in &block_lines {
-                    let parts: Vec<&str> = l.split_whitespace().collect();
-                    if parts.len() >= 6 && parts[2] == "none" && parts[3] == "bind" {
-                        binds.push(FstabBind {
-                            src: parts[0].to_string(),
-                            target: parts[1].to_string(),
-                        });
-                    }
-                }
+        for b in fstab.blocks() {
+            // Parse binds from the block for frontend restoration
+            let binds = b.binds().map(FstabBind::from).collect();
+
+            // managed will be set below if metadata exists
+            blocks.push(FstabBlock {
+                targets: b.targets(),
+                text: b.text(),
+                id: b.id,
+                binds,
+                managed: false,
+            });
+        }
+    }
 
-                // managed will be set below if metadata exists
-                blocks.push(FstabBlock {
-                    id,
-                    text: block_lines.join("\n"),
-                    targets,
-                    binds,
-                    managed: false,
-                });
+    // Now read metadata to mark managed blocks and include metadata-only entries
+    for meta in MetadataStore::open().list() {
+        // find existing block by id
+        if let Some(b) = blocks.iter_mut().find(|b| b.id == meta.id) {
+            b.managed = true;
+            // If metadata contains a prettier block text, prefer it
+            if !meta.block.trim().is_empty() {
+                b.text = meta.block;
             }
+            continue;
         }
+        // otherwise create a metadata-only entry
+        let text = if meta.block.trim().is_empty() {
+            "(managed by app)".to_string()
+        } else {
+            meta.block
+        };
+        let binds = FstabFile::parse(&text)
+            .entries()
+            .filter(|e| e.is_bind())
+            .map(FstabBind::from)
+            .collect();
+
+        blocks.push(FstabBlock {
+            id: meta.id,
+            text,
+            targets: meta.targets,
+            binds,
+            managed: true,
+        });
     }
 
-    // Now read metadata directory to mark managed blocks and include metadata-only entries
-    let meta_dir = get_meta_dir();
-    if let Ok(entries) = fs::read_dir(&meta_dir) {
-        for e in entries.flatten() {
-            if let Ok(s) = fs::read_to_string(e.path()) {
-                if let Ok(j) = serde_json::from_str::<serde_json::Value>(&s) {
-                    if let Some(idv) = j.get("id").and_then(|x| x.as_str()) {
-                        let id = idv.to_string();
-                        // find existing block by id
-                        if let Some(b) = blocks.iter_mut().find(|b| b.id == id) {
-                            b.managed = true;
-                            // If metadata contains a prettier block text, prefer it
-                            if let Some(bt) = j.get("block").and_then(|x| x.as_str()) {
-                                b.text = bt.to_string();
-                            }
-                            continue;
-                        }
-                        // otherwise create a metadata-only entry
-                        let mut targets: Vec<String> = Vec::new();
-                        if let Some(tarr) = j.get("targets").and_then(|x| x.as_array()) {
-                            for tv in tarr {
-                                if let Some(ts) = tv.as_str() {
-                                    targets.push(ts.to_string());
-                                }
-                            }
-                        }
-                        let txt = j
-                            .get("block")
-                            .and_then(|x| x.as_str())
-                            .unwrap_or("(managed by app)")
-                            .to_string();
-
-                        let mut binds = Vec::new();
-                        for l in txt.lines() {
-                            let parts: Vec<&str> = l.split_whitespace().collect();
-                            if parts.len() >= 6 && parts[2] == "none" && parts[3] == "bind" {
-                                binds.push(FstabBind {
-                                    src: parts[0].to_string(),
-                                    target: parts[1].to_string(),
-                                });
-                            }
-                        }
-
-                        blocks.push(FstabBlock {
-                            id,
-                            text: txt,
-                            targets,
-                            binds,
-                            managed: true,
-                        });
-                    }
-                }
-            }
+    if let Ok(table) = mountinfo::MountTable::read() {
+        for bind in blocks.iter_mut().flat_map(|b| b.binds.iter_mut()) {
+            bind.mount = Some(table.bind_state(&bind.src, &bind.target));
         }
     }
 
@@ -753,8 +703,7 @@ fn list_fstab_blocks() -> Result<Vec<FstabBlock>, String> {
 /// Find the lindy block id (if any) that contains the given target path.
 /// Returns Some(id) when found, or None when no matching block exists.
 #[tauri::command]
-fn find_block_for_target(target: &str) -> Result<Option<String>, String> {
-    use std::fs;
+fn find_block_for_target(target: &str) -> Result<Option<String>, LindyError> {
     let t = target.trim();
     if t.is_empty() {
         return Ok(None);
@@ -762,454 +711,318 @@ fn find_block_for_target(target: &str) -> Result<Option<String>, String> {
     // scan /etc/fstab blocks first; try direct read. If permission prevents reading, do NOT invoke pkexec here
     // because callers (frontend) may want to avoid triggering multiple polkit prompts. If local read fails
     // we will fall back to scanning metadata only.
-    let content_opt = match fs::read_to_string("/etc/fstab") {
-        Ok(s) => Some(s),
-        Err(_) => None,
-    };
-    if let Some(content) = content_opt {
-        let mut lines = content.lines();
-        while let Some(line) = lines.next() {
-            if let Some(pos) = line.find("# lindy BEGIN:") {
-                let id = line[pos + "# lindy BEGIN:".len()..].trim().to_string();
-                // collect until END
-                for l in &mut lines {
-                    if l.contains("# lindy END:") {
-                        break;
-                    }
-                    let parts: Vec<&str> = l.split_whitespace().collect();
-                    if parts.len() >= 4 && parts[2] == "none" && parts[3] == "bind" {
-                        if parts[1] == t {
-                            return Ok(Some(id));
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // check metadata directory as fallback
-    let meta_dir = get_meta_dir();
-    if let Ok(entries) = fs::read_dir(&meta_dir) {
-        for e in entries.flatten() {
-            if let Ok(s) = fs::read_to_string(e.path()) {
-                if let Ok(j) = serde_json::from_str::<serde_json::Value>(&s) {
-                    if let Some(jtargets) = j.get("targets") {
-                        if let Some(arr) = jtargets.as_array() {
-                            for jt in arr {
-                                if let Some(ts) = jt.as_str() {
-                                    if ts == t {
-                                        if let Some(idv) = j.get("id").and_then(|x| x.as_str()) {
-                                            return Ok(Some(idv.to_string()));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    if let Ok(fstab) = FstabFile::read("/etc/fstab") {
+        if let Some(block) = fstab.block_for_target(t) {
+            return Ok(Some(block.id));
         }
     }
 
-    Ok(None)
+    // check metadata as fallback
+    Ok(MetadataStore::open().find_target(t).map(|m| m.id))
 }
 
 /// Find a block for a target then perform removal in a single operation.
 #[tauri::command]
-fn remove_block_for_target(target: &str, force: bool) -> Result<String, String> {
-    use std::fs;
-    use std::time::{SystemTime, UNIX_EPOCH};
-
+fn remove_block_for_target(
+    target: &str,
+    force: bool,
+    dry_run: Option<bool>,
+) -> Result<LindyResponse, LindyError> {
     if target.trim().is_empty() {
-        return Err("missing target".into());
+        return Err(LindyError::invalid("missing target"));
+    }
+    if dry_run.unwrap_or(false) {
+        return plan_removal(None, Some(target), force);
     }
 
-    // shell-escape single-quoted value (simple helper)
-    fn shell_escape_single(s: &str) -> String {
-        if !s.contains('\'') {
-            return format!("'{}'", s);
-        }
-        let mut out = String::new();
-        out.push('"');
-        for c in s.chars() {
-            if c == '"' || c == '\\' || c == '$' || c == '`' || c == '\n' {
-                out.push('\\');
+    // The helper finds the block that binds onto `target`, unmounts its
+    // targets, removes it from /etc/fstab and runs mount -a, in one prompt.
+    let resp = run_privileged(&helper::Request::RemoveBlock {
+        id: None,
+        target: Some(target.to_string()),
+        force,
+    })?;
+
+    if resp.ok {
+        // the helper reports the id, but also drop any metadata that names the target
+        let store = MetadataStore::open();
+        while let Some(meta) = store.find_target(target) {
+            if store.remove(&meta.id).is_err() {
+                break;
             }
-            out.push(c);
         }
-        out.push('"');
-        out
+        if let Some(id) = &resp.id {
+            let _ = store.remove(id);
+        }
     }
+    response::from_helper(resp)
+}
 
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let tgt_esc = shell_escape_single(target);
-    let newtmp = format!("/tmp/lindy-newfst-{}-{}.tmp", "by-target", now);
-
-    // Build a single privileged shell script that:
-    // 1) finds the block id that contains the given target
-    // 2) extracts bind targets for that block and unmounts them
-    // 3) writes a new fstab without the block and atomically replaces /etc/fstab
-    // 4) runs mount -a
-    let mut cmd = String::new();
-    cmd.push_str("set -e\n");
-
-    // find id containing the target
-    cmd.push_str("id=$(awk -v t=");
-    cmd.push_str(&tgt_esc);
-    cmd.push_str(" 'BEGIN{block=0;id=\"\"} /^# lindy BEGIN: /{id=$0; sub(/^.*BEGIN: /,\"\", id); block=1; next} /^# lindy END: /{block=0; next} block && $0 ~ /[[:space:]]none[[:space:]]bind[[:space:]]/ && $2==t {print id; exit}' /etc/fstab)\n");
-
-    cmd.push_str("if [ -z \"$id\" ]; then echo '{\"status\":\"error\",\"code\":\"not_found\",\"message\":\"no managed block found for target\"}'; exit 5; fi\n");
-
-    // collect bind targets into tmp file
-    cmd.push_str("awk -v id=\"$id\" 'BEGIN{in_block=0} $0 ~ (\"# lindy BEGIN: \" id) {in_block=1; next} $0 ~ (\"# lindy END: \" id) {in_block=0; next} in_block && $0 ~ /[[:space:]]none[[:space:]]bind[[:space:]]/ {print $2}' /etc/fstab > /tmp/lind_targets.$id\n");
-
-    // unmount targets
-    if force {
-        cmd.push_str("for t in $(cat /tmp/lind_targets.$id 2>/dev/null || true); do echo Attempt umount $t; umount \"$t\" || umount -l \"$t\" || true; done\n");
-    } else {
-        cmd.push_str("for t in $(cat /tmp/lind_targets.$id 2>/dev/null || true); do echo Attempt umount $t; if umount \"$t\"; then echo ok; else echo failed; exit 2; fi; done\n");
+/// Remove a marked fstab block by id: unmount targets, remove block from /etc/fstab, backup original.
+#[tauri::command]
+fn remove_fstab_block(
+    id: &str,
+    force: bool,
+    dry_run: Option<bool>,
+) -> Result<LindyResponse, LindyError> {
+    if id.trim().is_empty() {
+        return Err(LindyError::invalid("missing id"));
+    }
+    if dry_run.unwrap_or(false) {
+        return plan_removal(Some(id), None, force);
     }
 
-    // create new fstab without the block
-    cmd.push_str("awk -v id=\"$id\" 'BEGIN{skip=0} $0 ~ (\"# lindy BEGIN: \" id) {skip=1; next} $0 ~ (\"# lindy END: \" id) {skip=0; next} { if(!skip) print $0 }' /etc/fstab > ");
-    cmd.push_str(&newtmp);
-    cmd.push_str("\n");
-
-    cmd.push_str(&format!(
-        "cp /etc/fstab /etc/fstab.lindy.bak.{now} && mv {new} /etc/fstab && sync && mount -a\n",
-        now = now,
-        new = newtmp
-    ));
-
-    // Log command
-    let _ = fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("/tmp/lindy-debug.log")
-        .and_then(|mut f| {
-            let _ = writeln!(
-                f,
-                "[{}] remove_block_for_target target={} cmd=---\n{}---",
-                now, target, cmd
-            );
-            Ok(())
-        });
-
-    let output = match run_pkexec_with_script(&cmd) {
-        Ok(o) => o,
-        Err(e) => {
-            return Err(format!("failed to spawn pkexec: {}", e));
-        }
-    };
-
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-    let code = output.status.code();
+    // When /etc/fstab is readable, check the block exists before prompting;
+    // otherwise leave it to the helper, which reads the file as root.
+    if let Ok(mut fstab) = FstabFile::read("/etc/fstab") {
+        helper::plan_remove(&mut fstab, Some(id), None)?;
+    }
 
-    let _ = fs::OpenOptions::new().create(true).append(true).open("/tmp/lindy-debug.log").and_then(|mut f| {
-        let _ = writeln!(f, "[{}] remove_block_for_target target={} exit={:?} stdout=---\n{}--- stderr=---\n{}---", now, target, code, stdout, stderr);
-        Ok(())
-    });
+    let resp = run_privileged(&helper::Request::RemoveBlock {
+        id: Some(id.to_string()),
+        target: None,
+        force,
+    })?;
 
-    if output.status.success() {
-        // remove metadata file if present
-        // attempt to infer id from stdout? but safer to remove any metadata that contains the target
-        if let Ok(entries) = fs::read_dir(get_meta_dir()) {
-            for e in entries.flatten() {
-                if let Ok(s) = fs::read_to_string(e.path()) {
-                    if s.contains(target) {
-                        let _ = fs::remove_file(e.path());
-                    }
-                }
-            }
-        }
-        return Ok(stdout);
+    if resp.ok {
+        let _ = MetadataStore::open().remove(id);
     }
-    Err(format!("pkexec exited with code {:?}: {}", code, stderr))
+    response::from_helper(resp)
 }
 
-/// Remove a marked fstab block by id: unmount targets, remove block from /etc/fstab, backup original.
+/// Compare the lindy blocks in /etc/fstab with the metadata store and
+/// classify every id as in sync, fstab-only, metadata-only or divergent.
 #[tauri::command]
-fn remove_fstab_block(id: &str, force: bool) -> Result<String, String> {
-    use std::fs;
-    use std::time::{SystemTime, UNIX_EPOCH};
+fn check_consistency() -> Result<Vec<consistency::ConsistencyEntry>, LindyError> {
+    let fstab = FstabFile::read("/etc/fstab")
+        .map_err(|e| LindyError::io(format!("failed reading /etc/fstab: {}", e)))?;
+    Ok(consistency::check(&fstab, &MetadataStore::open().list()))
+}
 
-    if id.trim().is_empty() {
-        return Err("missing id".into());
+/// Apply one resolution per id, in order. Every action is re-checked against
+/// the current state first, so a stale choice is refused instead of applied;
+/// a failed action does not stop the ones after it.
+#[tauri::command]
+fn repair_consistency(
+    actions: Vec<consistency::RepairAction>,
+) -> Result<LindyResponse, LindyError> {
+    let store = MetadataStore::open();
+    let mut outcomes = Vec::new();
+    for action in actions {
+        let result = FstabFile::read("/etc/fstab")
+            .map_err(|e| LindyError::io(format!("failed reading /etc/fstab: {}", e)))
+            .and_then(|fstab| {
+                let entry = consistency::check(&fstab, &store.list())
+                    .into_iter()
+                    .find(|e| e.id == action.id)
+                    .ok_or_else(|| {
+                        LindyError::not_found(format!("no block or metadata with id {}", action.id))
+                    })?;
+                consistency::plan(&entry, action.resolution, &fstab, unix_now())
+                    .map_err(LindyError::invalid)
+            })
+            .and_then(|step| apply_repair_step(&store, &action.id, step));
+        let (message, error) = match result {
+            Ok(message) => (message, None),
+            Err(e) => (e.message().to_string(), Some(e)),
+        };
+        outcomes.push(consistency::RepairOutcome {
+            id: action.id,
+            resolution: action.resolution,
+            message,
+            error,
+        });
     }
+    let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+    Ok(LindyResponse::Repaired {
+        message: format!(
+            "{} of {} repairs applied",
+            outcomes.len() - failed,
+            outcomes.len()
+        ),
+        outcomes,
+    })
+}
 
-    // Attempt to read /etc/fstab to extract targets and prepare a new fstab.
-    // If reading fails due to permissions, fall back to building a privileged
-    // shell script that performs the same extraction and replacement under pkexec
-    // so the polkit prompt will appear and do the work as root.
-    let maybe_content = fs::read_to_string("/etc/fstab");
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    // If we could read /etc/fstab normally, use the in-process parsing path.
-    if let Ok(content) = maybe_content {
-        let mut out_lines: Vec<String> = Vec::new();
-        let mut in_block = false;
-        let found = false;
-        let mut targets: Vec<String> = Vec::new();
-
-        for line in content.lines() {
-            if line.contains(&format!("# lindy BEGIN: {}", id)) {
-                in_block = true;
-                continue;
-            }
-            if in_block {
-                if line.contains(&format!("# lindy END: {}", id)) {
-                    in_block = false;
-                    continue;
-                }
-                // collect targets from bind lines
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 6 && parts[2] == "none" && parts[3] == "bind" {
-                    let target = parts[1].to_string();
-                    // validate absolute path
-                    if !target.starts_with('/') {
-                        return Err(format!("invalid target path: {}", target));
-                    }
-                    targets.push(target);
-                }
-                continue; // skip lines inside block
-            }
-            out_lines.push(line.to_string());
+fn apply_repair_step(
+    store: &MetadataStore,
+    id: &str,
+    step: consistency::Step,
+) -> Result<String, LindyError> {
+    let io = |e: std::io::Error| LindyError::io(format!("failed to write metadata: {}", e));
+    match step {
+        consistency::Step::SaveMetadata(meta) => {
+            store.save(&meta).map_err(io)?;
+            Ok(format!("adopted block {}", id))
         }
-
-        if !found {
-            return Err(format!("block id {} not found", id));
+        consistency::Step::DeleteMetadata => {
+            store.remove(id).map_err(io)?;
+            Ok(format!("removed metadata for {}", id))
         }
-
-        // Build temp new fstab
-        let newfst = format!("/tmp/lindy-newfst-{}-{}.tmp", id, now);
-        fs::write(&newfst, out_lines.join("\n") + "\n")
-            .map_err(|e| format!("failed to write new fstab temp: {}", e))?;
-
-        // helper to shell-escape single-quoted string safely
-        fn shell_escape_single(s: &str) -> String {
-            if !s.contains('\'') {
-                return format!("'{}'", s);
-            }
-            let mut out = String::new();
-            out.push('"');
-            for c in s.chars() {
-                if c == '"' || c == '\\' || c == '$' || c == '`' || c == '\n' {
-                    out.push('\\');
+        consistency::Step::Apply { block, targets } => {
+            apply_fstab_block(&block, id, targets, None).map(|r| r.message().to_string())
+        }
+        consistency::Step::Update { block } => {
+            update_fstab_block(id, &block).map(|r| r.message().to_string())
+        }
+        consistency::Step::Privileged(request) => {
+            let resp = run_privileged(&request)?;
+            if resp.ok {
+                if let helper::Request::RemoveBlock { .. } = request {
+                    store.remove(id).map_err(io)?;
                 }
-                out.push(c);
             }
-            out.push('"');
-            out
+            response::from_helper(resp).map(|r| r.message().to_string())
         }
+    }
+}
 
-        // Build the shell command string
-        let mut cmd = String::new();
-        cmd.push_str("set -e\n");
-
-        // Unmount bind targets in reverse order to ensure children are unmounted before parents
-        for t in targets.iter().rev() {
-            if !t.starts_with('/') {
-                return Err(format!("invalid target path: {}", t));
-            }
-            let esc = shell_escape_single(t);
-            if force {
-                cmd.push_str(&format!("echo Attempting umount {t}\nif umount {esc}; then echo umount {t} succeeded; else echo umount {t} failed, trying lazy unmount; if umount -l {esc}; then echo lazy unmount {t} succeeded; else echo lazy unmount {t} failed; fi; fi\n", t = t, esc = esc));
-            } else {
-                cmd.push_str(&format!("echo Attempting umount {t}\nif umount {esc}; then echo umount {t} succeeded; else echo umount {t} failed; exit 2; fi\n", t = t, esc = esc));
-            }
-        }
+/// Bundle every managed block for moving to another installation.
+/// Partitions are recorded by UUID; see bundle.rs.
+#[tauri::command]
+fn export_mappings() -> Result<bundle::MappingBundle, LindyError> {
+    let home =
+        std::env::var("HOME").map_err(|_| LindyError::not_found("Could not get HOME directory"))?;
+    let fstab = FstabFile::read("/etc/fstab")
+        .map_err(|e| LindyError::io(format!("failed reading /etc/fstab: {}", e)))?;
+    let managed: Vec<String> = MetadataStore::open()
+        .list()
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    let partitions = list_partitions().unwrap_or_default();
+    Ok(bundle::export(
+        &fstab,
+        &managed,
+        &partitions,
+        &home,
+        unix_now(),
+    ))
+}
 
-        // Attempt to find and unmount a partition mountpoint inside the block (non-bind line)
-        let partition_mountpoint: Option<String> = None;
-        for line in content.lines() {
-            if line.contains(&format!("# lindy BEGIN: {}", id)) {
-                in_block = true;
+/// Re-create the blocks of a bundle written by `export_mappings`. Blocks
+/// already in /etc/fstab or whose partitions are missing are reported and
+/// skipped; a block that fails to apply does not stop the others.
+#[tauri::command]
+fn import_mappings(path: &str) -> Result<LindyResponse, LindyError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| LindyError::io(format!("failed to read {}: {}", path, e)))?;
+    let bundle: bundle::MappingBundle = serde_json::from_str(&text)
+        .map_err(|e| LindyError::invalid(format!("{} is not a lindy bundle: {}", path, e)))?;
+    if bundle.version > bundle::BUNDLE_VERSION {
+        return Err(LindyError::invalid(format!(
+            "bundle version {} is newer than this lindy supports ({})",
+            bundle.version,
+            bundle::BUNDLE_VERSION
+        )));
+    }
+    let home =
+        std::env::var("HOME").map_err(|_| LindyError::not_found("Could not get HOME directory"))?;
+    let partitions = list_partitions()?;
+
+    let mut outcomes = Vec::new();
+    for b in &bundle.blocks {
+        // re-read every time: the previous import changed the file
+        let fstab = FstabFile::read("/etc/fstab").unwrap_or_default();
+        let prepared = match bundle::prepare(b, &bundle.home, &home, &partitions, &fstab) {
+            Ok(p) => p,
+            Err(outcome) => {
+                outcomes.push(*outcome);
                 continue;
             }
-            if in_block {
-                if line.contains(&format!("# lindy END: {}", id)) {
-                    break;
-                }
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 6 && parts[2] == "none" && parts[3] == "bind" {
-                    targets.push(parts[1].to_string());
+        };
+        use bundle::{ImportOutcome, ImportStatus};
+        outcomes.push(
+            match apply_fstab_block(&prepared.block, &b.id, prepared.targets, None) {
+                Ok(LindyResponse::AdoptableExistingBlock { message, .. }) => {
+                    ImportOutcome::new(&b.id, ImportStatus::Failed, message)
                 }
-            }
-        }
-        // Removed stray/duplicate closing braces that caused syntax error
-
-        if let Some(mp) = partition_mountpoint {
-            let esc = shell_escape_single(&mp);
-            if force {
-                cmd.push_str(&format!("echo Attempting umount {mp}\nif umount {esc}; then echo umount {mp} succeeded; else echo umount {mp} failed, trying lazy unmount; if umount -l {esc}; then echo lazy unmount {mp} succeeded; else echo lazy unmount {mp} failed; fi; fi\n", mp = mp, esc = esc));
-            } else {
-                cmd.push_str(&format!("echo Attempting umount {mp}\nif umount {esc}; then echo umount {mp} succeeded; else echo umount {mp} failed; exit 2; fi\n", mp = mp, esc = esc));
-            }
-        }
+                Ok(resp) => ImportOutcome::new(&b.id, ImportStatus::Restored, resp.message()),
+                Err(e) => ImportOutcome {
+                    message: e.message().to_string(),
+                    error: Some(e),
+                    ..ImportOutcome::new(&b.id, ImportStatus::Failed, "")
+                },
+            },
+        );
+    }
+    let restored = outcomes
+        .iter()
+        .filter(|o| o.status == bundle::ImportStatus::Restored)
+        .count();
+    Ok(LindyResponse::Imported {
+        message: format!("{} of {} mappings restored", restored, outcomes.len()),
+        outcomes,
+    })
+}
 
-        let backup = format!("/etc/fstab.lindy.bak.{}", now);
-        cmd.push_str(&format!(
-            "cp /etc/fstab {backup} && mv {new} /etc/fstab && sync && mount -a\n",
-            backup = backup,
-            new = newfst
-        ));
-
-        // Run via pkexec so polkit prompt appears
-        // Log command for debugging
-        let _ = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("/tmp/lindy-debug.log")
-            .and_then(|mut f| {
-                let _ = writeln!(
-                    f,
-                    "[{}] remove_fstab_block id={} cmd=---\n{}---",
-                    now, id, cmd
-                );
-                Ok(())
-            });
+/// List the `/etc/fstab.lindy.bak.<ts>` backups, newest first.
+#[tauri::command]
+fn list_fstab_backups() -> Result<Vec<backups::FstabBackup>, LindyError> {
+    Ok(backups::list(std::path::Path::new(backups::BACKUP_DIR)))
+}
 
-        let output = match run_pkexec_with_script(&cmd) {
-            Ok(o) => o,
-            Err(e) => {
-                let resp = serde_json::json!({
-                    "status": "error",
-                    "code": "spawn_pkexec_failed",
-                    "message": format!("failed to spawn pkexec: {}", e),
-                    "stdout": "",
-                    "stderr": "",
-                });
-                return Ok(serde_json::to_string(&resp).unwrap());
-            }
-        };
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        let _ = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/lindy-debug.log").and_then(|mut f| {
-            let _ = writeln!(f, "[{}] remove_fstab_block (pkexec-branch) id={} exit={:?} stdout=---\n{}--- stderr=---\n{}---", now, id, output.status.code(), stdout, stderr);
-            Ok(())
-        });
-        let _ = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("/tmp/lindy-debug.log")
-            .and_then(|mut f| {
-                let _ = writeln!(
-                    f,
-                    "[{}] remove_fstab_block id={} exit={:?} stdout=---\n{}--- stderr=---\n{}---",
-                    now,
-                    id,
-                    output.status.code(),
-                    stdout,
-                    stderr
-                );
-                Ok(())
-            });
+/// Unified diff from the current /etc/fstab to the backup taken at `ts`,
+/// i.e. the change `restore_fstab_backup(ts)` would make. Empty when equal.
+#[tauri::command]
+fn diff_fstab_backup(ts: u64) -> Result<String, LindyError> {
+    let backup = backups::find(std::path::Path::new(backups::BACKUP_DIR), ts)
+        .ok_or_else(|| LindyError::not_found(format!("no fstab backup with timestamp {}", ts)))?;
+    let output = Command::new("diff")
+        .args(["-u", "--label", "/etc/fstab", "--label", &backup.path])
+        .args(["/etc/fstab", &backup.path])
+        .output()
+        .map_err(|e| LindyError::io(format!("failed to run diff: {}", e)))?;
+    // diff exits 0 when the files are equal, 1 when they differ and 2 on trouble
+    match output.status.code() {
+        Some(0) | Some(1) => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+        _ => Err(LindyError::io(format!(
+            "diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
 
-        if output.status.success() {
-            // remove metadata file if present
-            let meta_path = format!("/var/lib/lindy/{}.json", id);
-            let _ = fs::remove_file(&meta_path);
-            let resp = serde_json::json!({
-                "status": "ok",
-                "code": "removed",
-                "message": "fstab block removed and mount -a executed",
-                "stdout": stdout,
-                "stderr": stderr,
-            });
-            return Ok(serde_json::to_string(&resp).unwrap());
-        } else {
-            let resp = serde_json::json!({
-                "status": "error",
-                "code": "pkexec_failed",
-                "message": format!("pkexec exited with code {:?}", output.status.code()),
-                "stdout": stdout,
-                "stderr": stderr,
-            });
-            return Ok(serde_json::to_string(&resp).unwrap());
-        }
-    } else {
-        // Could not read /etc/fstab locally; build a privileged shell to extract and remove the block entirely under pkexec.
-        let newtmp = format!("/tmp/lindy-newfst-{}-{}.tmp", id, now);
-        // AWK script to print bind targets between markers (ensure trailing newline so concatenation with subsequent shell code is safe)
-        // Use a non-reserved variable name `in_block` (some awk implementations treat `in` as the in-operator)
-        let awk_targets = format!(
-            r#"awk 'BEGIN{{in_block=0}} $0 ~ /^# lindy BEGIN: {id}$/{{in_block=1; next}} $0 ~ /^# lindy END: {id}$/{{in_block=0; next}} in_block && $0 ~ /[[:space:]]none[[:space:]]bind[[:space:]]/ {{ print $2 }}' /etc/fstab > /tmp/lind_targets.{id}
-"#,
-            id = id
-        );
-        // AWK script to create new fstab without the block
-        let awk_newfst = format!("awk 'BEGIN{{skip=0}} $0 ~ /^# lindy BEGIN: {id}$/{{skip=1; next}} $0 ~ /^# lindy END: {id}$/{{skip=0; next}} {{ if(!skip) print $0 }}' /etc/fstab > {newtmp}", id = id, newtmp = newtmp);
-
-        let mut cmd = String::new();
-        cmd.push_str("set -e\n");
-        cmd.push_str(&awk_targets);
-        // unmount targets read from file
-        cmd.push_str(&format!("for t in $(cat /tmp/lind_targets.{id} 2>/dev/null || true); do echo Attempt umount $t; if umount \"$t\"; then echo umount $t ok; else echo umount $t failed, trying lazy; umount -l \"$t\" || true; fi; done\n", id = id));
-        cmd.push_str(&awk_newfst);
-        cmd.push_str(&format!("cp /etc/fstab /etc/fstab.lindy.bak.{now} && mv {newtmp} /etc/fstab && sync && mount -a\n", now = now, newtmp = newtmp));
-
-        // Log the constructed privileged command for debugging (append-only)
-        let _ = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("/tmp/lindy-debug.log")
-            .and_then(|mut f| {
-                let _ = writeln!(
-                    f,
-                    "[{}] remove_fstab_block (pkexec-branch) id={} cmd=---\n{}---",
-                    now, id, cmd
-                );
-                Ok(())
-            });
+/// Replace /etc/fstab with the backup taken at `ts` and run `mount -a`.
+/// The current file is backed up first, so a restore can itself be undone;
+/// if `mount -a` fails the previous file is put back.
+#[tauri::command]
+fn restore_fstab_backup(ts: u64) -> Result<LindyResponse, LindyError> {
+    backups::find(std::path::Path::new(backups::BACKUP_DIR), ts)
+        .ok_or_else(|| LindyError::not_found(format!("no fstab backup with timestamp {}", ts)))?;
+    response::from_helper(run_privileged(&helper::Request::RestoreBackup { ts })?)
+}
 
-        let output = match run_pkexec_with_script(&cmd) {
-            Ok(o) => o,
-            Err(e) => {
-                let resp = serde_json::json!({
-                    "status": "error",
-                    "code": "spawn_pkexec_failed",
-                    "message": format!("failed to spawn pkexec: {}", e),
-                    "stdout": "",
-                    "stderr": "",
-                });
-                return Ok(serde_json::to_string(&resp).unwrap());
-            }
-        };
+/// Delete the backups outside the retention policy: everything that is
+/// neither among the `keep_last` newest nor younger than `max_age_days`.
+/// Missing arguments take the defaults used after every fstab change.
+#[tauri::command]
+fn prune_fstab_backups(
+    keep_last: Option<usize>,
+    max_age_days: Option<u64>,
+) -> Result<LindyResponse, LindyError> {
+    use std::time::{SystemTime, UNIX_EPOCH};
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-        if output.status.success() {
-            let meta_path = format!("/var/lib/lindy/{}.json", id);
-            let _ = fs::remove_file(&meta_path);
-            let resp = serde_json::json!({
-                "status": "ok",
-                "code": "removed",
-                "message": "fstab block removed and mount -a executed",
-                "stdout": stdout,
-                "stderr": stderr,
-            });
-            return Ok(serde_json::to_string(&resp).unwrap());
-        } else {
-            let resp = serde_json::json!({
-                "status": "error",
-                "code": "pkexec_failed",
-                "message": format!("pkexec exited with code {:?}", output.status.code()),
-                "stdout": stdout,
-                "stderr": stderr,
-            });
-            return Ok(serde_json::to_string(&resp).unwrap());
-        }
+    let defaults = backups::RetentionPolicy::default();
+    let policy = backups::RetentionPolicy {
+        keep_last: keep_last.unwrap_or(defaults.keep_last),
+        max_age_days: max_age_days.unwrap_or(defaults.max_age_days),
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let all = backups::list(std::path::Path::new(backups::BACKUP_DIR));
+    if policy.expired(&all, now).is_empty() {
+        return Ok(LindyResponse::NothingToPrune {
+            message: "no backups are outside the retention policy".into(),
+        });
     }
 
-    // All branches return above; nothing to do here.
+    response::from_helper(run_privileged(&helper::Request::PruneBackups {
+        keep_last: policy.keep_last,
+        max_age_days: policy.max_age_days,
+    })?)
 }
 
 /// Build a recommended fstab line set for a shared partition + one bind mount mapping.
@@ -1219,6 +1032,8 @@ fn remove_fstab_block(id: &str, force: bool) -> Result<String, String> {
 /// - base_mount: mount point for the partition (e.g. /mnt/popos)
 /// - src_inside_partition: absolute path inside the partition once mounted (e.g. /mnt/popos/home/dovndev/Projects)
 /// - target_local: local path to bind onto (e.g. /home/dovndev/Projects)
+/// - driver: fstab type for the partition (`ntfs3`, `ntfs-3g`, ...); chosen
+///   from the partition's filesystem when omitted
 #[tauri::command]
 fn generate_fstab_line(
     partition_uuid: &str,
@@ -1226,92 +1041,149 @@ fn generate_fstab_line(
     src_inside_partition: &str,
     target_local: &str,
     skip_partition_mount: bool,
-) -> String {
+    driver: Option<String>,
+) -> Result<String, LindyError> {
     // If user prefers not to include a partition mount line (already mounted) or UUID is empty,
     // only return the bind line. Otherwise include both lines.
-    let bind_line = format!("{} {} none bind 0 0", src_inside_partition, target_local);
+    let bind = bind_line(src_inside_partition, target_local, None);
     if skip_partition_mount || partition_uuid.trim().is_empty() {
-        return bind_line;
+        return Ok(bind);
+    }
+    let driver = partition_driver(partition_uuid, driver.as_deref())?;
+    Ok(format!(
+        "{}\n{}",
+        partition_line(partition_uuid, base_mount, driver, None),
+        bind
+    ))
+}
+
+/// Filesystem drivers, whether this system has them and the options each
+/// one gives the partition line.
+#[tauri::command]
+fn filesystem_drivers() -> Vec<mountopts::DriverInfo> {
+    mountopts::detect(&mountopts::Host::current())
+}
+
+// The driver named by the caller, or the preferred available one for the
+// filesystem of partition `uuid`. None writes fstab type `auto`.
+fn partition_driver(
+    uuid: &str,
+    driver: Option<&str>,
+) -> Result<Option<mountopts::Driver>, LindyError> {
+    if let Some(name) = driver {
+        return mountopts::Driver::parse(name)
+            .map(Some)
+            .ok_or_else(|| LindyError::invalid(format!("unknown filesystem driver: {}", name)));
     }
-    let partition_line = format!(
-        "UUID={} {} auto defaults,noatime,nofail,x-systemd.automount,x-systemd.device-timeout=10 0 2",
-        partition_uuid, base_mount
-    );
-    format!("{}\n{}", partition_line, bind_line)
+    let fstype = list_partitions()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|p| {
+            p.uuid
+                .as_deref()
+                .is_some_and(|u| u.eq_ignore_ascii_case(uuid.trim()))
+        })
+        .and_then(|p| p.fstype);
+    Ok(fstype
+        .and_then(|f| mountopts::preferred(&f, &mountopts::detect(&mountopts::Host::current()))))
+}
+
+/// Options of the partition line when none are given.
+const PARTITION_OPTIONS: &str =
+    "defaults,noatime,nofail,x-systemd.automount,x-systemd.device-timeout=10";
+
+// fstab line mounting the partition `uuid` at `mount_point`. Without
+// `options` a `driver` adds its template (owner, masks) to the defaults.
+fn partition_line(
+    uuid: &str,
+    mount_point: &str,
+    driver: Option<mountopts::Driver>,
+    options: Option<&str>,
+) -> String {
+    let options = match (options, driver) {
+        (Some(o), _) => o.to_string(),
+        (None, Some(d)) => match d.options(&mountopts::Host::current()) {
+            extra if extra.is_empty() => PARTITION_OPTIONS.to_string(),
+            extra => format!("{},{}", PARTITION_OPTIONS, extra),
+        },
+        (None, None) => PARTITION_OPTIONS.to_string(),
+    };
+    format!(
+        "UUID={} {} {} {} 0 2",
+        uuid.trim(),
+        fstab::encode_field(mount_point),
+        driver.map_or("auto", mountopts::Driver::fstab_type),
+        options
+    )
+}
+
+// fstab line binding `src` onto `target`; `options` defaults to `bind`.
+fn bind_line(src: &str, target: &str, options: Option<&str>) -> String {
+    format!(
+        "{} {} none {} 0 0",
+        fstab::encode_field(src),
+        fstab::encode_field(target),
+        options.unwrap_or("bind")
+    )
 }
 
-#[derive(serde::Serialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, ts_rs::TS)]
+#[ts(export)]
 struct PartitionInfo {
     name: String,
+    path: String,
     fstype: Option<String>,
     uuid: Option<String>,
     label: Option<String>,
     mountpoint: Option<String>,
+    /// Every mount point; `mountpoint` is the first.
+    mountpoints: Vec<String>,
     size: Option<String>,
+    fsused: Option<String>,
+    fsavail: Option<String>,
+    /// GPT type GUID or MBR type byte.
+    parttype: Option<String>,
+    partlabel: Option<String>,
+    partuuid: Option<String>,
+    /// Model, serial and transport of the disk the partition is on.
+    model: Option<String>,
+    serial: Option<String>,
+    transport: Option<String>,
+    removable: bool,
+    rotational: bool,
 }
 
 /// List block devices (flattened) using lsblk JSON output.
 #[tauri::command]
-fn list_partitions() -> Result<Vec<PartitionInfo>, String> {
-    use std::process::Command;
-    let output = Command::new("lsblk")
-        .args(["-J", "-o", "NAME,FSTYPE,UUID,LABEL,MOUNTPOINT,SIZE"]) // JSON
-        .output()
-        .map_err(|e| format!("failed to run lsblk: {}", e))?;
-    if !output.status.success() {
-        return Err("lsblk returned non-zero status".into());
-    }
-    let v: serde_json::Value =
-        serde_json::from_slice(&output.stdout).map_err(|e| format!("invalid lsblk json: {}", e))?;
-
-    fn collect(vec: &mut Vec<PartitionInfo>, node: &serde_json::Value) {
-        if let Some(name) = node.get("name").and_then(|x| x.as_str()) {
-            let fstype = node
-                .get("fstype")
-                .and_then(|x| x.as_str())
-                .map(|s| s.to_string());
-            let uuid = node
-                .get("uuid")
-                .and_then(|x| x.as_str())
-                .map(|s| s.to_string());
-            let label = node
-                .get("label")
-                .and_then(|x| x.as_str())
-                .map(|s| s.to_string());
-            let mountpoint = node
-                .get("mountpoint")
-                .and_then(|x| x.as_str())
-                .map(|s| s.to_string());
-            let size = node
-                .get("size")
-                .and_then(|x| x.as_str())
-                .map(|s| s.to_string());
-            vec.push(PartitionInfo {
-                name: name.to_string(),
-                fstype,
-                uuid,
-                label,
-                mountpoint,
-                size,
-            });
-        }
-        if let Some(children) = node.get("children").and_then(|x| x.as_array()) {
-            for ch in children {
-                collect(vec, ch);
-            }
-        }
-    }
-
-    let mut flat = Vec::new();
-    if let Some(blockdevices) = v.get("blockdevices").and_then(|x| x.as_array()) {
-        for dev in blockdevices {
-            collect(&mut flat, dev);
-        }
-    }
-    Ok(flat)
+fn list_partitions() -> Result<Vec<PartitionInfo>, LindyError> {
+    let devices = lsblk::run().map_err(LindyError::io)?;
+    Ok(devices
+        .into_iter()
+        .map(|d| PartitionInfo {
+            mountpoint: d.mountpoints.first().cloned(),
+            name: d.name,
+            path: d.path,
+            fstype: d.fstype,
+            uuid: d.uuid,
+            label: d.label,
+            mountpoints: d.mountpoints,
+            size: d.size,
+            fsused: d.fsused,
+            fsavail: d.fsavail,
+            parttype: d.parttype,
+            partlabel: d.partlabel,
+            partuuid: d.partuuid,
+            model: d.model,
+            serial: d.serial,
+            transport: d.transport,
+            removable: d.removable,
+            rotational: d.rotational,
+        })
+        .collect())
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ts_rs::TS)]
+#[ts(export)]
 struct UserFolder {
     name: String,
     linux_path: String,
@@ -1320,14 +1192,16 @@ struct UserFolder {
     exists_windows: bool,
 }
 
-#[derive(serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, ts_rs::TS)]
+#[ts(export)]
 struct FolderMapping {
     linux_path: String,
     windows_path: String,
     folder_type: String,
 }
 
-#[derive(serde::Serialize, Clone)]
+#[derive(Debug, serde::Serialize, Clone, ts_rs::TS)]
+#[ts(export)]
 struct WindowsPartition {
     uuid: String,
     label: Option<String>,
@@ -1336,16 +1210,55 @@ struct WindowsPartition {
     size: Option<String>,
     is_mounted: bool,
     has_users_folder: bool,
+    /// Names to pass as `username`, most recently used first.
     detected_users: Vec<String>,
+    /// User profiles from the registry of a mounted Windows; empty when it
+    /// is not mounted or its registry cannot be read.
+    profiles: Vec<registry::WindowsProfile>,
+    /// Windows is hibernated (Fast Startup) or did not shut down cleanly, so
+    /// the partition can only be mounted read-only. None when it could not
+    /// be checked.
+    hibernated: Option<bool>,
+    /// hiberfil.sys holds a saved session. Unlike `hibernated` not set for
+    /// a volume that is only dirty, which any data partition can be; this
+    /// is what counts towards `score`.
+    hiberfil: Option<bool>,
+    /// BitLocker-encrypted. Unlocked volumes are listed by their
+    /// /dev/mapper device.
+    encrypted: bool,
+    /// A BitLocker volume that has to be unlocked (`unlock_bitlocker`)
+    /// before it can be mounted; `uuid` is the BitLocker volume's.
+    locked: bool,
+    /// From the partition type; recovery partitions are never auto-mapped.
+    kind: lsblk::PartitionKind,
+    parttype: Option<String>,
+    partlabel: Option<String>,
+    partuuid: Option<String>,
+    mount_points: Vec<String>,
+    fsused: Option<String>,
+    fsavail: Option<String>,
+    model: Option<String>,
+    serial: Option<String>,
+    transport: Option<String>,
+    removable: bool,
+    rotational: bool,
+    /// Windows system folders found on it; None when its contents could not
+    /// be read.
+    markers: Option<ntfs::SystemMarkers>,
+    /// How much it looks like the Windows system partition, and why (see
+    /// score.rs). Partitions are listed highest first.
+    score: i32,
+    reasons: Vec<String>,
 }
 
 /// Detect common user folders in the current Linux user's home directory
 #[tauri::command]
-fn detect_user_folders() -> Result<Vec<UserFolder>, String> {
+fn detect_user_folders() -> Result<Vec<UserFolder>, LindyError> {
     use std::env;
     use std::path::Path;
 
-    let home = env::var("HOME").map_err(|_| "Could not get HOME directory")?;
+    let home =
+        env::var("HOME").map_err(|_| LindyError::not_found("Could not get HOME directory"))?;
 
     // Common folder mappings between Linux and Windows
     let folder_mappings = vec![
@@ -1381,70 +1294,52 @@ fn detect_user_folders() -> Result<Vec<UserFolder>, String> {
 fn suggest_folder_mappings(
     windows_base_path: &str,
     username: Option<String>,
-) -> Result<Vec<FolderMapping>, String> {
+) -> Result<Vec<FolderMapping>, LindyError> {
     use std::env;
     use std::path::Path;
 
-    let home = env::var("HOME").map_err(|_| "Could not get HOME directory")?;
+    let home =
+        env::var("HOME").map_err(|_| LindyError::not_found("Could not get HOME directory"))?;
 
     // Validate windows_base_path
     let base_path = Path::new(windows_base_path);
     if !base_path.exists() {
-        return Err(format!(
+        return Err(LindyError::not_found(format!(
             "Windows base path does not exist: {}",
             windows_base_path
-        ));
+        )));
     }
 
     // Try to detect Windows username if not provided
-    let win_user = if let Some(user) = username {
-        user
-    } else {
-        // Try common Windows user folder patterns
-        let users_path = base_path.join("Users");
-
-        if users_path.exists() {
-            // Look for user folders (skip system folders)
-            let skip_folders = [
-                "Public",
-                "Default",
-                "All Users",
-                "Default User",
-                "desktop.ini",
-            ];
-
-            if let Ok(entries) = std::fs::read_dir(&users_path) {
-                for entry in entries.flatten() {
-                    if let Ok(file_type) = entry.file_type() {
-                        if file_type.is_dir() {
-                            let folder_name = entry.file_name().to_string_lossy().to_string();
-                            if !skip_folders.contains(&folder_name.as_str())
-                                && !folder_name.starts_with('.')
-                                && !folder_name.to_lowercase().starts_with("defaultapp")
-                            {
-                                return suggest_folder_mappings(
-                                    windows_base_path,
-                                    Some(folder_name),
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        return Err("Could not detect Windows username. Please specify manually in the Windows Username field.".to_string());
+    let win_user = match username {
+        Some(user) => user,
+        None => check_for_windows_users(windows_base_path)
+            .1
+            .into_iter()
+            .next()
+            .ok_or_else(|| LindyError::not_found("Could not detect Windows username. Please specify manually in the Windows Username field."))?,
     };
 
+    // The profile folder: the registry knows it for account names and for
+    // profiles outside Users; otherwise Users/<name>
+    let profiles = registry::profiles(base_path).unwrap_or_default();
+    let user_dir = registry::profile_folder(&profiles, &win_user)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("Users/{}", win_user));
+
     // Validate Windows user path exists
-    let win_user_path = format!("{}/Users/{}", windows_base_path, win_user);
+    let win_user_path = format!("{}/{}", windows_base_path, user_dir);
     if !Path::new(&win_user_path).exists() {
-        return Err(format!(
+        return Err(LindyError::not_found(format!(
             "Windows user folder does not exist: {}",
             win_user_path
-        ));
+        )));
     }
 
+    // Known Folders moved to OneDrive or another drive, from the user's hive
+    let moved = moved_folders(base_path, Path::new(&win_user_path));
+    let mut missing = Vec::new();
+
     // Common folder mappings with additional variations
     let folder_mappings = vec![
         ("Desktop", vec!["Desktop"]),
@@ -1465,9 +1360,21 @@ fn suggest_folder_mappings(
             continue;
         }
 
+        if let Some((_, raw, resolved)) = moved.iter().find(|(name, _, _)| name == linux_name) {
+            match resolved {
+                Some(path) if path.is_dir() => mappings.push(FolderMapping {
+                    linux_path,
+                    windows_path: path.to_string_lossy().into_owned(),
+                    folder_type: linux_name.to_string(),
+                }),
+                _ => missing.push(format!("{} ({})", linux_name, raw)),
+            }
+            continue;
+        }
+
         // Try each Windows variant
         for windows_name in windows_variants {
-            let windows_path = format!("{}/Users/{}/{}", windows_base_path, win_user, windows_name);
+            let windows_path = format!("{}/{}", win_user_path, windows_name);
 
             if Path::new(&windows_path).exists() {
                 mappings.push(FolderMapping {
@@ -1481,119 +1388,280 @@ fn suggest_folder_mappings(
     }
 
     if mappings.is_empty() {
-        return Err(format!(
-            "No matching folders found. Checked user: {} at path: {}/Users/{}",
-            win_user, windows_base_path, win_user
-        ));
+        let mut message = format!(
+            "No matching folders found. Checked user: {} at path: {}",
+            win_user, win_user_path
+        );
+        if !missing.is_empty() {
+            message.push_str(&format!(
+                ". Moved to a drive /etc/fstab does not mount or to a missing folder: {}",
+                missing.join(", ")
+            ));
+        }
+        return Err(LindyError::not_found(message));
     }
 
     Ok(mappings)
 }
 
+// Known Folders of the profile at `profile` that are not in the profile
+// folder: (Linux folder name, path as Windows has it, path on Linux). The
+// Linux path is None when the drive is not mounted here.
+fn moved_folders(
+    base: &std::path::Path,
+    profile: &std::path::Path,
+) -> Vec<(String, String, Option<std::path::PathBuf>)> {
+    let Ok(values) = registry::shell_folders(&profile.join("NTUSER.DAT")) else {
+        return Vec::new();
+    };
+    let moved: Vec<_> = knownfolders::SHELL_FOLDERS
+        .iter()
+        .filter_map(|(folder, value)| {
+            let (_, raw) = values
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(value))?;
+            (!knownfolders::is_default(raw, value)).then(|| (folder.to_string(), raw.clone()))
+        })
+        .collect();
+    if moved.is_empty() {
+        return Vec::new();
+    }
+    let drives = knownfolders::Drives {
+        profile: profile.to_path_buf(),
+        system: base.to_path_buf(),
+        letters: drive_mounts(base),
+    };
+    moved
+        .into_iter()
+        .map(|(folder, raw)| {
+            let resolved = knownfolders::resolve(&raw, &drives);
+            (folder, raw, resolved)
+        })
+        .collect()
+}
+
+// Mount points of the drive letters of the Windows at `base`: its own
+// drive is `base`, the others are found by matching MountedDevices to the
+// partitions lsblk sees. Only a partition /etc/fstab mounts counts: a bind
+// from one the desktop mounted (under /run/media) would have no source at
+// boot, so its folders are left out like those on unmounted drives.
+fn drive_mounts(base: &std::path::Path) -> Vec<(char, std::path::PathBuf)> {
+    let mut mounts = Vec::new();
+    let system = registry::system_drive(base).ok().flatten();
+    if let Some(letter) = system {
+        mounts.push((letter.to_ascii_uppercase(), base.to_path_buf()));
+    }
+    let Ok(letters) = registry::drive_letters(base) else {
+        return mounts;
+    };
+    let Ok(fstab) = FstabFile::read("/etc/fstab") else {
+        return mounts;
+    };
+    let devices = lsblk::run().unwrap_or_default();
+    for (letter, id) in letters {
+        if mounts.iter().any(|(l, _)| *l == letter) {
+            continue;
+        }
+        let Some(d) = devices
+            .iter()
+            .find(|d| id.matches(d.partuuid.as_deref(), lsblk::start(&d.name)))
+        else {
+            continue;
+        };
+        let names = |e: &fstab::FstabEntry| match e.spec.split_once('=') {
+            Some(("UUID", u)) => d.uuid.as_deref().is_some_and(|x| x.eq_ignore_ascii_case(u)),
+            Some(("PARTUUID", u)) => d
+                .partuuid
+                .as_deref()
+                .is_some_and(|x| x.eq_ignore_ascii_case(u)),
+            _ => e.spec == d.path,
+        };
+        let entry = fstab
+            .entries()
+            .find(|e| !e.is_bind() && e.file.starts_with('/') && names(e));
+        if let Some(e) = entry {
+            mounts.push((letter, std::path::PathBuf::from(&e.file)));
+        }
+    }
+    mounts
+}
+
 /// Detect Windows partitions on the system
 #[tauri::command]
-fn detect_windows_partitions() -> Result<Vec<WindowsPartition>, String> {
-    // use std::path::Path; (removed unused import)
-
-    // Get partition list using lsblk
-    let output = Command::new("lsblk")
-        .args(["-J", "-o", "NAME,FSTYPE,UUID,LABEL,MOUNTPOINT,SIZE"])
-        .output()
-        .map_err(|e| format!("failed to run lsblk: {}", e))?;
+fn detect_windows_partitions() -> Result<Vec<WindowsPartition>, LindyError> {
+    let mut windows_partitions = Vec::new();
+    for d in lsblk::run().map_err(LindyError::io)? {
+        let fstype = d.fstype.as_deref().unwrap_or_default();
+        // An unlocked BitLocker volume has its mapper device as a child,
+        // which is listed instead.
+        let locked = fstype == "BitLocker" && !d.has_children;
+        // Look for NTFS partitions (Windows) or exFAT (could be Windows)
+        if !(fstype == "ntfs" || fstype == "exfat" || locked) {
+            continue;
+        }
+        let Some(uuid) = d.uuid else {
+            continue;
+        };
+        let mount_point = d.mountpoints.first().cloned();
+        let state = if fstype == "ntfs" {
+            ntfs_volume_state(&d.path, mount_point.as_deref())
+        } else {
+            None
+        };
+        let hibernated = state.map(|s| s.read_only_reason().is_some());
+        let hiberfil = state.map(|s| s.hibernated);
+
+        // What only a system volume has: read from the mounted tree, or from
+        // the MFT when the device is readable (as root or in group disk)
+        let markers = match (&mount_point, fstype) {
+            (Some(mp), _) => Some(ntfs::markers_at(std::path::Path::new(mp))),
+            (None, "ntfs") => ntfs::markers_device(&d.path).ok(),
+            _ => None,
+        };
 
-    if !output.status.success() {
-        return Err("lsblk returned non-zero status".into());
+        // Check if this partition has a Users folder (indicating it's a Windows system partition)
+        let (has_users_folder, detected_users) = if let Some(ref mp) = mount_point {
+            check_for_windows_users(mp)
+        } else {
+            (markers.is_some_and(|m| m.users), Vec::new())
+        };
+        let profiles = mount_point
+            .as_deref()
+            .and_then(|mp| registry::profiles(std::path::Path::new(mp)).ok())
+            .unwrap_or_default();
+
+        let mut partition = WindowsPartition {
+            uuid,
+            label: d.label,
+            device: d.path,
+            is_mounted: mount_point.is_some(),
+            mount_point,
+            size: d.size,
+            has_users_folder,
+            detected_users,
+            profiles,
+            hibernated,
+            hiberfil,
+            encrypted: locked || d.parent_fstype.as_deref() == Some("BitLocker"),
+            locked,
+            kind: lsblk::PartitionKind::from_parttype(d.parttype.as_deref()),
+            parttype: d.parttype,
+            partlabel: d.partlabel,
+            partuuid: d.partuuid,
+            mount_points: d.mountpoints,
+            fsused: d.fsused,
+            fsavail: d.fsavail,
+            model: d.model,
+            serial: d.serial,
+            transport: d.transport,
+            removable: d.removable,
+            rotational: d.rotational,
+            markers,
+            score: 0,
+            reasons: Vec::new(),
+        };
+        rescore(&mut partition);
+        windows_partitions.push(partition);
     }
 
-    let v: serde_json::Value =
-        serde_json::from_slice(&output.stdout).map_err(|e| format!("invalid lsblk json: {}", e))?;
+    // Most likely to be the main Windows partition first; equal scores keep
+    // the lsblk order
+    windows_partitions.sort_by_key(|p| std::cmp::Reverse(p.score));
 
-    let mut windows_partitions = Vec::new();
+    Ok(windows_partitions)
+}
 
-    fn collect_windows_partitions(
-        partitions: &mut Vec<WindowsPartition>,
-        node: &serde_json::Value,
-    ) {
-        if let Some(fstype) = node.get("fstype").and_then(|x| x.as_str()) {
-            // Look for NTFS partitions (Windows) or exFAT (could be Windows)
-            if fstype == "ntfs" || fstype == "exfat" {
-                if let Some(uuid) = node.get("uuid").and_then(|x| x.as_str()) {
-                    let name = node
-                        .get("name")
-                        .and_then(|x| x.as_str())
-                        .unwrap_or("unknown");
-                    let label = node
-                        .get("label")
-                        .and_then(|x| x.as_str())
-                        .map(|s| s.to_string());
-                    let mount_point = node
-                        .get("mountpoint")
-                        .and_then(|x| x.as_str())
-                        .map(|s| s.to_string());
-                    let size = node
-                        .get("size")
-                        .and_then(|x| x.as_str())
-                        .map(|s| s.to_string());
-                    let is_mounted = mount_point.is_some();
-
-                    // Check if this partition has a Users folder (indicating it's a Windows system partition)
-                    let (has_users_folder, detected_users) = if let Some(ref mp) = mount_point {
-                        check_for_windows_users(mp)
-                    } else {
-                        (false, Vec::new())
-                    };
-
-                    partitions.push(WindowsPartition {
-                        uuid: uuid.to_string(),
-                        label,
-                        device: format!("/dev/{}", name),
-                        mount_point,
-                        size,
-                        is_mounted,
-                        has_users_folder,
-                        detected_users,
-                    });
-                }
-            }
-        }
+// Score `p` from what is known about it.
+fn rescore(p: &mut WindowsPartition) {
+    let (score, reasons) = score::score(&score::Evidence {
+        markers: p.markers,
+        users: p.detected_users.len(),
+        kind: p.kind,
+        hibernated: p.hiberfil,
+        removable: p.removable,
+    });
+    p.score = score;
+    p.reasons = reasons;
+}
 
-        if let Some(children) = node.get("children").and_then(|x| x.as_array()) {
-            for child in children {
-                collect_windows_partitions(partitions, child);
-            }
-        }
+// Whether auto-mapping may pick `p` by itself.
+fn is_candidate(p: &WindowsPartition) -> bool {
+    !p.locked && p.kind != lsblk::PartitionKind::Recovery
+}
+
+// Candidates whose contents could not be read without root, when there is
+// more than one candidate to choose from.
+fn unchecked_candidates(partitions: &[WindowsPartition]) -> Vec<&WindowsPartition> {
+    if partitions.iter().filter(|p| is_candidate(p)).count() < 2 {
+        return Vec::new();
     }
+    partitions
+        .iter()
+        .filter(|p| is_candidate(p) && p.markers.is_none())
+        .collect()
+}
 
-    if let Some(blockdevices) = v.get("blockdevices").and_then(|x| x.as_array()) {
-        for dev in blockdevices {
-            collect_windows_partitions(&mut windows_partitions, dev);
+// Have the helper read the MFT of the unchecked candidates and rank again.
+// Mounting the chosen partition asks for the password anyway, and polkit
+// keeps the authorization for the mount that follows.
+fn inspect_unchecked(partitions: &mut [WindowsPartition]) -> Result<(), LindyError> {
+    let uuids: Vec<String> = unchecked_candidates(partitions)
+        .iter()
+        .map(|p| p.uuid.clone())
+        .collect();
+    if uuids.is_empty() {
+        return Ok(());
+    }
+    let resp = run_privileged(&helper::Request::Inspect { uuids })?;
+    for p in partitions.iter_mut() {
+        if let Some(markers) = resp.markers.get(&p.uuid) {
+            p.markers = Some(*markers);
+            p.has_users_folder |= markers.users;
+            rescore(p);
         }
     }
+    partitions.sort_by_key(|p| std::cmp::Reverse(p.score));
+    Ok(())
+}
 
-    // Sort by likelihood of being the main Windows partition
-    windows_partitions.sort_by(|a, b| {
-        // Prioritize mounted partitions with Users folder
-        match (a.has_users_folder, b.has_users_folder) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => {
-                // Then prioritize mounted partitions
-                match (a.is_mounted, b.is_mounted) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => std::cmp::Ordering::Equal,
-                }
-            }
-        }
-    });
+// Hibernation and dirty state of the NTFS volume on `device`. Reading the
+// device needs root on most systems; a mounted volume is then judged by the
+// header of its hiberfil.sys, which misses the dirty flag.
+fn ntfs_volume_state(device: &str, mount_point: Option<&str>) -> Option<ntfs::VolumeState> {
+    use std::io::Read;
 
-    Ok(windows_partitions)
+    if let Ok(state) = ntfs::inspect_device(device) {
+        return Some(state);
+    }
+    let mp = mount_point?;
+    let mut header = [0u8; 4];
+    let hibernated = match std::fs::File::open(std::path::Path::new(mp).join("hiberfil.sys")) {
+        Ok(mut f) => f.read_exact(&mut header).is_ok() && ntfs::hiberfil_hibernated(&header),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(_) => return None,
+    };
+    Some(ntfs::VolumeState {
+        hibernated,
+        dirty: false,
+    })
 }
 
+// Whether the Windows mounted at `mount_point` has a Users folder, and its
+// users, most recently used first. The users are the registry profiles
+// whose folder is on the partition, named by account; when the registry
+// cannot be read, the folders under Users are guessed from.
 fn check_for_windows_users(mount_point: &str) -> (bool, Vec<String>) {
     use std::path::Path;
 
     let users_path = Path::new(mount_point).join("Users");
+    if let Ok(profiles) = registry::profiles(Path::new(mount_point)) {
+        let users = profiles
+            .iter()
+            .filter(|p| p.folder.is_some())
+            .map(|p| p.name().to_string())
+            .collect();
+        return (users_path.exists(), users);
+    }
     if !users_path.exists() {
         return (false, Vec::new());
     }
@@ -1626,97 +1694,170 @@ fn check_for_windows_users(mount_point: &str) -> (bool, Vec<String>) {
     (true, users)
 }
 
-/// Automatically detect Windows partition, mount it if needed, and suggest folder mappings
+fn bitlocker_locked(p: &WindowsPartition) -> LindyError {
+    LindyError::BitlockerLocked {
+        message: format!(
+            "The Windows partition {} is encrypted with BitLocker; unlock it with its password or recovery key",
+            p.device
+        ),
+        partition: Box::new(p.clone()),
+    }
+}
+
+// The partition auto-mapping uses: the one with `uuid` when the user chose,
+// else the highest scored one. Recovery partitions are skipped, and
+// partitions sharing the top score are handed back for the user to choose
+// from. Locked BitLocker volumes cannot be mounted; when there is nothing
+// else the caller is asked to unlock one.
+fn best_windows_partition<'a>(
+    partitions: &'a [WindowsPartition],
+    uuid: Option<&str>,
+) -> Result<&'a WindowsPartition, LindyError> {
+    if let Some(uuid) = uuid {
+        return match partitions.iter().find(|p| p.uuid == uuid) {
+            Some(p) if p.locked => Err(bitlocker_locked(p)),
+            Some(p) => Ok(p),
+            None => Err(LindyError::NoWindowsPartitions {
+                message: format!("No Windows partition with UUID {} found", uuid),
+            }),
+        };
+    }
+    let usable: Vec<&WindowsPartition> = partitions.iter().filter(|p| is_candidate(p)).collect();
+    let scores: Vec<i32> = usable.iter().map(|p| p.score).collect();
+    match score::top(&scores).as_slice() {
+        [i] => Ok(usable[*i]),
+        [] => match partitions.iter().find(|p| p.locked) {
+            Some(p) => Err(bitlocker_locked(p)),
+            None => Err(LindyError::NoWindowsPartitions {
+                message: "No Windows partitions (NTFS/exFAT/BitLocker) found on the system".into(),
+            }),
+        },
+        tied => {
+            let candidates: Vec<WindowsPartition> =
+                tied.iter().map(|i| usable[*i].clone()).collect();
+            let devices: Vec<&str> = candidates.iter().map(|p| p.device.as_str()).collect();
+            Err(LindyError::AmbiguousWindowsPartition {
+                message: format!(
+                    "{} look equally likely to hold Windows; choose one",
+                    devices.join(", ")
+                ),
+                candidates,
+            })
+        }
+    }
+}
+
+/// Open a BitLocker volume (`cryptsetup open --type bitlk`) with its
+/// password or recovery key. Its NTFS filesystem then shows up in
+/// `detect_windows_partitions` under /dev/mapper and mounts like any other.
+/// `persist` (`prompt` or `key_file`) adds a crypttab entry so it is opened
+/// at boot too.
+#[tauri::command]
+fn unlock_bitlocker(
+    device: String,
+    key: String,
+    persist: Option<helper::Persist>,
+) -> Result<LindyResponse, LindyError> {
+    let name = format!("lindy-{}", device.rsplit('/').next().unwrap_or_default());
+    response::from_helper(run_privileged(&helper::Request::Unlock {
+        device,
+        name,
+        key,
+        persist,
+    })?)
+}
+
+// Where auto-mapping mounts a partition that is not mounted yet.
+fn windows_mount_path(partition: &WindowsPartition, base: Option<String>) -> String {
+    let base = base.unwrap_or_else(|| "/mnt/windows".to_string());
+    if let Some(ref label) = partition.label {
+        format!("{}/{}", base, label.replace(" ", "_").replace("/", "_"))
+    } else {
+        format!("{}/{}", base, &partition.uuid[..8])
+    }
+}
+
+/// Automatically detect Windows partition, mount it if needed, and suggest folder mappings.
+/// `uuid` is the partition the user chose after an
+/// `ambiguous_windows_partition` error; without it partitions whose contents
+/// could not be read are inspected through the helper first.
+/// With `dry_run` it returns the plan for mounting the partition (empty when
+/// it is mounted already) and does not look for users or folders.
 #[tauri::command]
 fn auto_mount_and_map(
     preferred_mount_base: Option<String>,
     username: Option<String>,
-) -> Result<String, String> {
-    use std::time::{SystemTime, UNIX_EPOCH};
-
+    uuid: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<LindyResponse, LindyError> {
     // Detect Windows partitions
-    let windows_partitions = detect_windows_partitions()?;
-
-    if windows_partitions.is_empty() {
-        let resp = serde_json::json!({
-            "status": "error",
-            "code": "no_windows_partitions",
-            "message": "No Windows partitions (NTFS/exFAT) found on the system"
-        });
-        return Ok(serde_json::to_string(&resp).unwrap());
+    let mut windows_partitions = detect_windows_partitions()?;
+    let dry_run = dry_run.unwrap_or(false);
+    let unchecked: Vec<String> = match uuid {
+        Some(_) => Vec::new(),
+        None => unchecked_candidates(&windows_partitions)
+            .iter()
+            .map(|p| p.device.clone())
+            .collect(),
+    };
+    if !dry_run && !unchecked.is_empty() {
+        inspect_unchecked(&mut windows_partitions)?;
     }
 
-    // Find the best Windows partition (prioritize those with Users folder)
-    let best_partition = windows_partitions
-        .iter()
-        .find(|p| p.has_users_folder)
-        .or_else(|| windows_partitions.first())
-        .ok_or("No suitable Windows partition found")?
-        .clone();
+    let mut best_partition = best_windows_partition(&windows_partitions, uuid.as_deref())?.clone();
+
+    if dry_run {
+        let mut plan = match &best_partition.mount_point {
+            Some(_) => plan::Plan::default(),
+            None => {
+                let path = windows_mount_path(&best_partition, preferred_mount_base);
+                let exists = std::path::Path::new(&path).exists();
+                let hibernated = best_partition.hibernated == Some(true);
+                let mut plan = plan::Plan::mount(&best_partition.uuid, &path, exists, hibernated);
+                if hibernated {
+                    plan.conflicts.push(format!(
+                        "Windows on {} is hibernated or was not shut down cleanly; it will be mounted read-only",
+                        best_partition.device
+                    ));
+                }
+                plan
+            }
+        };
+        if !unchecked.is_empty() {
+            plan.conflicts.push(format!(
+                "{} can only be looked into as root; auto-mapping checks them first and may choose another partition",
+                unchecked.join(", ")
+            ));
+        }
+        return Ok(planned(plan));
+    }
+    let mut read_only = false;
 
     let mount_point = if let Some(existing_mp) = &best_partition.mount_point {
         // Already mounted
         existing_mp.clone()
     } else {
         // Need to mount it using privileged operations
-        let base = preferred_mount_base.unwrap_or_else(|| "/mnt/windows".to_string());
-        let mount_path = if let Some(ref label) = best_partition.label {
-            format!("{}/{}", base, label.replace(" ", "_").replace("/", "_"))
-        } else {
-            format!("{}/{}", base, &best_partition.uuid[..8])
-        };
-
-        // Build privileged script to create directory and mount
-        let _now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        let mut script = String::new();
-        script.push_str("set -e\n");
-        script.push_str(&format!(
-            "echo 'Creating mount directory: {}'\n",
-            mount_path
-        ));
-        script.push_str(&format!("mkdir -p '{}'\n", mount_path));
-        script.push_str(&format!(
-            "echo 'Mounting partition {} to {}'\n",
-            best_partition.uuid, mount_path
-        ));
-        script.push_str(&format!(
-            "mount -U '{}' '{}'\n",
-            best_partition.uuid, mount_path
-        ));
-        script.push_str(&format!("echo 'Mount successful: {}'\n", mount_path));
-
-        // Execute via pkexec
-        let output = match run_pkexec_with_script(&script) {
-            Ok(o) => o,
-            Err(e) => {
-                let resp = serde_json::json!({
-                    "status": "error",
-                    "code": "spawn_pkexec_failed",
-                    "message": format!("Failed to spawn pkexec for mounting: {}", e),
-                    "stdout": "",
-                    "stderr": "",
-                });
-                return Ok(serde_json::to_string(&resp).unwrap());
-            }
-        };
-
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-        if !output.status.success() {
-            let resp = serde_json::json!({
-                "status": "error",
-                "code": "mount_failed",
-                "message": format!("Failed to mount Windows partition: {}", stderr),
-                "stdout": stdout,
-                "stderr": stderr,
-                "partition_uuid": best_partition.uuid,
-                "mount_path": mount_path,
+        let mount_path = windows_mount_path(&best_partition, preferred_mount_base);
+
+        // Create the directory and mount through the privileged helper
+        let resp = run_privileged(&helper::Request::Mount {
+            uuid: best_partition.uuid.clone(),
+            target: mount_path.clone(),
+        })?;
+
+        if !resp.ok {
+            return Err(LindyError::MountFailed {
+                message: format!(
+                    "Failed to mount Windows partition at {}: {}",
+                    mount_path, resp.message
+                ),
+                log: resp.log,
             });
-            return Ok(serde_json::to_string(&resp).unwrap());
+        }
+        if resp.code == "windows_hibernated" {
+            best_partition.hibernated = Some(true);
+            read_only = true;
         }
 
         mount_path
@@ -1729,64 +1870,55 @@ fn auto_mount_and_map(
         // Re-scan the now-mounted partition for users
         let (has_users, users) = check_for_windows_users(&mount_point);
         if !has_users || users.is_empty() {
-            let resp = serde_json::json!({
-                "status": "error",
-                "code": "no_users_detected",
-                "message": "No Windows users detected in the mounted partition. Please specify username manually.",
-                "mount_point": mount_point,
-                "partition_info": best_partition,
+            return Err(LindyError::NoUsersDetected {
+                message: "No Windows users detected in the mounted partition. Please specify username manually.".into(),
+                mount_point,
+                partition_info: Box::new(best_partition),
             });
-            return Ok(serde_json::to_string(&resp).unwrap());
         }
         users[0].clone()
     };
 
-    // Generate folder mappings
+    // Generate folder mappings; suggest_folder_mappings never returns an
+    // empty list, a user without matching folders is an error.
     let mappings = match suggest_folder_mappings(&mount_point, Some(detected_username.clone())) {
         Ok(m) => m,
         Err(e) => {
-            let resp = serde_json::json!({
-                "status": "error",
-                "code": "mapping_failed",
-                "message": format!("Failed to generate folder mappings: {}", e),
-                "mount_point": mount_point,
-                "username": detected_username,
-                "partition_info": best_partition,
+            return Err(LindyError::MappingFailed {
+                message: format!("Failed to generate folder mappings: {}", e),
+                mount_point,
+                username: detected_username,
+                partition_info: Box::new(best_partition),
             });
-            return Ok(serde_json::to_string(&resp).unwrap());
         }
     };
 
-    if mappings.is_empty() {
-        let resp = serde_json::json!({
-            "status": "error",
-            "code": "no_mappings_found",
-            "message": format!("No matching folders found for user '{}' at '{}'", detected_username, mount_point),
-            "mount_point": mount_point,
-            "username": detected_username,
-            "partition_info": best_partition,
-        });
-        return Ok(serde_json::to_string(&resp).unwrap());
-    }
-
     // Return success with all the information
-    let resp = serde_json::json!({
-        "status": "ok",
-        "code": "auto_map_success",
-        "message": format!("Successfully detected and mounted Windows partition with {} folder mappings", mappings.len()),
-        "windows_partition": best_partition,
-        "mappings": mappings,
-        "mount_point": mount_point,
-        "username": detected_username,
-    });
-
-    Ok(serde_json::to_string(&resp).unwrap())
+    let message = if read_only {
+        format!(
+            "Mounted Windows partition read-only (Windows is hibernated or Fast Startup is on) with {} folder mappings",
+            mappings.len()
+        )
+    } else {
+        format!(
+            "Successfully detected and mounted Windows partition with {} folder mappings",
+            mappings.len()
+        )
+    };
+    Ok(LindyResponse::AutoMapSuccess {
+        message,
+        windows_partition: Box::new(best_partition),
+        mappings,
+        mount_point,
+        username: detected_username,
+        read_only,
+    })
 }
 
 // Unit tests for backend logic. These tests avoid performing real privileged
 // operations by ensuring `pkexec` in PATH exits non-zero; `perform_mounts`
-// will therefore return a structured JSON with code `pkexec_failed` and will
-// not write metadata under `/var/lib/lindy`.
+// will therefore fail with `LindyError::PkexecFailed` and only records the
+// attempt (persisted=false) in the metadata store.
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1815,17 +1947,35 @@ mod tests {
             env::set_var("PATH", &new_path);
         }
 
-        let block =
-            "# lindy BEGIN: testid\n/dev/fake /mnt/fake auto defaults 0 2\n# lindy END: testid\n";
+        // a bind between two existing directories passes pre-flight validation
+        let src = tmpdir.join("src");
+        let dst = tmpdir.join("dst");
+        fs::create_dir(&src).expect("create src dir");
+        fs::create_dir(&dst).expect("create dst dir");
+        let block = format!(
+            "# lindy BEGIN: testid\n{} {} none bind 0 0\n# lindy END: testid\n",
+            src.display(),
+            dst.display()
+        );
+        let block = block.as_str();
         let id = "testid";
-        let targets = vec!["/mnt/fake".to_string()];
+        let targets = vec![dst.display().to_string()];
 
         // Call perform_mounts; since our fake pkexec exits with code 5, we expect
-        // a structured JSON response with code `pkexec_failed` (not `applied`).
-        let res = perform_mounts(block, id, targets.clone(), None, None, false, Some(false))
-            .expect("perform_mounts returned");
-        let v: serde_json::Value = serde_json::from_str(&res).expect("parse json");
-        assert_eq!(v.get("status").and_then(|s| s.as_str()), Some("error"));
+        // a `pkexec_failed` error (not `applied`).
+        let err = perform_mounts(
+            block,
+            id,
+            targets.clone(),
+            None,
+            None,
+            false,
+            Some(false),
+            None,
+            None,
+        )
+        .expect_err("perform_mounts should fail");
+        let v = serde_json::to_value(&err).expect("serialize error");
         assert_eq!(
             v.get("code").and_then(|s| s.as_str()),
             Some("pkexec_failed")
@@ -1841,6 +1991,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_perform_mounts_rejects_invalid_block() {
+        // A relative bind source fails validation before pkexec is ever spawned.
+        let block =
+            "# lindy BEGIN: invalidid\nrelative/src /tmp none bind 0 0\n# lindy END: invalidid\n";
+        let err = perform_mounts(
+            block,
+            "invalidid",
+            vec![],
+            None,
+            None,
+            false,
+            None,
+            None,
+            None,
+        )
+        .expect_err("perform_mounts should fail");
+        let v = serde_json::to_value(&err).expect("serialize error");
+        assert_eq!(
+            v.get("code").and_then(|s| s.as_str()),
+            Some("validation_failed")
+        );
+        let diags = v.get("diagnostics").and_then(|d| d.as_array()).unwrap();
+        assert!(diags
+            .iter()
+            .any(|d| d.get("severity").and_then(|s| s.as_str()) == Some("error")));
+    }
+
     #[test]
     fn test_detect_user_folders() {
         // Test that detect_user_folders returns expected folder structure
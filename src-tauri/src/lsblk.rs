@@ -0,0 +1,221 @@
+// Block devices as lsblk reports them.
+//
+// `lsblk -J` nests partitions under their disk and mapper devices under the
+// partition they sit on. `parse` flattens that tree; disk properties (model,
+// serial, transport) are only reported on the disk, so partitions inherit
+// them. The partition type GUID is what tells the Windows partition from the
+// recovery partition next to it: both are NTFS and often similar in label.
+
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Columns asked for; MOUNTPOINTS needs util-linux 2.37.
+const COLUMNS: &str = "NAME,PATH,FSTYPE,UUID,LABEL,MOUNTPOINT,MOUNTPOINTS,SIZE,FSUSED,FSAVAIL,\
+                       PARTTYPE,PARTLABEL,PARTUUID,MODEL,SERIAL,RM,ROTA,TRAN";
+/// Columns every lsblk in use knows, for when the full set is refused.
+const BASIC_COLUMNS: &str = "NAME,FSTYPE,UUID,LABEL,MOUNTPOINT,SIZE";
+
+/// One node of the lsblk tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockDevice {
+    pub name: String,
+    pub path: String,
+    pub fstype: Option<String>,
+    pub uuid: Option<String>,
+    pub label: Option<String>,
+    /// Every mount point, the first one is MOUNTPOINT.
+    pub mountpoints: Vec<String>,
+    pub size: Option<String>,
+    pub fsused: Option<String>,
+    pub fsavail: Option<String>,
+    pub parttype: Option<String>,
+    pub partlabel: Option<String>,
+    pub partuuid: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub removable: bool,
+    pub rotational: bool,
+    /// Transport of the disk (`nvme`, `sata`, `usb`, ...).
+    pub transport: Option<String>,
+    /// fstype of the device this one sits on, e.g. `BitLocker` for the
+    /// mapper device of an unlocked volume.
+    pub parent_fstype: Option<String>,
+    pub has_children: bool,
+}
+
+/// What a partition is for, from its type GUID (GPT) or type byte (MBR).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum PartitionKind {
+    /// Microsoft basic data: the Windows system or a data partition.
+    BasicData,
+    /// Windows recovery environment (WinRE).
+    Recovery,
+    EfiSystem,
+    MicrosoftReserved,
+    #[default]
+    Other,
+}
+
+impl PartitionKind {
+    pub fn from_parttype(parttype: Option<&str>) -> PartitionKind {
+        let Some(t) = parttype else {
+            return PartitionKind::Other;
+        };
+        match t.to_ascii_lowercase().as_str() {
+            "ebd0a0a2-b9e5-4433-87c0-68b6b72699c7" | "0x7" | "0x07" => PartitionKind::BasicData,
+            "de94bba4-06d1-4d40-a16a-bfd50179d6ac" | "0x27" => PartitionKind::Recovery,
+            "c12a7328-f81f-11d2-ba4b-00a0c93ec93b" | "0xef" => PartitionKind::EfiSystem,
+            "e3c9e316-0b5c-4db8-817d-f92df00215ae" => PartitionKind::MicrosoftReserved,
+            _ => PartitionKind::Other,
+        }
+    }
+}
+
+/// Run lsblk and flatten its output.
+pub fn run() -> Result<Vec<BlockDevice>, String> {
+    let json = query(COLUMNS).or_else(|_| query(BASIC_COLUMNS))?;
+    Ok(parse(&json))
+}
+
+/// Byte offset of partition `name` on its disk, from sysfs (in 512-byte
+/// sectors whatever the disk's sector size).
+pub fn start(name: &str) -> Option<u64> {
+    let sectors = std::fs::read_to_string(format!("/sys/class/block/{}/start", name)).ok()?;
+    sectors.trim().parse::<u64>().ok().map(|s| s * 512)
+}
+
+fn query(columns: &str) -> Result<serde_json::Value, String> {
+    let output = std::process::Command::new("lsblk")
+        .args(["-J", "-o", columns])
+        .output()
+        .map_err(|e| format!("failed to run lsblk: {}", e))?;
+    if !output.status.success() {
+        return Err("lsblk returned non-zero status".into());
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| format!("invalid lsblk json: {}", e))
+}
+
+fn string(node: &serde_json::Value, key: &str) -> Option<String> {
+    node.get(key)
+        .and_then(|x| x.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+// RM and ROTA are booleans in newer lsblk and "0"/"1" in older ones.
+fn flag(node: &serde_json::Value, key: &str) -> Option<bool> {
+    match node.get(key)? {
+        serde_json::Value::Bool(b) => Some(*b),
+        serde_json::Value::String(s) => Some(s == "1"),
+        serde_json::Value::Number(n) => Some(n.as_u64() == Some(1)),
+        _ => None,
+    }
+}
+
+/// Flatten `lsblk -J` output, parents before their children.
+pub fn parse(json: &serde_json::Value) -> Vec<BlockDevice> {
+    fn collect(out: &mut Vec<BlockDevice>, node: &serde_json::Value, parent: &BlockDevice) {
+        let Some(name) = string(node, "name") else {
+            return;
+        };
+        let children = node
+            .get("children")
+            .and_then(|x| x.as_array())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut mountpoints: Vec<String> = node
+            .get("mountpoints")
+            .and_then(|x| x.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|m| m.as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let Some(mp) = string(node, "mountpoint") {
+            if !mountpoints.contains(&mp) {
+                mountpoints.insert(0, mp);
+            }
+        }
+        let dev = BlockDevice {
+            path: string(node, "path").unwrap_or_else(|| format!("/dev/{}", name)),
+            name,
+            fstype: string(node, "fstype"),
+            uuid: string(node, "uuid"),
+            label: string(node, "label"),
+            mountpoints,
+            size: string(node, "size"),
+            fsused: string(node, "fsused"),
+            fsavail: string(node, "fsavail"),
+            parttype: string(node, "parttype"),
+            partlabel: string(node, "partlabel"),
+            partuuid: string(node, "partuuid"),
+            model: string(node, "model").or_else(|| parent.model.clone()),
+            serial: string(node, "serial").or_else(|| parent.serial.clone()),
+            removable: flag(node, "rm").unwrap_or(parent.removable),
+            rotational: flag(node, "rota").unwrap_or(parent.rotational),
+            transport: string(node, "tran").or_else(|| parent.transport.clone()),
+            parent_fstype: parent.fstype.clone(),
+            has_children: !children.is_empty(),
+        };
+        out.push(dev.clone());
+        for child in children {
+            collect(out, child, &dev);
+        }
+    }
+
+    let mut out = Vec::new();
+    if let Some(devices) = json.get("blockdevices").and_then(|x| x.as_array()) {
+        for dev in devices {
+            collect(&mut out, dev, &BlockDevice::default());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flattens_and_inherits_disk_properties() {
+        let json = serde_json::json!({"blockdevices": [{
+            "name": "nvme0n1", "path": "/dev/nvme0n1", "model": "Samsung SSD 980 ",
+            "serial": "S64D", "rm": false, "rota": false, "tran": "nvme",
+            "mountpoints": [null],
+            "children": [
+                {"name": "nvme0n1p3", "path": "/dev/nvme0n1p3", "fstype": "BitLocker",
+                 "parttype": "ebd0a0a2-b9e5-4433-87c0-68b6b72699c7", "partlabel": "Basic data partition",
+                 "rm": false, "rota": false, "mountpoints": [null],
+                 "children": [{"name": "lindy-nvme0n1p3", "path": "/dev/mapper/lindy-nvme0n1p3",
+                               "fstype": "ntfs", "uuid": "AAAA", "mountpoints": ["/mnt/win", "/srv/win"]}]},
+                {"name": "nvme0n1p4", "fstype": "ntfs", "parttype": "DE94BBA4-06D1-4D40-A16A-BFD50179D6AC",
+                 "rm": "0", "rota": "1", "mountpoint": null}
+            ]
+        }]});
+        let devs = parse(&json);
+        assert_eq!(devs.len(), 4);
+        let (p3, mapper, p4) = (&devs[1], &devs[2], &devs[3]);
+        assert_eq!(p3.model.as_deref(), Some("Samsung SSD 980"));
+        assert!(p3.has_children);
+        assert_eq!(mapper.parent_fstype.as_deref(), Some("BitLocker"));
+        assert_eq!(mapper.mountpoints, vec!["/mnt/win", "/srv/win"]);
+        assert_eq!(mapper.transport.as_deref(), Some("nvme"));
+        assert_eq!(p4.path, "/dev/nvme0n1p4");
+        assert!(p4.rotational && p4.mountpoints.is_empty());
+        assert_eq!(
+            PartitionKind::from_parttype(p3.parttype.as_deref()),
+            PartitionKind::BasicData
+        );
+        assert_eq!(
+            PartitionKind::from_parttype(p4.parttype.as_deref()),
+            PartitionKind::Recovery
+        );
+        assert_eq!(
+            PartitionKind::from_parttype(Some("0x27")),
+            PartitionKind::Recovery
+        );
+    }
+}
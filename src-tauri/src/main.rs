@@ -2,5 +2,12 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
-    lindy_lib::run()
+    // `lindy <command> ...` runs headless (see `lindy help`); no arguments
+    // opens the window.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        lindy_lib::run()
+    } else {
+        std::process::exit(lindy_lib::run_cli(args))
+    }
 }
@@ -0,0 +1,257 @@
+// What the app remembers about the blocks it manages.
+//
+// One JSON file per block id lives in `$HOME/.local/share/lindy`. Older
+// versions (and the scripts in scripts/) wrote the same records to
+// /var/lib/lindy, which the app never read back; `MetadataStore::open` merges
+// those into the user store once per process, and the helper deletes the
+// legacy file when it removes a block so removed blocks do not come back.
+
+use crate::fstab::FstabFile;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Version written by this build. Records without a `version` field are
+/// version 0 (the untyped JSON written before the store existed).
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Where metadata used to be written.
+pub const LEGACY_DIR: &str = "/var/lib/lindy";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockMetadata {
+    #[serde(default)]
+    pub version: u32,
+    pub id: String,
+    /// Block text including its BEGIN/END markers.
+    #[serde(default)]
+    pub block: String,
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// UUID of the partition the block mounts, when it has a partition line.
+    #[serde(default)]
+    pub partition_uuid: Option<String>,
+    /// Whether the block was written to /etc/fstab. Version 0 records only
+    /// carried the flag when persisting had been attempted and failed.
+    #[serde(default = "default_persisted")]
+    pub persisted: bool,
+    #[serde(default)]
+    pub rolled_back: bool,
+    #[serde(default)]
+    pub persist_error: Option<String>,
+    /// Helper log of the last persistence attempt.
+    #[serde(default, alias = "persist_stdout")]
+    pub persist_log: Option<String>,
+    #[serde(default)]
+    pub installed_at: u64,
+    #[serde(default)]
+    pub persisted_at: Option<u64>,
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+}
+
+fn default_persisted() -> bool {
+    true
+}
+
+impl BlockMetadata {
+    pub fn new(id: &str, block: &str, targets: Vec<String>, now: u64) -> BlockMetadata {
+        BlockMetadata {
+            version: SCHEMA_VERSION,
+            id: id.to_string(),
+            block: block.to_string(),
+            targets,
+            partition_uuid: partition_uuid(block),
+            persisted: false,
+            rolled_back: false,
+            persist_error: None,
+            persist_log: None,
+            installed_at: now,
+            persisted_at: None,
+            updated_at: None,
+        }
+    }
+
+    // Bring an older record up to SCHEMA_VERSION.
+    fn upgrade(mut self) -> BlockMetadata {
+        if self.partition_uuid.is_none() {
+            self.partition_uuid = partition_uuid(&self.block);
+        }
+        self.version = SCHEMA_VERSION;
+        self
+    }
+}
+
+/// UUID of the first `UUID=` entry in a block.
+pub fn partition_uuid(block: &str) -> Option<String> {
+    FstabFile::parse(block)
+        .entries()
+        .find_map(|e| e.spec.strip_prefix("UUID=").map(str::to_string))
+}
+
+pub struct MetadataStore {
+    dir: PathBuf,
+}
+
+impl MetadataStore {
+    pub fn new(dir: impl Into<PathBuf>) -> MetadataStore {
+        MetadataStore { dir: dir.into() }
+    }
+
+    /// The current user's store, with legacy records merged in on first use.
+    pub fn open() -> MetadataStore {
+        static MIGRATE: std::sync::Once = std::sync::Once::new();
+        let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+        let store = MetadataStore::new(Path::new(&home).join(".local/share/lindy"));
+        MIGRATE.call_once(|| {
+            if let Err(e) = store.migrate(Path::new(LEGACY_DIR)) {
+                eprintln!("lindy: metadata migration failed: {}", e);
+            }
+        });
+        store
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    pub fn load(&self, id: &str) -> Option<BlockMetadata> {
+        read_record(&self.path(id))
+    }
+
+    /// Every record in the store, ordered by id.
+    pub fn list(&self) -> Vec<BlockMetadata> {
+        let mut records = read_dir_records(&self.dir);
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+        records
+    }
+
+    /// The record whose targets include `target`.
+    pub fn find_target(&self, target: &str) -> Option<BlockMetadata> {
+        self.list()
+            .into_iter()
+            .find(|m| m.targets.iter().any(|t| t == target))
+    }
+
+    /// Write a record, replacing any previous one with the same id.
+    pub fn save(&self, meta: &BlockMetadata) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut meta = meta.clone();
+        meta.version = SCHEMA_VERSION;
+        let json = serde_json::to_string_pretty(&meta).map_err(io::Error::other)?;
+        let path = self.path(&meta.id);
+        let staged = path.with_extension("json.new");
+        fs::write(&staged, json)?;
+        fs::rename(&staged, &path)
+    }
+
+    /// Delete the record for `id`; a missing record is not an error.
+    pub fn remove(&self, id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Upgrade old records in the store and copy in records from `legacy`
+    /// for ids the store does not have yet. Returns how many were written.
+    pub fn migrate(&self, legacy: &Path) -> io::Result<usize> {
+        let mut written = 0;
+        for meta in read_dir_records(&self.dir) {
+            if meta.version < SCHEMA_VERSION {
+                self.save(&meta.upgrade())?;
+                written += 1;
+            }
+        }
+        for meta in read_dir_records(legacy) {
+            if self.load(&meta.id).is_none() {
+                self.save(&meta.upgrade())?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+}
+
+fn read_record(path: &Path) -> Option<BlockMetadata> {
+    let text = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn read_dir_records(dir: &Path) -> Vec<BlockMetadata> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+        .filter_map(|e| read_record(&e.path()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lindy_meta_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    const BLOCK: &str = "# lindy BEGIN: a\nUUID=1234-ABCD /mnt/win auto defaults 0 2\n/mnt/win/Docs /home/me/Docs none bind 0 0\n# lindy END: a\n";
+
+    #[test]
+    fn saves_loads_and_removes_records() {
+        let dir = temp_dir("store");
+        let store = MetadataStore::new(dir.join("user"));
+        let meta = BlockMetadata::new("a", BLOCK, vec!["/home/me/Docs".into()], 10);
+        assert_eq!(meta.partition_uuid.as_deref(), Some("1234-ABCD"));
+        store.save(&meta).unwrap();
+
+        assert_eq!(store.load("a"), Some(meta.clone()));
+        assert_eq!(
+            store.find_target("/home/me/Docs").map(|m| m.id),
+            Some("a".into())
+        );
+        assert_eq!(store.list().len(), 1);
+        store.remove("a").unwrap();
+        store.remove("a").unwrap();
+        assert!(store.list().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn migrates_legacy_records_without_overwriting() {
+        let dir = temp_dir("migrate");
+        let legacy = dir.join("legacy");
+        fs::create_dir(&legacy).unwrap();
+        let v0 = serde_json::json!({
+            "id": "a",
+            "block": BLOCK,
+            "targets": ["/home/me/Docs"],
+            "installed_at": 5,
+            "persist_stdout": "mount -a ok",
+        });
+        fs::write(legacy.join("a.json"), v0.to_string()).unwrap();
+        fs::write(legacy.join("b.json"), r#"{"id":"b","persisted":false}"#).unwrap();
+        fs::write(legacy.join("notes.txt"), "not metadata").unwrap();
+
+        let store = MetadataStore::new(dir.join("user"));
+        let mut newer = BlockMetadata::new("b", "", vec![], 20);
+        newer.persisted = true;
+        store.save(&newer).unwrap();
+
+        assert_eq!(store.migrate(&legacy).unwrap(), 1);
+        let a = store.load("a").unwrap();
+        assert_eq!(a.version, SCHEMA_VERSION);
+        assert!(a.persisted);
+        assert_eq!(a.partition_uuid.as_deref(), Some("1234-ABCD"));
+        assert_eq!(a.persist_log.as_deref(), Some("mount -a ok"));
+        assert_eq!(store.load("b"), Some(newer));
+        // nothing left to do the second time
+        assert_eq!(store.migrate(&legacy).unwrap(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,260 @@
+// What is actually mounted, from /proc/self/mountinfo.
+//
+// fstab only says what should be mounted. For a bind, the kernel records the
+// device of the filesystem the source lives on and the source's path inside
+// that filesystem (the `root` field), so comparing the topmost mount on a
+// target with the mount that contains the source tells whether the bind is
+// in place, points somewhere else, is covered by a later mount, or refers to
+// a directory that no longer exists.
+
+use crate::fstab::decode_field;
+use serde::Serialize;
+use std::path::Path;
+use ts_rs::TS;
+
+pub const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// One line of mountinfo (see proc(5)). Paths are decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+#[ts(export)]
+pub struct MountInfo {
+    pub mount_id: u32,
+    pub parent_id: u32,
+    /// `major:minor` of the mounted filesystem.
+    pub dev: String,
+    /// Path inside the filesystem that forms the root of this mount.
+    pub root: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub source: String,
+}
+
+impl MountInfo {
+    pub fn parse(line: &str) -> Option<MountInfo> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // optional fields end at the first lone "-"
+        let sep = fields.iter().position(|f| *f == "-")?;
+        if sep < 6 || fields.len() < sep + 3 {
+            return None;
+        }
+        Some(MountInfo {
+            mount_id: fields[0].parse().ok()?,
+            parent_id: fields[1].parse().ok()?,
+            dev: fields[2].to_string(),
+            root: decode_field(fields[3]),
+            mount_point: decode_field(fields[4]),
+            fs_type: fields[sep + 1].to_string(),
+            source: decode_field(fields[sep + 2]),
+        })
+    }
+}
+
+/// Live state of one bind from fstab.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum BindState {
+    /// The target shows the source directory.
+    Mounted,
+    NotMounted,
+    /// The bind is mounted but another mount was stacked on top of it.
+    Shadowed {
+        source: String,
+        root: String,
+    },
+    /// The target is mounted from somewhere else.
+    Mismatch {
+        source: String,
+        root: String,
+        expected_root: String,
+    },
+    /// The target is mounted but its source directory is gone.
+    Stale {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MountTable {
+    pub mounts: Vec<MountInfo>,
+}
+
+impl MountTable {
+    pub fn parse(text: &str) -> MountTable {
+        MountTable {
+            mounts: text.lines().filter_map(MountInfo::parse).collect(),
+        }
+    }
+
+    pub fn read() -> std::io::Result<MountTable> {
+        std::fs::read_to_string(MOUNTINFO_PATH).map(|t| MountTable::parse(&t))
+    }
+
+    /// Mounts on `path`, bottom first; the last one is visible.
+    fn stack(&self, path: &str) -> Vec<&MountInfo> {
+        self.mounts
+            .iter()
+            .filter(|m| m.mount_point == path)
+            .collect()
+    }
+
+    /// Whether something is mounted on `path`.
+    pub fn is_mount_point(&self, path: &str) -> bool {
+        !self.stack(trim_slash(path)).is_empty()
+    }
+
+    /// The visible mount containing `path` (an absolute, resolved path).
+    fn containing(&self, path: &str) -> Option<&MountInfo> {
+        self.mounts
+            .iter()
+            .filter(|m| under(path, &m.mount_point))
+            // longest mount point wins; on ties the later (upper) mount
+            .max_by_key(|m| m.mount_point.len())
+            .and_then(|m| self.stack(&m.mount_point).last().copied())
+    }
+
+    /// State of a bind of `src` onto `target`. The source is resolved
+    /// (symlinks followed) the same way mount(8) does before binding.
+    pub fn bind_state(&self, src: &str, target: &str) -> BindState {
+        let resolved = std::fs::canonicalize(src)
+            .ok()
+            .map(|p| p.to_string_lossy().into_owned());
+        self.state_of(resolved.as_deref(), src, target)
+    }
+
+    // `resolved` is None when the source path does not exist.
+    fn state_of(&self, resolved: Option<&str>, src: &str, target: &str) -> BindState {
+        let target = trim_slash(target);
+        let stack = self.stack(target);
+        let Some(top) = stack.last() else {
+            return BindState::NotMounted;
+        };
+        if top.root.ends_with("//deleted") {
+            return BindState::Stale {
+                reason: format!("{} was deleted while mounted", src),
+            };
+        }
+        let Some(resolved) = resolved else {
+            return BindState::Stale {
+                reason: format!("{} no longer exists", src),
+            };
+        };
+        let Some(host) = self.containing(resolved) else {
+            return BindState::Stale {
+                reason: format!("no mount contains {}", src),
+            };
+        };
+        let expected_root = join(&host.root, &resolved[host.mount_point.len()..]);
+        let shows_src = |m: &MountInfo| m.dev == host.dev && m.root == expected_root;
+        if shows_src(top) {
+            BindState::Mounted
+        } else if stack.iter().any(|m| shows_src(m)) {
+            BindState::Shadowed {
+                source: top.source.clone(),
+                root: top.root.clone(),
+            }
+        } else {
+            BindState::Mismatch {
+                source: top.source.clone(),
+                root: top.root.clone(),
+                expected_root,
+            }
+        }
+    }
+}
+
+fn trim_slash(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        p => p,
+    }
+}
+
+// Whether `path` is `dir` or lies below it.
+fn under(path: &str, dir: &str) -> bool {
+    Path::new(path).starts_with(dir)
+}
+
+// `rest` is what follows the mount point in the source path ("" or "/...").
+fn join(root: &str, rest: &str) -> String {
+    let rest = rest.trim_start_matches('/');
+    match (root, rest) {
+        (r, "") => r.to_string(),
+        ("/", r) => format!("/{}", r),
+        (root, r) => format!("{}/{}", root, r),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+40 22 259:3 / /mnt/win rw,relatime shared:20 - fuseblk /dev/nvme0n1p3 rw,user_id=0
+41 22 259:3 /Users/me/Documents /home/me/Documents rw,relatime shared:20 - fuseblk /dev/nvme0n1p3 rw
+42 22 259:3 /Users/me/Music /home/me/My\\040Music rw,relatime shared:20 - fuseblk /dev/nvme0n1p3 rw
+43 22 259:2 /home/me/elsewhere /home/me/Pictures rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+44 22 259:3 /Users/me/Videos /home/me/Videos rw,relatime shared:20 - fuseblk /dev/nvme0n1p3 rw
+45 22 0:50 / /home/me/Videos rw,relatime shared:30 - tmpfs tmpfs rw
+46 22 259:3 /Users/me/Old//deleted /home/me/Old rw,relatime - fuseblk /dev/nvme0n1p3 rw
+";
+
+    #[test]
+    fn parses_lines_and_escapes() {
+        let t = MountTable::parse(MOUNTINFO);
+        assert_eq!(t.mounts.len(), 8);
+        let music = &t.mounts[3];
+        assert_eq!(music.mount_point, "/home/me/My Music");
+        assert_eq!(music.root, "/Users/me/Music");
+        assert_eq!(music.fs_type, "fuseblk");
+        assert_eq!(music.source, "/dev/nvme0n1p3");
+        assert!(MountInfo::parse("22 1 259:2 / / rw").is_none());
+    }
+
+    #[test]
+    fn classifies_binds() {
+        let t = MountTable::parse(MOUNTINFO);
+        let state = |src: &str, target: &str| t.state_of(Some(src), src, target);
+
+        assert_eq!(
+            state("/mnt/win/Users/me/Documents", "/home/me/Documents/"),
+            BindState::Mounted
+        );
+        assert_eq!(
+            state("/mnt/win/Users/me/Music", "/home/me/My Music"),
+            BindState::Mounted
+        );
+        assert_eq!(
+            state("/mnt/win/Users/me/Desktop", "/home/me/Desktop"),
+            BindState::NotMounted
+        );
+        assert_eq!(
+            state("/mnt/win/Users/me/Pictures", "/home/me/Pictures"),
+            BindState::Mismatch {
+                source: "/dev/nvme0n1p2".into(),
+                root: "/home/me/elsewhere".into(),
+                expected_root: "/Users/me/Pictures".into(),
+            }
+        );
+        assert_eq!(
+            state("/mnt/win/Users/me/Videos", "/home/me/Videos"),
+            BindState::Shadowed {
+                source: "tmpfs".into(),
+                root: "/".into(),
+            }
+        );
+        assert!(matches!(
+            state("/mnt/win/Users/me/Old", "/home/me/Old"),
+            BindState::Stale { .. }
+        ));
+        assert!(matches!(
+            t.state_of(None, "/mnt/win/gone", "/home/me/Documents"),
+            BindState::Stale { .. }
+        ));
+        // a source on the root filesystem
+        assert_eq!(
+            state("/home/me/elsewhere", "/home/me/Pictures"),
+            BindState::Mounted
+        );
+    }
+}
@@ -0,0 +1,247 @@
+// Mount options of the partition line, per filesystem driver.
+//
+// Filesystems without Unix ownership (NTFS, exFAT, FAT) are mounted as root
+// unless the options name an owner, which leaves the bound folders read-only
+// for the user. The templates here give files to the user who set up the
+// mapping, with the masks and name handling each driver understands. NTFS
+// has two drivers: the in-kernel ntfs3 (5.15+) and the FUSE ntfs-3g; which
+// ones this system has is detected so the user only picks from those.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use ts_rs::TS;
+
+/// Driver written as the fstab type of the partition line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub enum Driver {
+    #[serde(rename = "ntfs3")]
+    Ntfs3,
+    #[serde(rename = "ntfs-3g")]
+    Ntfs3g,
+    #[serde(rename = "exfat")]
+    Exfat,
+    #[serde(rename = "vfat")]
+    Vfat,
+    #[serde(rename = "ext4")]
+    Ext4,
+    #[serde(rename = "btrfs")]
+    Btrfs,
+}
+
+// Kernel release that added ntfs3's `windows_names`.
+const NTFS3_WINDOWS_NAMES: (u32, u32) = (6, 2);
+
+// Where `mount` looks for FUSE helpers such as mount.ntfs-3g.
+const HELPER_DIRS: [&str; 4] = ["/usr/sbin", "/usr/bin", "/sbin", "/bin"];
+
+/// Owner of the mounted files and the running kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Host {
+    pub uid: u32,
+    pub gid: u32,
+    pub kernel: (u32, u32),
+}
+
+impl Host {
+    /// The user running lindy on this kernel.
+    pub fn current() -> Host {
+        let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").unwrap_or_default();
+        Host {
+            uid: unsafe { libc::getuid() },
+            gid: unsafe { libc::getgid() },
+            kernel: kernel_version(&release).unwrap_or((0, 0)),
+        }
+    }
+}
+
+impl Driver {
+    pub const ALL: [Driver; 6] = [
+        Driver::Ntfs3,
+        Driver::Ntfs3g,
+        Driver::Exfat,
+        Driver::Vfat,
+        Driver::Ext4,
+        Driver::Btrfs,
+    ];
+
+    pub fn fstab_type(self) -> &'static str {
+        match self {
+            Driver::Ntfs3 => "ntfs3",
+            Driver::Ntfs3g => "ntfs-3g",
+            Driver::Exfat => "exfat",
+            Driver::Vfat => "vfat",
+            Driver::Ext4 => "ext4",
+            Driver::Btrfs => "btrfs",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Driver> {
+        Driver::ALL.into_iter().find(|d| d.fstab_type() == s)
+    }
+
+    /// Drivers for a filesystem as lsblk names it, preferred first.
+    pub fn for_fstype(fstype: &str) -> &'static [Driver] {
+        match fstype {
+            "ntfs" | "ntfs3" | "ntfs-3g" => &[Driver::Ntfs3, Driver::Ntfs3g],
+            "exfat" => &[Driver::Exfat],
+            "vfat" => &[Driver::Vfat],
+            "ext4" => &[Driver::Ext4],
+            "btrfs" => &[Driver::Btrfs],
+            _ => &[],
+        }
+    }
+
+    /// Whether the driver runs in userspace through FUSE.
+    pub fn is_fuse(self) -> bool {
+        self == Driver::Ntfs3g
+    }
+
+    /// Driver-specific options; empty for filesystems that store ownership.
+    pub fn options(self, host: &Host) -> String {
+        let owner = format!("uid={},gid={}", host.uid, host.gid);
+        match self {
+            Driver::Ntfs3 => {
+                let mut opts = format!("{},dmask=022,fmask=133,iocharset=utf8,prealloc", owner);
+                if host.kernel >= NTFS3_WINDOWS_NAMES {
+                    opts.push_str(",windows_names");
+                }
+                opts
+            }
+            Driver::Ntfs3g => format!("{},umask=022,windows_names", owner),
+            Driver::Exfat => format!("{},dmask=022,fmask=133,iocharset=utf8", owner),
+            Driver::Vfat => format!(
+                "{},dmask=022,fmask=133,iocharset=utf8,shortname=mixed",
+                owner
+            ),
+            Driver::Ext4 | Driver::Btrfs => String::new(),
+        }
+    }
+}
+
+/// A driver and whether this system can use it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+#[ts(export)]
+pub struct DriverInfo {
+    pub driver: Driver,
+    pub available: bool,
+    pub fuse: bool,
+    /// Options the partition line gets with this driver.
+    pub options: String,
+}
+
+// "6.8.0-45-generic" -> (6, 8).
+fn kernel_version(release: &str) -> Option<(u32, u32)> {
+    let mut parts = release.trim().split(['.', '-']);
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Whether `driver` can be used, given the contents of /proc/filesystems
+/// (drivers loaded now), whether a kernel module is installed and whether a
+/// mount helper is on the system.
+pub fn is_available(
+    driver: Driver,
+    proc_filesystems: &str,
+    has_module: impl Fn(&str) -> bool,
+    has_helper: impl Fn(&str) -> bool,
+) -> bool {
+    if driver.is_fuse() {
+        return has_helper(&format!("mount.{}", driver.fstab_type()));
+    }
+    let name = driver.fstab_type();
+    proc_filesystems
+        .lines()
+        .any(|l| l.split_whitespace().last() == Some(name))
+        || has_module(name)
+}
+
+// Module `name` of the running kernel is installed, or built in.
+fn has_module(name: &str) -> bool {
+    let Ok(release) = std::fs::read_to_string("/proc/sys/kernel/osrelease") else {
+        return false;
+    };
+    let dir = Path::new("/lib/modules").join(release.trim());
+    dir.join("kernel/fs").join(name).is_dir()
+        || std::fs::read_to_string(dir.join("modules.builtin"))
+            .is_ok_and(|b| b.contains(&format!("kernel/fs/{}/", name)))
+}
+
+/// Every driver with whether this system has it and its options for `host`.
+pub fn detect(host: &Host) -> Vec<DriverInfo> {
+    let filesystems = std::fs::read_to_string("/proc/filesystems").unwrap_or_default();
+    let has_helper = |name: &str| HELPER_DIRS.iter().any(|d| Path::new(d).join(name).exists());
+    Driver::ALL
+        .into_iter()
+        .map(|driver| DriverInfo {
+            driver,
+            available: is_available(driver, &filesystems, has_module, has_helper),
+            fuse: driver.is_fuse(),
+            options: driver.options(host),
+        })
+        .collect()
+}
+
+/// The driver to use for `fstype` when the user did not choose one: the
+/// first available one, or None to leave it to `mount` (fstab type `auto`).
+pub fn preferred(fstype: &str, drivers: &[DriverInfo]) -> Option<Driver> {
+    Driver::for_fstype(fstype)
+        .iter()
+        .copied()
+        .find(|d| drivers.iter().any(|i| i.driver == *d && i.available))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST: Host = Host {
+        uid: 1000,
+        gid: 1001,
+        kernel: (6, 1),
+    };
+
+    #[test]
+    fn options_per_driver() {
+        assert_eq!(
+            Driver::Ntfs3.options(&HOST),
+            "uid=1000,gid=1001,dmask=022,fmask=133,iocharset=utf8,prealloc"
+        );
+        let newer = Host {
+            kernel: (6, 8),
+            ..HOST
+        };
+        assert!(Driver::Ntfs3
+            .options(&newer)
+            .ends_with(",prealloc,windows_names"));
+        assert_eq!(
+            Driver::Ntfs3g.options(&HOST),
+            "uid=1000,gid=1001,umask=022,windows_names"
+        );
+        assert_eq!(Driver::Ext4.options(&HOST), "");
+        assert_eq!(Driver::parse("ntfs-3g"), Some(Driver::Ntfs3g));
+        assert_eq!(kernel_version("6.8.0-45-generic"), Some((6, 8)));
+    }
+
+    #[test]
+    fn detects_available_drivers() {
+        let proc = "nodev\tsysfs\n\text4\n\tvfat\nnodev\tfuse\n";
+        let none = |_: &str| false;
+        assert!(is_available(Driver::Ext4, proc, none, none));
+        assert!(!is_available(Driver::Ntfs3, proc, none, none));
+        assert!(is_available(Driver::Ntfs3, proc, |m| m == "ntfs3", none));
+        assert!(is_available(Driver::Ntfs3g, proc, none, |h| h == "mount.ntfs-3g"));
+
+        let infos: Vec<DriverInfo> = Driver::ALL
+            .into_iter()
+            .map(|driver| DriverInfo {
+                driver,
+                available: driver != Driver::Ntfs3,
+                fuse: driver.is_fuse(),
+                options: String::new(),
+            })
+            .collect();
+        assert_eq!(preferred("ntfs", &infos), Some(Driver::Ntfs3g));
+        assert_eq!(preferred("xfs", &infos), None);
+    }
+}
@@ -0,0 +1,616 @@
+// Whether Windows left an NTFS volume in a state that must not be written.
+//
+// Hibernation and Fast Startup (which hibernates the kernel session on
+// shutdown) keep the volume's metadata cached in hiberfil.sys; writing to the
+// volume from Linux and then resuming Windows corrupts it. An unclean
+// shutdown sets the dirty flag of $Volume instead. ntfs-3g refuses read-write
+// mounts in both cases and ntfs3 may silently fall back to read-only, so the
+// helper checks first and mounts read-only itself.
+//
+// Both are read from the raw volume: the boot sector locates the MFT, record
+// 3 ($Volume) holds the flags, and the root directory index (record 5) leads
+// to hiberfil.sys, whose first four bytes are "hibr" while Windows is
+// hibernated ("wake" or zeroes after a resume or a full shutdown).
+//
+// The same directory lookups tell a Windows system volume from a data
+// volume without mounting it (`markers`): only the system volume has
+// Windows\System32, Users and pagefile.sys.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use ts_rs::TS;
+
+const MFT_RECORD: u64 = 0;
+const VOLUME_RECORD: u64 = 3;
+const ROOT_RECORD: u64 = 5;
+
+const ATTR_VOLUME_INFORMATION: u32 = 0x70;
+const ATTR_DATA: u32 = 0x80;
+const ATTR_INDEX_ROOT: u32 = 0x90;
+const ATTR_INDEX_ALLOCATION: u32 = 0xA0;
+const ATTR_END: u32 = 0xFFFF_FFFF;
+
+const VOLUME_IS_DIRTY: u16 = 0x0001;
+const INDEX_ENTRY_END: u16 = 0x0002;
+
+// Update sequence fixups are applied per 512 bytes whatever the sector size.
+const FIXUP_STRIDE: usize = 512;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VolumeState {
+    /// hiberfil.sys holds a hibernated session (hibernation or Fast Startup).
+    pub hibernated: bool,
+    /// $Volume has the dirty flag set.
+    pub dirty: bool,
+}
+
+impl VolumeState {
+    /// Why the volume should only be mounted read-only, if it should.
+    pub fn read_only_reason(&self) -> Option<&'static str> {
+        if self.hibernated {
+            Some("Windows is hibernated or shut down with Fast Startup")
+        } else if self.dirty {
+            Some("Windows did not shut down cleanly (the volume is marked dirty)")
+        } else {
+            None
+        }
+    }
+}
+
+/// What a Windows system volume has in its root.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct SystemMarkers {
+    /// Windows\System32
+    pub system32: bool,
+    pub users: bool,
+    pub pagefile: bool,
+}
+
+/// Whether the first bytes of hiberfil.sys describe a hibernated session.
+pub fn hiberfil_hibernated(header: &[u8]) -> bool {
+    header.len() >= 4 && header[..4].eq_ignore_ascii_case(b"hibr")
+}
+
+/// Inspect the NTFS volume on `device` (e.g. `/dev/disk/by-uuid/<uuid>`).
+/// Fails when it cannot be read or is not NTFS.
+pub fn inspect_device(device: &str) -> io::Result<VolumeState> {
+    inspect(&mut File::open(device)?)
+}
+
+pub fn inspect<R: Read + Seek>(dev: &mut R) -> io::Result<VolumeState> {
+    let vol = Volume::open(dev)?;
+    let volume = vol.record(dev, VOLUME_RECORD)?;
+    let dirty = match attribute(&volume, ATTR_VOLUME_INFORMATION)? {
+        Some(attr) => le(resident_content(attr)?, 0x0A, 2)? as u16 & VOLUME_IS_DIRTY != 0,
+        None => false,
+    };
+    let hibernated = match vol.find_in(dev, ROOT_RECORD, "hiberfil.sys")? {
+        Some(record) => {
+            let record = vol.record(dev, record)?;
+            let header = vol.read_start(dev, &record, 4)?;
+            hiberfil_hibernated(&header)
+        }
+        None => false,
+    };
+    Ok(VolumeState { hibernated, dirty })
+}
+
+/// Look for the system folders on the NTFS volume on `device`, without
+/// mounting it. Needs read access to the device, so root on most systems.
+pub fn markers_device(device: &str) -> io::Result<SystemMarkers> {
+    markers(&mut File::open(device)?)
+}
+
+pub fn markers<R: Read + Seek>(dev: &mut R) -> io::Result<SystemMarkers> {
+    let vol = Volume::open(dev)?;
+    let system32 = match vol.find_in(dev, ROOT_RECORD, "Windows")? {
+        Some(windows) => vol.find_in(dev, windows, "System32")?.is_some(),
+        None => false,
+    };
+    Ok(SystemMarkers {
+        system32,
+        users: vol.find_in(dev, ROOT_RECORD, "Users")?.is_some(),
+        pagefile: vol.find_in(dev, ROOT_RECORD, "pagefile.sys")?.is_some(),
+    })
+}
+
+/// The same markers for a volume mounted at `root`.
+pub fn markers_at(root: &Path) -> SystemMarkers {
+    SystemMarkers {
+        system32: root.join("Windows").join("System32").is_dir(),
+        users: root.join("Users").is_dir(),
+        pagefile: root.join("pagefile.sys").exists(),
+    }
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.to_string())
+}
+
+// Little-endian unsigned integer of `n` bytes at `off`.
+fn le(buf: &[u8], off: usize, n: usize) -> io::Result<u64> {
+    let bytes = buf
+        .get(off..off + n)
+        .ok_or_else(|| invalid("truncated NTFS structure"))?;
+    Ok(bytes.iter().rev().fold(0, |acc, b| (acc << 8) | *b as u64))
+}
+
+// Sizes given in the boot sector as a count of clusters, or as a negative
+// power of two of bytes when a record is smaller than a cluster.
+fn size_from(raw: u8, cluster: u64) -> u64 {
+    match raw as i8 {
+        n if n > 0 => n as u64 * cluster,
+        n => 1u64 << (-(n as i32)).min(31),
+    }
+}
+
+// Undo the update sequence: the last two bytes of every 512-byte stride were
+// replaced by the sequence number on write and saved in the array.
+fn apply_fixups(buf: &mut [u8], magic: &[u8; 4]) -> io::Result<()> {
+    if &buf[..4] != magic {
+        return Err(invalid("bad NTFS record magic"));
+    }
+    let offset = le(buf, 4, 2)? as usize;
+    let count = le(buf, 6, 2)? as usize;
+    let usn = le(buf, offset, 2)?;
+    for i in 1..count {
+        let end = i * FIXUP_STRIDE;
+        if end > buf.len() || le(buf, end - 2, 2)? != usn {
+            return Err(invalid("torn NTFS record"));
+        }
+        let saved = le(buf, offset + 2 * i, 2)? as u16;
+        buf[end - 2..end].copy_from_slice(&saved.to_le_bytes());
+    }
+    Ok(())
+}
+
+// The first unnamed attribute of type `kind` in an MFT record.
+fn attribute(record: &[u8], kind: u32) -> io::Result<Option<&[u8]>> {
+    let mut off = le(record, 0x14, 2)? as usize;
+    loop {
+        let ty = le(record, off, 4)? as u32;
+        if ty == ATTR_END {
+            return Ok(None);
+        }
+        let len = le(record, off + 4, 4)? as usize;
+        if len == 0 || off + len > record.len() {
+            return Err(invalid("bad NTFS attribute length"));
+        }
+        let unnamed = record.get(off + 9) == Some(&0);
+        // directory indexes are named $I30
+        let index = kind == ATTR_INDEX_ROOT || kind == ATTR_INDEX_ALLOCATION;
+        if ty == kind && (unnamed || index) {
+            return Ok(Some(&record[off..off + len]));
+        }
+        off += len;
+    }
+}
+
+fn resident_content(attr: &[u8]) -> io::Result<&[u8]> {
+    let len = le(attr, 0x10, 4)? as usize;
+    let off = le(attr, 0x14, 2)? as usize;
+    attr.get(off..off + len)
+        .ok_or_else(|| invalid("bad resident attribute"))
+}
+
+// Data runs of a non-resident attribute as (first cluster, cluster count);
+// sparse runs have no cluster.
+fn runs(attr: &[u8]) -> io::Result<Vec<(Option<u64>, u64)>> {
+    let mut pos = le(attr, 0x20, 2)? as usize;
+    let mut lcn: i64 = 0;
+    let mut out = Vec::new();
+    while let Some(&header) = attr.get(pos) {
+        if header == 0 {
+            break;
+        }
+        let (len_size, off_size) = ((header & 0x0F) as usize, (header >> 4) as usize);
+        let count = le(attr, pos + 1, len_size)?;
+        let start = if off_size == 0 {
+            None
+        } else {
+            let raw = le(attr, pos + 1 + len_size, off_size)?;
+            // sign-extend the relative offset
+            let shift = 64 - 8 * off_size as u32;
+            lcn += ((raw << shift) as i64) >> shift;
+            Some(lcn as u64)
+        };
+        out.push((start, count));
+        pos += 1 + len_size + off_size;
+    }
+    Ok(out)
+}
+
+// Records named in the index entries starting at `off` with the given name.
+fn index_lookup(buf: &[u8], mut off: usize, end: usize, name: &str) -> io::Result<Option<u64>> {
+    while off + 16 <= end {
+        let len = le(buf, off + 8, 2)? as usize;
+        let flags = le(buf, off + 12, 2)? as u16;
+        if flags & INDEX_ENTRY_END != 0 || len == 0 {
+            break;
+        }
+        // the key is a FILE_NAME attribute: name length at 0x40, name at 0x42
+        let key = off + 16;
+        let chars = buf.get(key + 0x40).copied().unwrap_or(0) as usize;
+        let units: Vec<u16> = (0..chars)
+            .map(|i| le(buf, key + 0x42 + 2 * i, 2).map(|u| u as u16))
+            .collect::<io::Result<_>>()?;
+        if String::from_utf16_lossy(&units).eq_ignore_ascii_case(name) {
+            return Ok(Some(le(buf, off, 6)?));
+        }
+        off += len;
+    }
+    Ok(None)
+}
+
+struct Volume {
+    cluster: u64,
+    mft_start: u64,
+    record_size: u64,
+    index_block_size: u64,
+    /// Data runs of $MFT; empty when they could not be read, in which case
+    /// the MFT is taken to be contiguous.
+    mft_runs: Vec<(Option<u64>, u64)>,
+}
+
+impl Volume {
+    fn open<R: Read + Seek>(dev: &mut R) -> io::Result<Volume> {
+        let mut boot = [0u8; 512];
+        dev.seek(SeekFrom::Start(0))?;
+        dev.read_exact(&mut boot)?;
+        if &boot[3..11] != b"NTFS    " {
+            return Err(invalid("not an NTFS volume"));
+        }
+        let sector = le(&boot, 0x0B, 2)?;
+        let cluster = sector * size_from(boot[0x0D], 1).max(1);
+        if cluster == 0 {
+            return Err(invalid("bad NTFS cluster size"));
+        }
+        let mut vol = Volume {
+            cluster,
+            mft_start: le(&boot, 0x30, 8)? * cluster,
+            record_size: size_from(boot[0x40], cluster),
+            index_block_size: size_from(boot[0x44], cluster),
+            mft_runs: Vec::new(),
+        };
+        // record 0 ($MFT) is always at the start and maps the rest of it
+        if let Ok(mft) = vol.record(dev, MFT_RECORD) {
+            if let Some(data) = attribute(&mft, ATTR_DATA)?.filter(|d| d.get(8) == Some(&1)) {
+                vol.mft_runs = runs(data)?;
+            }
+        }
+        Ok(vol)
+    }
+
+    fn read_at<R: Read + Seek>(&self, dev: &mut R, pos: u64, len: u64) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0u8; len as usize];
+        dev.seek(SeekFrom::Start(pos))?;
+        dev.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    // MFT record `n`, found through the data runs of $MFT. A record is
+    // assumed not to straddle two runs, which holds for clusters of at least
+    // the record size (4 KiB clusters, 1 KiB records by default).
+    fn record<R: Read + Seek>(&self, dev: &mut R, n: u64) -> io::Result<Vec<u8>> {
+        let mut offset = n * self.record_size;
+        let mut pos = if self.mft_runs.is_empty() {
+            Some(self.mft_start + offset)
+        } else {
+            None
+        };
+        for (lcn, count) in &self.mft_runs {
+            let len = count * self.cluster;
+            if offset < len {
+                let lcn = lcn.ok_or_else(|| invalid("sparse MFT record"))?;
+                pos = Some(lcn * self.cluster + offset);
+                break;
+            }
+            offset -= len;
+        }
+        let pos = pos.ok_or_else(|| invalid("MFT record out of range"))?;
+        let mut buf = self.read_at(dev, pos, self.record_size)?;
+        apply_fixups(&mut buf, b"FILE")?;
+        Ok(buf)
+    }
+
+    /// The first `len` bytes of the unnamed data of a file record.
+    fn read_start<R: Read + Seek>(
+        &self,
+        dev: &mut R,
+        record: &[u8],
+        len: usize,
+    ) -> io::Result<Vec<u8>> {
+        let Some(data) = attribute(record, ATTR_DATA)? else {
+            return Ok(Vec::new());
+        };
+        if data.get(8) == Some(&0) {
+            let content = resident_content(data)?;
+            return Ok(content[..len.min(content.len())].to_vec());
+        }
+        match runs(data)?.first() {
+            Some((Some(lcn), _)) => self.read_at(dev, lcn * self.cluster, len as u64),
+            // sparse or empty
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// MFT record number of `name` in the directory with record `dir`.
+    fn find_in<R: Read + Seek>(
+        &self,
+        dev: &mut R,
+        dir: u64,
+        name: &str,
+    ) -> io::Result<Option<u64>> {
+        let root = self.record(dev, dir)?;
+        if let Some(attr) = attribute(&root, ATTR_INDEX_ROOT)? {
+            let content = resident_content(attr)?;
+            // 16-byte index root header, then the node header
+            let first = 16 + le(content, 16, 4)? as usize;
+            let end = 16 + le(content, 20, 4)? as usize;
+            if let Some(n) = index_lookup(content, first, end.min(content.len()), name)? {
+                return Ok(Some(n));
+            }
+        }
+        let Some(attr) = attribute(&root, ATTR_INDEX_ALLOCATION)? else {
+            return Ok(None);
+        };
+        // the directories lindy looks in (the root and Windows) have at most
+        // a few hundred entries, so scanning every index block is cheaper
+        // than walking the tree
+        for (lcn, count) in runs(attr)? {
+            let Some(lcn) = lcn else { continue };
+            let bytes = self.read_at(dev, lcn * self.cluster, count * self.cluster)?;
+            for block in bytes.chunks(self.index_block_size as usize) {
+                let mut block = block.to_vec();
+                if apply_fixups(&mut block, b"INDX").is_err() {
+                    continue;
+                }
+                let first = 0x18 + le(&block, 0x18, 4)? as usize;
+                let end = 0x18 + le(&block, 0x1C, 4)? as usize;
+                if let Some(n) = index_lookup(&block, first, end.min(block.len()), name)? {
+                    return Ok(Some(n));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const CLUSTER: usize = 512;
+    const MFT_LCN: usize = 4;
+    const RECORD: usize = 1024;
+    const INDEX_LCN: usize = 64;
+    const INDEX_BLOCK: usize = 4096;
+    const MFT_CLUSTERS: u8 = 60;
+    const HIBERFIL_RECORD: usize = 20;
+    const WINDOWS_RECORD: usize = 24;
+    const HIBERFIL_LCN: usize = 80;
+
+    fn put(buf: &mut [u8], off: usize, v: u64, n: usize) {
+        buf[off..off + n].copy_from_slice(&v.to_le_bytes()[..n]);
+    }
+
+    // Apply the update sequence the way NTFS writes it.
+    fn protect(buf: &mut [u8], usa: usize) {
+        let count = buf.len() / FIXUP_STRIDE + 1;
+        put(buf, 4, usa as u64, 2);
+        put(buf, 6, count as u64, 2);
+        put(buf, usa, 7, 2);
+        for i in 1..count {
+            let end = i * FIXUP_STRIDE;
+            let saved = le(buf, end - 2, 2).unwrap();
+            put(buf, usa + 2 * i, saved, 2);
+            put(buf, end - 2, 7, 2);
+        }
+    }
+
+    fn resident(ty: u32, content: &[u8]) -> Vec<u8> {
+        let len = (0x18 + content.len()).div_ceil(8) * 8;
+        let mut a = vec![0u8; len];
+        put(&mut a, 0, ty as u64, 4);
+        put(&mut a, 4, len as u64, 4);
+        put(&mut a, 0x10, content.len() as u64, 4);
+        put(&mut a, 0x14, 0x18, 2);
+        a[0x18..0x18 + content.len()].copy_from_slice(content);
+        a
+    }
+
+    fn non_resident(ty: u32, name: &str, runlist: &[u8]) -> Vec<u8> {
+        let name_bytes: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let runs_at = (0x40 + name_bytes.len()).div_ceil(8) * 8;
+        let len = (runs_at + runlist.len() + 1).div_ceil(8) * 8;
+        let mut a = vec![0u8; len];
+        put(&mut a, 0, ty as u64, 4);
+        put(&mut a, 4, len as u64, 4);
+        a[8] = 1;
+        a[9] = name.encode_utf16().count() as u8;
+        put(&mut a, 0x0A, 0x40, 2);
+        put(&mut a, 0x20, runs_at as u64, 2);
+        a[0x40..0x40 + name_bytes.len()].copy_from_slice(&name_bytes);
+        a[runs_at..runs_at + runlist.len()].copy_from_slice(runlist);
+        a
+    }
+
+    fn record(attrs: &[Vec<u8>]) -> Vec<u8> {
+        let mut r = vec![0u8; RECORD];
+        r[..4].copy_from_slice(b"FILE");
+        put(&mut r, 0x14, 0x38, 2);
+        let mut off = 0x38;
+        for a in attrs {
+            r[off..off + a.len()].copy_from_slice(a);
+            off += a.len();
+        }
+        put(&mut r, off, ATTR_END as u64, 4);
+        protect(&mut r, 0x30);
+        r
+    }
+
+    fn index_entry(record: u64, name: &str) -> Vec<u8> {
+        let units: Vec<u16> = name.encode_utf16().collect();
+        let len = (16 + 0x42 + 2 * units.len()).div_ceil(8) * 8;
+        let mut e = vec![0u8; len];
+        put(&mut e, 0, record, 6);
+        put(&mut e, 8, len as u64, 2);
+        put(&mut e, 10, (0x42 + 2 * units.len()) as u64, 2);
+        e[16 + 0x40] = units.len() as u8;
+        for (i, u) in units.iter().enumerate() {
+            put(&mut e, 16 + 0x42 + 2 * i, *u as u64, 2);
+        }
+        e
+    }
+
+    fn end_entry() -> Vec<u8> {
+        let mut e = vec![0u8; 16];
+        put(&mut e, 8, 16, 2);
+        put(&mut e, 12, INDEX_ENTRY_END as u64, 2);
+        e
+    }
+
+    // Resident index root holding `entries`.
+    fn index_root(entries: &[Vec<u8>]) -> Vec<u8> {
+        let entries = entries.concat();
+        let mut root = vec![0u8; 32];
+        put(&mut root, 16, 16, 4);
+        put(&mut root, 20, (16 + entries.len()) as u64, 4);
+        root.extend(entries);
+        resident(ATTR_INDEX_ROOT, &root)
+    }
+
+    fn volume(dirty: bool, hiberfil: &[u8]) -> Vec<u8> {
+        let mut v = vec![0u8; (HIBERFIL_LCN + 1) * CLUSTER];
+        v[3..11].copy_from_slice(b"NTFS    ");
+        put(&mut v, 0x0B, CLUSTER as u64, 2);
+        v[0x0D] = 1;
+        put(&mut v, 0x30, MFT_LCN as u64, 8);
+        v[0x40] = (-10i8) as u8; // 1024-byte records
+        v[0x44] = (INDEX_BLOCK / CLUSTER) as u8;
+
+        let mut info = [0u8; 12];
+        info[8] = 3; // NTFS 3.1
+        info[9] = 1;
+        put(&mut info, 0x0A, dirty as u64, 2);
+        let mut records = vec![
+            (
+                MFT_RECORD as usize,
+                record(&[non_resident(
+                    ATTR_DATA,
+                    "",
+                    &[0x11, MFT_CLUSTERS, MFT_LCN as u8],
+                )]),
+            ),
+            (
+                VOLUME_RECORD as usize,
+                record(&[resident(ATTR_VOLUME_INFORMATION, &info)]),
+            ),
+        ];
+
+        // root: Users and Windows in the resident root, the rest in an
+        // index block
+        let runlist = [0x11, (INDEX_BLOCK / CLUSTER) as u8, INDEX_LCN as u8];
+        records.push((
+            ROOT_RECORD as usize,
+            record(&[
+                index_root(&[
+                    index_entry(40, "Users"),
+                    index_entry(WINDOWS_RECORD as u64, "Windows"),
+                    end_entry(),
+                ]),
+                non_resident(ATTR_INDEX_ALLOCATION, "$I30", &runlist),
+            ]),
+        ));
+        records.push((
+            WINDOWS_RECORD,
+            record(&[index_root(&[index_entry(41, "System32"), end_entry()])]),
+        ));
+
+        let mut block = vec![0u8; INDEX_BLOCK];
+        block[..4].copy_from_slice(b"INDX");
+        let entries = [
+            index_entry(30, "bootmgr"),
+            index_entry(HIBERFIL_RECORD as u64, "hiberfil.sys"),
+            index_entry(42, "pagefile.sys"),
+            end_entry(),
+        ]
+        .concat();
+        put(&mut block, 0x18, 0x28, 4);
+        put(&mut block, 0x1C, (0x28 + entries.len()) as u64, 4);
+        block[0x40..0x40 + entries.len()].copy_from_slice(&entries);
+        protect(&mut block, 0x28);
+        v[INDEX_LCN * CLUSTER..][..INDEX_BLOCK].copy_from_slice(&block);
+
+        records.push((
+            HIBERFIL_RECORD,
+            record(&[non_resident(ATTR_DATA, "", &[0x11, 1, HIBERFIL_LCN as u8])]),
+        ));
+        v[HIBERFIL_LCN * CLUSTER..][..hiberfil.len()].copy_from_slice(hiberfil);
+
+        for (n, r) in records {
+            v[MFT_LCN * CLUSTER + n * RECORD..][..RECORD].copy_from_slice(&r);
+        }
+        v
+    }
+
+    #[test]
+    fn reads_hibernation_and_dirty_flag() {
+        let state =
+            |dirty, header: &[u8]| inspect(&mut Cursor::new(volume(dirty, header))).unwrap();
+        assert_eq!(
+            state(false, b"HIBR"),
+            VolumeState {
+                hibernated: true,
+                dirty: false
+            }
+        );
+        assert_eq!(state(false, b"wake"), VolumeState::default());
+        assert_eq!(
+            state(true, b"\0\0\0\0"),
+            VolumeState {
+                hibernated: false,
+                dirty: true
+            }
+        );
+        assert!(state(false, b"hibr").read_only_reason().is_some());
+        assert!(state(false, b"wake").read_only_reason().is_none());
+    }
+
+    #[test]
+    fn finds_system_folders() {
+        let mut dev = Cursor::new(volume(false, b""));
+        assert_eq!(
+            markers(&mut dev).unwrap(),
+            SystemMarkers {
+                system32: true,
+                users: true,
+                pagefile: true
+            }
+        );
+        let vol = Volume::open(&mut dev).unwrap();
+        assert_eq!(
+            vol.mft_runs,
+            vec![(Some(MFT_LCN as u64), MFT_CLUSTERS as u64)]
+        );
+        assert_eq!(
+            vol.find_in(&mut dev, ROOT_RECORD, "Program Files").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_other_volumes_and_torn_records() {
+        let mut exfat = volume(false, b"");
+        exfat[3..11].copy_from_slice(b"EXFAT   ");
+        assert!(inspect(&mut Cursor::new(exfat)).is_err());
+
+        let mut torn = volume(false, b"");
+        let end = MFT_LCN * CLUSTER + VOLUME_RECORD as usize * RECORD + FIXUP_STRIDE;
+        torn[end - 1] ^= 0xFF;
+        assert!(inspect(&mut Cursor::new(torn)).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+// Dry runs of the mutating commands.
+//
+// With `dry_run` set, perform_mounts, apply_fstab_block, remove_fstab_block,
+// remove_block_for_target and auto_mount_and_map return a `Plan` instead of
+// running pkexec. It is computed the way the helper computes the real change
+// (same block normalisation, same `plan_remove`), so what the confirmation
+// screen shows is what the helper will do, as long as /etc/fstab and the
+// mount table do not change in between.
+
+use crate::backups;
+use crate::fstab::{self, FstabFile};
+use crate::helper::{self, Failure};
+use crate::validate::Candidate;
+use serde::Serialize;
+use std::path::Path;
+use ts_rs::TS;
+
+// Lines of context around the change in `fstab_diff`.
+const CONTEXT: usize = 3;
+
+/// One step the helper would run, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    Mkdir {
+        path: String,
+    },
+    /// With `read_only` the partition is mounted `ro` because Windows is
+    /// hibernated.
+    Mount {
+        source: String,
+        target: String,
+        read_only: bool,
+    },
+    /// With `lazy_if_busy` a busy target is unmounted with `umount -l`
+    /// instead of failing the command.
+    Umount {
+        target: String,
+        lazy_if_busy: bool,
+    },
+    /// `mount -a` after /etc/fstab was replaced; `mounts` are the entries
+    /// it is expected to mount.
+    MountAll {
+        mounts: Vec<String>,
+    },
+}
+
+/// What a mutating command would do. Nothing is changed to compute it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, TS)]
+#[ts(export)]
+pub struct Plan {
+    /// Unified diff of /etc/fstab; empty when the file stays as it is.
+    pub fstab_diff: String,
+    pub operations: Vec<Operation>,
+    /// Backups of /etc/fstab that would be written.
+    pub backups: Vec<String>,
+    /// Reasons the command is expected to fail or to need attention.
+    pub conflicts: Vec<String>,
+}
+
+impl Plan {
+    // Replacing `current` with `new`: a backup, the new file and `mount -a`.
+    fn install(current: &FstabFile, new: &FstabFile, mounts: Vec<String>, now: u64) -> Plan {
+        let backup = backups::path_in(Path::new(backups::BACKUP_DIR), now);
+        Plan {
+            fstab_diff: unified_diff(&current.to_string(), &new.to_string(), "/etc/fstab"),
+            operations: vec![Operation::MountAll { mounts }],
+            backups: vec![backup.display().to_string()],
+            conflicts: Vec::new(),
+        }
+    }
+
+    /// Appending block `id` to `current`, as `ApplyBlock` does. `mounted`
+    /// tells whether a path is a mount point now.
+    pub fn apply(
+        current: &FstabFile,
+        id: &str,
+        block: &str,
+        now: u64,
+        mounted: impl Fn(&str) -> bool,
+    ) -> Plan {
+        let lines = match helper::check_id(id)
+            .map_err(|f| f.message)
+            .and_then(|_| fstab::block_lines(id, block))
+        {
+            Ok(lines) => lines,
+            Err(e) => {
+                return Plan {
+                    conflicts: vec![e],
+                    ..Plan::default()
+                }
+            }
+        };
+        let block = FstabFile {
+            lines,
+            trailing_newline: true,
+        };
+        let candidate = Candidate::with_appended(Some(current), &block.to_string());
+        let (mounted, unmounted): (Vec<String>, Vec<String>) = helper::mountpoints(block.entries())
+            .into_iter()
+            .partition(|m| mounted(m));
+        let mut plan = Plan::install(current, &candidate.file, unmounted, now);
+        if current.block(id).is_some() {
+            plan.conflicts
+                .push(format!("block id {} already exists in /etc/fstab", id));
+        }
+        for m in mounted {
+            plan.conflicts
+                .push(format!("{} is already a mount point", m));
+        }
+        plan
+    }
+
+    /// Removing the block `id`, or the block binding onto `target`, as
+    /// `RemoveBlock` does. Only targets that are mounted get unmounted.
+    pub fn remove(
+        current: &FstabFile,
+        id: Option<&str>,
+        target: Option<&str>,
+        force: bool,
+        now: u64,
+        mounted: impl Fn(&str) -> bool,
+    ) -> Result<Plan, Failure> {
+        let mut new = current.clone();
+        let removal = helper::plan_remove(&mut new, id, target)?;
+        let mut plan = Plan::install(current, &new, Vec::new(), now);
+        let umounts = removal
+            .targets
+            .iter()
+            .rev()
+            .chain(&removal.partition)
+            .filter(|t| mounted(t))
+            .map(|t| Operation::Umount {
+                target: t.clone(),
+                lazy_if_busy: force,
+            });
+        plan.operations.splice(0..0, umounts);
+        Ok(plan)
+    }
+
+    /// Mounting a partition by UUID, as `Mount` does.
+    pub fn mount(uuid: &str, target: &str, target_exists: bool, read_only: bool) -> Plan {
+        let mut operations = Vec::new();
+        if !target_exists {
+            operations.push(Operation::Mkdir {
+                path: target.to_string(),
+            });
+        }
+        operations.push(Operation::Mount {
+            source: format!("UUID={}", uuid),
+            target: target.to_string(),
+            read_only,
+        });
+        Plan {
+            operations,
+            ..Plan::default()
+        }
+    }
+}
+
+/// Unified diff from `old` to `new` with a single hunk spanning the first to
+/// the last changed line, which is every change lindy makes to /etc/fstab
+/// (one block appended, rewritten or removed). Empty when the lines are equal.
+pub fn unified_diff(old: &str, new: &str, label: &str) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    if a == b {
+        return String::new();
+    }
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let start = prefix.saturating_sub(CONTEXT);
+    let a_end = (a.len() - suffix + CONTEXT).min(a.len());
+    let b_end = (b.len() - suffix + CONTEXT).min(b.len());
+    let range = |start: usize, end: usize| match end - start {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        n => format!("{},{}", start + 1, n),
+    };
+
+    let mut out = format!(
+        "--- {}\n+++ {}\n@@ -{} +{} @@\n",
+        label,
+        label,
+        range(start, a_end),
+        range(start, b_end)
+    );
+    let mut push = |mark: char, lines: &[&str]| {
+        for l in lines {
+            out.push(mark);
+            out.push_str(l);
+            out.push('\n');
+        }
+    };
+    push(' ', &a[start..prefix]);
+    push('-', &a[prefix..a.len() - suffix]);
+    push('+', &b[prefix..b.len() - suffix]);
+    push(' ', &a[a.len() - suffix..a_end]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FSTAB: &str = "UUID=r / ext4 defaults 0 1\n\
+                         UUID=h /home ext4 defaults 0 2\n\
+                         # lindy BEGIN: w\n\
+                         UUID=AAAA /mnt/win ntfs3 defaults 0 2\n\
+                         /mnt/win/Users/me/Documents /home/me/Documents none bind 0 0\n\
+                         # lindy END: w\n";
+
+    #[test]
+    fn diffs_one_hunk_with_context() {
+        assert_eq!(unified_diff(FSTAB, FSTAB, "/etc/fstab"), "");
+        let removed = "UUID=r / ext4 defaults 0 1\nUUID=h /home ext4 defaults 0 2\n";
+        assert_eq!(
+            unified_diff(FSTAB, removed, "/etc/fstab"),
+            "--- /etc/fstab\n+++ /etc/fstab\n@@ -1,6 +1,2 @@\n\
+             \x20UUID=r / ext4 defaults 0 1\n\
+             \x20UUID=h /home ext4 defaults 0 2\n\
+             -# lindy BEGIN: w\n\
+             -UUID=AAAA /mnt/win ntfs3 defaults 0 2\n\
+             -/mnt/win/Users/me/Documents /home/me/Documents none bind 0 0\n\
+             -# lindy END: w\n"
+        );
+        assert_eq!(
+            unified_diff("", "a\n", "f"),
+            "--- f\n+++ f\n@@ -0,0 +1 @@\n+a\n"
+        );
+    }
+
+    #[test]
+    fn plans_apply_and_remove() {
+        let fstab = FstabFile::parse(FSTAB);
+        let mounted = |p: &str| p == "/mnt/win" || p == "/home/me/Documents";
+
+        let plan = Plan::apply(
+            &fstab,
+            "m",
+            "/mnt/win/Music /home/me/Music none bind 0 0\n/mnt/win /home/me/win none bind 0 0",
+            7,
+            mounted,
+        );
+        assert!(plan.fstab_diff.contains("\n+# lindy BEGIN: m\n"));
+        assert_eq!(
+            plan.operations,
+            vec![Operation::MountAll {
+                mounts: vec!["/home/me/Music".into(), "/home/me/win".into()]
+            }]
+        );
+        assert_eq!(plan.backups, vec!["/etc/fstab.lindy.bak.7"]);
+        assert!(plan.conflicts.is_empty());
+
+        let again = Plan::apply(
+            &fstab,
+            "w",
+            "/a /home/me/Documents none bind 0 0",
+            7,
+            mounted,
+        );
+        assert_eq!(again.conflicts.len(), 2, "{:?}", again.conflicts);
+
+        let plan =
+            Plan::remove(&fstab, None, Some("/home/me/Documents"), true, 7, mounted).unwrap();
+        assert!(plan.fstab_diff.contains("\n-# lindy END: w\n"));
+        assert_eq!(
+            plan.operations,
+            vec![
+                Operation::Umount {
+                    target: "/home/me/Documents".into(),
+                    lazy_if_busy: true
+                },
+                Operation::Umount {
+                    target: "/mnt/win".into(),
+                    lazy_if_busy: true
+                },
+                Operation::MountAll { mounts: vec![] },
+            ]
+        );
+        assert!(Plan::remove(&fstab, Some("nope"), None, false, 7, mounted).is_err());
+    }
+}
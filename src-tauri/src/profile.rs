@@ -0,0 +1,299 @@
+// Declarative mapping profiles.
+//
+// A profile is a TOML file describing one lindy block: the partition and
+// where it is mounted, the Windows user, and the folders to bind. `render`
+// turns it into block text with the same line builders the UI uses and
+// `plan` compares that with /etc/fstab, so applying a profile a second time
+// changes nothing and identical machines end up with identical blocks.
+//
+//     id = "lab"
+//     windows_user = "Student"
+//
+//     [partition]
+//     label = "Windows"            # or uuid = "..."
+//     mount_base = "/mnt/windows"
+//     driver = "ntfs3"             # optional: ntfs3, ntfs-3g, exfat, ...
+//     options = "defaults,nofail"  # optional
+//
+//     [[folders]]
+//     windows = "Documents"        # relative to <mount_base>/Users/<windows_user>
+//     linux = "~/Documents"
+//     options = "bind,ro"          # optional
+
+use crate::fstab::{FstabFile, FstabLine};
+use crate::mountopts::Driver;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Id of the block the profile owns.
+    pub id: String,
+    pub partition: PartitionSpec,
+    #[serde(default)]
+    pub windows_user: Option<String>,
+    #[serde(default)]
+    pub folders: Vec<FolderPair>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartitionSpec {
+    #[serde(default)]
+    pub uuid: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Where the partition is (or gets) mounted.
+    pub mount_base: String,
+    /// fstab type of the partition line; `auto` when omitted, which keeps
+    /// the block the same on every machine.
+    #[serde(default)]
+    pub driver: Option<Driver>,
+    /// Options of the partition line; the driver's template when omitted.
+    #[serde(default)]
+    pub options: Option<String>,
+    /// The partition is mounted by other means; write no line for it.
+    #[serde(default)]
+    pub skip_mount: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FolderPair {
+    /// Absolute, or relative to the Windows user's folder (to `mount_base`
+    /// without a `windows_user`).
+    pub windows: String,
+    /// Absolute; a leading `~` is the current user's home.
+    pub linux: String,
+    /// Bind options, `bind` when omitted.
+    #[serde(default)]
+    pub options: Option<String>,
+}
+
+/// Block text and bind targets of a profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendered {
+    pub block: String,
+    pub targets: Vec<String>,
+}
+
+impl Profile {
+    pub fn parse(text: &str) -> Result<Profile, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// Build the block, or list every problem with the profile.
+    /// `uuid_for_label` looks a partition up by label.
+    pub fn render(
+        &self,
+        uuid_for_label: impl Fn(&str) -> Option<String>,
+        home: &str,
+    ) -> Result<Rendered, Vec<String>> {
+        let mut problems = Vec::new();
+        if let Err(f) = crate::helper::check_id(&self.id) {
+            problems.push(f.message);
+        }
+        let part = &self.partition;
+        if !part.mount_base.starts_with('/') {
+            problems.push(format!("mount_base {} is not absolute", part.mount_base));
+        }
+        let uuid = match (&part.uuid, &part.label) {
+            (Some(_), Some(_)) => {
+                problems.push("partition has both uuid and label".into());
+                None
+            }
+            (Some(uuid), None) => Some(uuid.clone()),
+            (None, Some(label)) => {
+                let found = uuid_for_label(label);
+                if found.is_none() {
+                    problems.push(format!("no partition labelled {}", label));
+                }
+                found
+            }
+            (None, None) if part.skip_mount => None,
+            (None, None) => {
+                problems.push("partition needs a uuid or label (or skip_mount)".into());
+                None
+            }
+        };
+        if self.folders.is_empty() {
+            problems.push("no folders to map".into());
+        }
+
+        let user_dir = match &self.windows_user {
+            Some(user) => format!("{}/Users/{}", part.mount_base.trim_end_matches('/'), user),
+            None => part.mount_base.trim_end_matches('/').to_string(),
+        };
+        let mut binds = Vec::new();
+        let mut targets: Vec<String> = Vec::new();
+        for f in &self.folders {
+            let src = if f.windows.starts_with('/') {
+                f.windows.clone()
+            } else {
+                format!("{}/{}", user_dir, f.windows.trim_start_matches('/'))
+            };
+            let target = match f.linux.strip_prefix('~') {
+                Some(rest) => format!("{}{}", home, rest),
+                None => f.linux.clone(),
+            };
+            if !target.starts_with('/') {
+                problems.push(format!("linux path {} is not absolute", f.linux));
+            }
+            if targets.contains(&target) {
+                problems.push(format!("{} is mapped twice", target));
+            }
+            binds.push(crate::bind_line(&src, &target, f.options.as_deref()));
+            targets.push(target);
+        }
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        let mut lines = vec![format!("{} {}", crate::fstab::BEGIN_MARKER, self.id)];
+        if let (Some(uuid), false) = (uuid, part.skip_mount) {
+            lines.push(crate::partition_line(
+                &uuid,
+                &part.mount_base,
+                part.driver,
+                part.options.as_deref(),
+            ));
+        }
+        lines.extend(binds);
+        lines.push(format!("{} {}", crate::fstab::END_MARKER, self.id));
+        Ok(Rendered {
+            block: lines.join("\n") + "\n",
+            targets,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanAction {
+    Create,
+    Update,
+    Unchanged,
+}
+
+/// How /etc/fstab has to change to match a profile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProfilePlan {
+    pub id: String,
+    pub action: PlanAction,
+    pub block: String,
+    pub targets: Vec<String>,
+    /// Entry lines the profile adds to the current block.
+    pub added: Vec<String>,
+    /// Entry lines of the current block the profile drops.
+    pub removed: Vec<String>,
+}
+
+/// Compare a rendered profile with the block `id` in `fstab`. Entries are
+/// compared parsed, so spacing differences do not count as changes.
+pub fn plan(id: &str, rendered: Rendered, fstab: &FstabFile) -> ProfilePlan {
+    let wanted: Vec<FstabLine> = FstabFile::parse(&rendered.block).lines;
+    let current = fstab.block(id);
+    let current_lines = current
+        .as_ref()
+        .map(|b| b.lines.clone())
+        .unwrap_or_default();
+    let missing_from = |a: &[FstabLine], b: &[FstabLine]| -> Vec<String> {
+        a.iter()
+            .filter(|l| {
+                l.entry()
+                    .is_some_and(|e| !b.iter().any(|o| o.entry() == Some(e)))
+            })
+            .map(|l| l.raw.clone())
+            .collect()
+    };
+    let added = missing_from(&wanted, &current_lines);
+    let removed = missing_from(&current_lines, &wanted);
+    let action = match current {
+        None => PlanAction::Create,
+        Some(_) if added.is_empty() && removed.is_empty() => PlanAction::Unchanged,
+        Some(_) => PlanAction::Update,
+    };
+    ProfilePlan {
+        id: id.to_string(),
+        action,
+        block: rendered.block,
+        targets: rendered.targets,
+        added,
+        removed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = r#"
+id = "lab"
+windows_user = "Student"
+
+[partition]
+label = "Windows"
+mount_base = "/mnt/windows"
+
+[[folders]]
+windows = "Documents"
+linux = "~/Documents"
+
+[[folders]]
+windows = "/mnt/windows/Shared Music"
+linux = "/home/me/Music"
+options = "bind,ro"
+"#;
+
+    fn label(l: &str) -> Option<String> {
+        (l == "Windows").then(|| "1234-ABCD".to_string())
+    }
+
+    #[test]
+    fn renders_block_from_profile() {
+        let profile = Profile::parse(PROFILE).unwrap();
+        let r = profile.render(label, "/home/me").unwrap();
+        assert_eq!(
+            r.block,
+            "# lindy BEGIN: lab\n\
+             UUID=1234-ABCD /mnt/windows auto defaults,noatime,nofail,x-systemd.automount,x-systemd.device-timeout=10 0 2\n\
+             /mnt/windows/Users/Student/Documents /home/me/Documents none bind 0 0\n\
+             /mnt/windows/Shared\\040Music /home/me/Music none bind,ro 0 0\n\
+             # lindy END: lab\n"
+        );
+        assert_eq!(r.targets, vec!["/home/me/Documents", "/home/me/Music"]);
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        assert!(Profile::parse("id = \"x\"\nbogus = 1\n").is_err());
+        let profile = Profile::parse(
+            "id = \"bad id\"\n[partition]\nlabel = \"Nope\"\nmount_base = \"rel\"\n\
+             [[folders]]\nwindows = \"A\"\nlinux = \"/t\"\n\
+             [[folders]]\nwindows = \"B\"\nlinux = \"/t\"\n",
+        )
+        .unwrap();
+        let problems = profile.render(label, "/home/me").unwrap_err();
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+    }
+
+    #[test]
+    fn plans_create_update_and_unchanged() {
+        let profile = Profile::parse(PROFILE).unwrap();
+        let r = profile.render(label, "/home/me").unwrap();
+
+        let empty = FstabFile::parse("UUID=r / ext4 defaults 0 1\n");
+        assert_eq!(plan("lab", r.clone(), &empty).action, PlanAction::Create);
+
+        let same = FstabFile::parse(&format!("{}{}", empty, r.block.replace(" 0 0", "   0   0")));
+        assert_eq!(plan("lab", r.clone(), &same).action, PlanAction::Unchanged);
+
+        let old = FstabFile::parse(
+            "# lindy BEGIN: lab\n/x /home/me/Old none bind 0 0\n# lindy END: lab\n",
+        );
+        let p = plan("lab", r, &old);
+        assert_eq!(p.action, PlanAction::Update);
+        assert_eq!(p.removed, vec!["/x /home/me/Old none bind 0 0"]);
+        assert_eq!(p.added.len(), 3);
+    }
+}
@@ -0,0 +1,730 @@
+// Windows user profiles, read from the registry hives of an offline Windows.
+//
+// The folders under Users are not the list of users: Public and Default are
+// there too, deleted accounts leave theirs behind, profiles can live
+// elsewhere, and a folder is named after the account as it was when the
+// profile was created. The SOFTWARE hive lists the real profiles under
+// ProfileList (SID and ProfileImagePath); the SAM hive maps the SIDs of
+// local accounts to their names. A user's own hive, NTUSER.DAT in the
+// profile folder, says where their Desktop, Documents, ... were redirected
+// (User Shell Folders), and SYSTEM which partition each drive letter is
+// (MountedDevices).
+//
+// A hive ("regf") is a 4 KiB header followed by hive bins holding cells;
+// offsets in cells are relative to the first bin. Each cell starts with its
+// size as a negative i32 (allocated) and holds a key ("nk"), a value ("vk"),
+// a subkey list ("lf", "lh", "li" or "ri") or raw data. Cells are read on
+// demand: SOFTWARE is often over 100 MB and only a few keys are needed.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use ts_rs::TS;
+
+const BINS_START: u64 = 4096;
+// Cells lindy reads are small; a larger size means a damaged hive.
+const MAX_CELL: usize = 1 << 20;
+
+const KEY_COMP_NAME: u16 = 0x0020;
+const VALUE_COMP_NAME: u16 = 0x0001;
+const DATA_INLINE: u32 = 0x8000_0000;
+
+const REG_SZ: u32 = 1;
+const REG_EXPAND_SZ: u32 = 2;
+
+const PROFILE_LIST: &str = "Microsoft\\Windows NT\\CurrentVersion\\ProfileList";
+const CURRENT_VERSION: &str = "Microsoft\\Windows NT\\CurrentVersion";
+const SAM_ACCOUNT: &str = "SAM\\Domains\\Account";
+const SAM_NAMES: &str = "SAM\\Domains\\Account\\Users\\Names";
+const USER_SHELL_FOLDERS: &str =
+    "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\User Shell Folders";
+const MOUNTED_DEVICES: &str = "MountedDevices";
+const DOS_DEVICE: &str = "\\DosDevices\\";
+// MountedDevices data of a GPT partition: this prefix and its GUID.
+const GPT_PREFIX: &[u8] = b"DMIO:ID:";
+
+// Local accounts and Microsoft Entra ID accounts; the rest of ProfileList
+// is services (SYSTEM, LocalService, NetworkService).
+const USER_SID_PREFIXES: [&str; 2] = ["S-1-5-21-", "S-1-12-1-"];
+
+/// A user profile of the Windows installation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+pub struct WindowsProfile {
+    pub sid: String,
+    /// Account name from the SAM hive; None for accounts it does not hold
+    /// (domain and Microsoft Entra ID accounts) or when it cannot be read.
+    pub account: Option<String>,
+    /// ProfileImagePath as Windows has it, e.g. `C:\Users\ann`.
+    pub profile_path: String,
+    /// The profile folder relative to the partition root (`Users/ann`);
+    /// None when it is on another drive or no longer exists.
+    pub folder: Option<String>,
+}
+
+impl WindowsProfile {
+    /// Account name, or the profile folder's name when it is unknown.
+    pub fn name(&self) -> &str {
+        self.account.as_deref().unwrap_or_else(|| {
+            self.profile_path
+                .rsplit('\\')
+                .next()
+                .unwrap_or(&self.profile_path)
+        })
+    }
+}
+
+/// Profiles of the Windows installation mounted at `root`, most recently
+/// used first. Fails when the SOFTWARE hive cannot be read.
+pub fn profiles(root: &Path) -> io::Result<Vec<WindowsProfile>> {
+    let config = root.join("Windows").join("System32").join("config");
+    let mut software = Hive::open(File::open(config.join("SOFTWARE"))?)?;
+    let accounts = File::open(config.join("SAM"))
+        .and_then(Hive::open)
+        .and_then(|mut sam| local_accounts(&mut sam))
+        .unwrap_or_default();
+    let mut found = read_profiles(&mut software, &accounts)?;
+    for p in &mut found {
+        p.folder = p.folder.take().filter(|f| root.join(f).is_dir());
+    }
+    Ok(found)
+}
+
+/// How MountedDevices identifies the partition behind a drive letter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriveId {
+    /// GPT partition GUID, written as lsblk writes PARTUUID.
+    Gpt(String),
+    /// MBR disk signature and the partition's offset in bytes.
+    Mbr { signature: u32, offset: u64 },
+}
+
+impl DriveId {
+    /// Whether this is the partition with `partuuid` (as lsblk reports it)
+    /// starting at byte `start`.
+    pub fn matches(&self, partuuid: Option<&str>, start: Option<u64>) -> bool {
+        let Some(partuuid) = partuuid else {
+            return false;
+        };
+        match self {
+            DriveId::Gpt(guid) => guid.eq_ignore_ascii_case(partuuid),
+            // MBR PARTUUIDs are <disk signature>-<partition number>
+            DriveId::Mbr { signature, offset } => partuuid
+                .split_once('-')
+                .and_then(|(sig, _)| u32::from_str_radix(sig, 16).ok())
+                .is_some_and(|sig| sig == *signature && start == Some(*offset)),
+        }
+    }
+}
+
+/// Drive letters of the Windows installation mounted at `root` and the
+/// partitions they stand for, from its SYSTEM hive.
+pub fn drive_letters(root: &Path) -> io::Result<Vec<(char, DriveId)>> {
+    let path = root.join("Windows/System32/config/SYSTEM");
+    read_drive_letters(&mut Hive::open(File::open(path)?)?)
+}
+
+/// The drive letter Windows is installed on (from SystemRoot).
+pub fn system_drive(root: &Path) -> io::Result<Option<char>> {
+    let path = root.join("Windows/System32/config/SOFTWARE");
+    let drive = read_system_drive(&mut Hive::open(File::open(path)?)?)?;
+    Ok(drive.and_then(|d| d.chars().next()))
+}
+
+/// User Shell Folders of the user whose hive is `ntuser` (NTUSER.DAT), as
+/// (value name, path as Windows has it, e.g. `%USERPROFILE%\OneDrive\Documents`).
+pub fn shell_folders(ntuser: &Path) -> io::Result<Vec<(String, String)>> {
+    let mut hive = Hive::open(File::open(ntuser)?)?;
+    let Some(key) = hive.key(USER_SHELL_FOLDERS)? else {
+        return Ok(Vec::new());
+    };
+    Ok(hive
+        .values(&key)?
+        .into_iter()
+        .filter_map(|(name, v)| Some((name, v.string()?)))
+        .collect())
+}
+
+/// The folder of the profile called `user` (account or folder name).
+pub fn profile_folder<'a>(profiles: &'a [WindowsProfile], user: &str) -> Option<&'a str> {
+    let by = |name: &dyn Fn(&WindowsProfile) -> Option<&str>| {
+        profiles
+            .iter()
+            .find(|p| name(p).is_some_and(|n| n.eq_ignore_ascii_case(user)))
+            .and_then(|p| p.folder.as_deref())
+    };
+    by(&|p| p.account.as_deref()).or_else(|| by(&|p| p.folder.as_deref()?.rsplit('/').next()))
+}
+
+// Profiles listed in SOFTWARE, with `folder` set from the path whether or
+// not it exists.
+fn read_profiles<R: Read + Seek>(
+    software: &mut Hive<R>,
+    accounts: &[(String, String)],
+) -> io::Result<Vec<WindowsProfile>> {
+    let Some(list) = software.key(PROFILE_LIST)? else {
+        return Ok(Vec::new());
+    };
+    // the drive Windows is on, which is the mounted partition
+    let system_drive = read_system_drive(software)?;
+
+    let mut found = Vec::new();
+    for key in software.subkeys(&list)? {
+        if !USER_SID_PREFIXES.iter().any(|p| key.name.starts_with(p)) {
+            continue;
+        }
+        let Some(path) = software
+            .value(&key, "ProfileImagePath")?
+            .and_then(|v| v.string())
+        else {
+            continue;
+        };
+        let load_time = |name: &str, hive: &mut Hive<R>| -> io::Result<u64> {
+            Ok(hive
+                .value(&key, name)?
+                .and_then(|v| v.data.get(..4).map(|b| le(b, 0, 4)))
+                .transpose()?
+                .unwrap_or(0))
+        };
+        let last_used = (load_time("LocalProfileLoadTimeHigh", software)? << 32)
+            | load_time("LocalProfileLoadTimeLow", software)?;
+        found.push((
+            last_used,
+            WindowsProfile {
+                account: accounts
+                    .iter()
+                    .find(|(sid, _)| *sid == key.name)
+                    .map(|(_, name)| name.clone()),
+                sid: key.name,
+                folder: relative_folder(&path, system_drive.as_deref()),
+                profile_path: path,
+            },
+        ));
+    }
+    found.sort_by_key(|(last_used, _)| std::cmp::Reverse(*last_used));
+    Ok(found.into_iter().map(|(_, p)| p).collect())
+}
+
+// `C:` from SystemRoot (`C:\WINDOWS`).
+fn read_system_drive<R: Read + Seek>(software: &mut Hive<R>) -> io::Result<Option<String>> {
+    let Some(cv) = software.key(CURRENT_VERSION)? else {
+        return Ok(None);
+    };
+    Ok(software
+        .value(&cv, "SystemRoot")?
+        .and_then(|v| v.string())
+        .and_then(|s| s.get(..2).map(str::to_string)))
+}
+
+fn read_drive_letters<R: Read + Seek>(system: &mut Hive<R>) -> io::Result<Vec<(char, DriveId)>> {
+    let Some(key) = system.key(MOUNTED_DEVICES)? else {
+        return Ok(Vec::new());
+    };
+    let mut drives = Vec::new();
+    for (name, v) in system.values(&key)? {
+        // `\DosDevices\D:`; the other values name volumes by GUID
+        let Some(letter) = name
+            .strip_prefix(DOS_DEVICE)
+            .and_then(|d| d.strip_suffix(':'))
+            .and_then(|d| d.chars().next())
+        else {
+            continue;
+        };
+        let d = &v.data;
+        let id = if let Some(guid) = d.strip_prefix(GPT_PREFIX).filter(|g| g.len() == 16) {
+            DriveId::Gpt(format!(
+                "{:08x}-{:04x}-{:04x}-{}-{}",
+                le(guid, 0, 4)?,
+                le(guid, 4, 2)?,
+                le(guid, 6, 2)?,
+                hex(&guid[8..10]),
+                hex(&guid[10..16])
+            ))
+        } else if d.len() == 12 {
+            DriveId::Mbr {
+                signature: le(d, 0, 4)? as u32,
+                offset: le(d, 4, 8)?,
+            }
+        } else {
+            // removable and virtual drives are named by device path
+            continue;
+        };
+        drives.push((letter.to_ascii_uppercase(), id));
+    }
+    Ok(drives)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// `C:\Users\ann` or `%SystemDrive%\Users\ann` -> `Users/ann`, when it is on
+// the system drive.
+fn relative_folder(path: &str, system_drive: Option<&str>) -> Option<String> {
+    let rest = if let Some(rest) = path.strip_prefix("%SystemDrive%") {
+        rest
+    } else {
+        let drive = path.get(..2)?;
+        if !system_drive.is_some_and(|d| d.eq_ignore_ascii_case(drive)) {
+            return None;
+        }
+        &path[2..]
+    };
+    let rest = rest.trim_matches('\\');
+    (!rest.is_empty()).then(|| rest.replace('\\', "/"))
+}
+
+// (SID, name) of the local accounts in SAM. Each account is a subkey of
+// Users\Names whose default value has the account's RID as its type; the
+// machine SID is the last 12 bytes of the V value of the Account key.
+fn local_accounts<R: Read + Seek>(sam: &mut Hive<R>) -> io::Result<Vec<(String, String)>> {
+    let Some(account) = sam.key(SAM_ACCOUNT)? else {
+        return Ok(Vec::new());
+    };
+    let v = sam
+        .value(&account, "V")?
+        .map(|v| v.data)
+        .unwrap_or_default();
+    if v.len() < 12 {
+        return Ok(Vec::new());
+    }
+    let tail = &v[v.len() - 12..];
+    let machine = format!(
+        "S-1-5-21-{}-{}-{}",
+        le(tail, 0, 4)?,
+        le(tail, 4, 4)?,
+        le(tail, 8, 4)?
+    );
+    let Some(names) = sam.key(SAM_NAMES)? else {
+        return Ok(Vec::new());
+    };
+    let mut accounts = Vec::new();
+    for key in sam.subkeys(&names)? {
+        if let Some(default) = sam.value(&key, "")? {
+            accounts.push((format!("{}-{}", machine, default.kind), key.name));
+        }
+    }
+    Ok(accounts)
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.to_string())
+}
+
+// Little-endian unsigned integer of `n` bytes at `off`.
+fn le(buf: &[u8], off: usize, n: usize) -> io::Result<u64> {
+    let bytes = buf
+        .get(off..off + n)
+        .ok_or_else(|| invalid("truncated registry cell"))?;
+    Ok(bytes.iter().rev().fold(0, |acc, b| (acc << 8) | *b as u64))
+}
+
+// Key and value names are Latin-1 when compressed, UTF-16LE otherwise.
+fn name(bytes: &[u8], compressed: bool) -> String {
+    if compressed {
+        bytes.iter().map(|b| *b as char).collect()
+    } else {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    }
+}
+
+struct Key {
+    name: String,
+    subkey_count: u32,
+    subkey_list: u32,
+    value_count: u32,
+    value_list: u32,
+}
+
+struct Value {
+    kind: u32,
+    data: Vec<u8>,
+}
+
+impl Value {
+    // REG_SZ and REG_EXPAND_SZ, without the terminating NUL.
+    fn string(&self) -> Option<String> {
+        if self.kind != REG_SZ && self.kind != REG_EXPAND_SZ {
+            return None;
+        }
+        let s = name(&self.data, false);
+        Some(s.split('\0').next().unwrap_or_default().to_string())
+    }
+}
+
+struct Hive<R> {
+    dev: R,
+    root: u32,
+}
+
+impl<R: Read + Seek> Hive<R> {
+    fn open(mut dev: R) -> io::Result<Hive<R>> {
+        let mut header = [0u8; 0x28];
+        dev.seek(SeekFrom::Start(0))?;
+        dev.read_exact(&mut header)?;
+        if &header[..4] != b"regf" {
+            return Err(invalid("not a registry hive"));
+        }
+        let root = le(&header, 0x24, 4)? as u32;
+        Ok(Hive { dev, root })
+    }
+
+    // Data of the cell at `off`, without its size.
+    fn cell(&mut self, off: u32) -> io::Result<Vec<u8>> {
+        let mut size = [0u8; 4];
+        self.dev.seek(SeekFrom::Start(BINS_START + off as u64))?;
+        self.dev.read_exact(&mut size)?;
+        let size = i32::from_le_bytes(size).unsigned_abs() as usize;
+        if !(4..=MAX_CELL).contains(&size) {
+            return Err(invalid("bad registry cell size"));
+        }
+        let mut buf = vec![0u8; size - 4];
+        self.dev.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn key_at(&mut self, off: u32) -> io::Result<Key> {
+        let c = self.cell(off)?;
+        if c.get(..2) != Some(b"nk") {
+            return Err(invalid("expected a registry key"));
+        }
+        let flags = le(&c, 0x02, 2)? as u16;
+        let len = le(&c, 0x48, 2)? as usize;
+        let raw = c
+            .get(0x4C..0x4C + len)
+            .ok_or_else(|| invalid("truncated key name"))?;
+        Ok(Key {
+            name: name(raw, flags & KEY_COMP_NAME != 0),
+            subkey_count: le(&c, 0x14, 4)? as u32,
+            subkey_list: le(&c, 0x1C, 4)? as u32,
+            value_count: le(&c, 0x24, 4)? as u32,
+            value_list: le(&c, 0x28, 4)? as u32,
+        })
+    }
+
+    // Offsets of the keys in the subkey list at `off`.
+    fn list(&mut self, off: u32, out: &mut Vec<u32>) -> io::Result<()> {
+        let c = self.cell(off)?;
+        let count = le(&c, 0x02, 2)? as usize;
+        let (stride, nested) = match c.get(..2) {
+            Some(b"lf") | Some(b"lh") => (8, false),
+            Some(b"li") => (4, false),
+            Some(b"ri") => (4, true),
+            _ => return Err(invalid("bad subkey list")),
+        };
+        for i in 0..count {
+            let entry = le(&c, 4 + i * stride, 4)? as u32;
+            if nested {
+                self.list(entry, out)?;
+            } else {
+                out.push(entry);
+            }
+        }
+        Ok(())
+    }
+
+    fn subkeys(&mut self, key: &Key) -> io::Result<Vec<Key>> {
+        let mut offsets = Vec::new();
+        if key.subkey_count > 0 {
+            self.list(key.subkey_list, &mut offsets)?;
+        }
+        offsets.into_iter().map(|o| self.key_at(o)).collect()
+    }
+
+    /// The key at `path` (components separated by `\`), below the root.
+    fn key(&mut self, path: &str) -> io::Result<Option<Key>> {
+        let mut key = self.key_at(self.root)?;
+        for part in path.split('\\') {
+            match self
+                .subkeys(&key)?
+                .into_iter()
+                .find(|k| k.name.eq_ignore_ascii_case(part))
+            {
+                Some(k) => key = k,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(key))
+    }
+
+    /// Value `name` of `key`; "" is the default value.
+    fn value(&mut self, key: &Key, value: &str) -> io::Result<Option<Value>> {
+        Ok(self
+            .values(key)?
+            .into_iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(value))
+            .map(|(_, v)| v))
+    }
+
+    /// Every value of `key` with its name.
+    fn values(&mut self, key: &Key) -> io::Result<Vec<(String, Value)>> {
+        let mut values = Vec::new();
+        if key.value_count == 0 {
+            return Ok(values);
+        }
+        let list = self.cell(key.value_list)?;
+        for i in 0..key.value_count as usize {
+            let c = self.cell(le(&list, 4 * i, 4)? as u32)?;
+            if c.get(..2) != Some(b"vk") {
+                return Err(invalid("expected a registry value"));
+            }
+            let len = le(&c, 0x02, 2)? as usize;
+            let flags = le(&c, 0x10, 2)? as u16;
+            let raw = c
+                .get(0x14..0x14 + len)
+                .ok_or_else(|| invalid("truncated value name"))?;
+            let value_name = name(raw, flags & VALUE_COMP_NAME != 0);
+            let size = le(&c, 0x04, 4)? as u32;
+            let kind = le(&c, 0x0C, 4)? as u32;
+            let data = if size & DATA_INLINE != 0 {
+                let len = (size & !DATA_INLINE).min(4) as usize;
+                c[0x08..0x08 + len].to_vec()
+            } else {
+                let mut data = self.cell(le(&c, 0x08, 4)? as u32)?;
+                data.truncate(size as usize);
+                data
+            };
+            values.push((value_name, Value { kind, data }));
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const SID: &str = "S-1-5-21-1-2-3";
+
+    /// Writes cells into a hive the way Windows lays them out.
+    struct Builder {
+        buf: Vec<u8>,
+    }
+
+    impl Builder {
+        fn new() -> Builder {
+            let mut buf = vec![0u8; BINS_START as usize + 32];
+            buf[..4].copy_from_slice(b"regf");
+            buf[BINS_START as usize..][..4].copy_from_slice(b"hbin");
+            Builder { buf }
+        }
+
+        fn cell(&mut self, data: &[u8]) -> u32 {
+            let off = self.buf.len() as u32 - BINS_START as u32;
+            let size = (4 + data.len()).div_ceil(8) * 8;
+            self.buf.extend((-(size as i32)).to_le_bytes());
+            self.buf.extend(data);
+            self.buf
+                .resize(BINS_START as usize + off as usize + size, 0);
+            off
+        }
+
+        fn value(&mut self, value: &str, kind: u32, data: &[u8]) -> u32 {
+            let mut vk = vec![0u8; 0x14];
+            vk[..2].copy_from_slice(b"vk");
+            vk[0x02..0x04].copy_from_slice(&(value.len() as u16).to_le_bytes());
+            if data.len() <= 4 {
+                let size = data.len() as u32 | DATA_INLINE;
+                vk[0x04..0x08].copy_from_slice(&size.to_le_bytes());
+                vk[0x08..0x08 + data.len()].copy_from_slice(data);
+            } else {
+                let at = self.cell(data);
+                vk[0x04..0x08].copy_from_slice(&(data.len() as u32).to_le_bytes());
+                vk[0x08..0x0C].copy_from_slice(&at.to_le_bytes());
+            }
+            vk[0x0C..0x10].copy_from_slice(&kind.to_le_bytes());
+            vk[0x10..0x12].copy_from_slice(&VALUE_COMP_NAME.to_le_bytes());
+            vk.extend(value.as_bytes());
+            self.cell(&vk)
+        }
+
+        fn string(&mut self, value: &str, s: &str) -> u32 {
+            let data: Vec<u8> = s
+                .encode_utf16()
+                .chain([0])
+                .flat_map(u16::to_le_bytes)
+                .collect();
+            self.value(value, REG_EXPAND_SZ, &data)
+        }
+
+        fn key(&mut self, key: &str, subkeys: &[u32], values: &[u32]) -> u32 {
+            let mut nk = vec![0u8; 0x4C];
+            nk[..2].copy_from_slice(b"nk");
+            nk[0x02..0x04].copy_from_slice(&KEY_COMP_NAME.to_le_bytes());
+            if !subkeys.is_empty() {
+                let mut lf = b"lf".to_vec();
+                lf.extend((subkeys.len() as u16).to_le_bytes());
+                for s in subkeys {
+                    lf.extend(s.to_le_bytes());
+                    lf.extend([0u8; 4]);
+                }
+                let at = self.cell(&lf);
+                nk[0x14..0x18].copy_from_slice(&(subkeys.len() as u32).to_le_bytes());
+                nk[0x1C..0x20].copy_from_slice(&at.to_le_bytes());
+            }
+            if !values.is_empty() {
+                let list: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+                let at = self.cell(&list);
+                nk[0x24..0x28].copy_from_slice(&(values.len() as u32).to_le_bytes());
+                nk[0x28..0x2C].copy_from_slice(&at.to_le_bytes());
+            }
+            nk[0x48..0x4A].copy_from_slice(&(key.len() as u16).to_le_bytes());
+            nk.extend(key.as_bytes());
+            self.cell(&nk)
+        }
+
+        // A chain of keys from the root, the last one holding `last`.
+        fn path(&mut self, path: &str, last: &[u32]) -> Vec<u8> {
+            let mut parts = path.rsplit('\\');
+            let mut child = self.key(parts.next().unwrap(), last, &[]);
+            for part in parts {
+                child = self.key(part, &[child], &[]);
+            }
+            self.root(&[child])
+        }
+
+        fn root(&mut self, children: &[u32]) -> Vec<u8> {
+            let root = self.key("ROOT", children, &[]);
+            self.buf[0x24..0x28].copy_from_slice(&root.to_le_bytes());
+            std::mem::take(&mut self.buf)
+        }
+    }
+
+    fn profile(b: &mut Builder, sid: &str, path: &str, load_time: u32) -> u32 {
+        let values = [
+            b.string("ProfileImagePath", path),
+            b.value("LocalProfileLoadTimeHigh", 4, &load_time.to_le_bytes()),
+        ];
+        b.key(sid, &[], &values)
+    }
+
+    fn software() -> Vec<u8> {
+        let mut b = Builder::new();
+        let profiles = [
+            profile(
+                &mut b,
+                "S-1-5-18",
+                "%systemroot%\\system32\\config\\systemprofile",
+                0,
+            ),
+            profile(&mut b, &format!("{}-1001", SID), "C:\\Users\\ann", 1),
+            profile(
+                &mut b,
+                &format!("{}-1002", SID),
+                "%SystemDrive%\\Users\\bob.LAPTOP",
+                7,
+            ),
+            profile(&mut b, "S-1-12-1-9-9-9-9", "D:\\Profiles\\carol", 3),
+        ];
+        let list = b.key("ProfileList", &profiles, &[]);
+        let root = b.string("SystemRoot", "C:\\WINDOWS");
+        let cv = b.key("CurrentVersion", &[list], &[root]);
+        b.path("Microsoft\\Windows NT", &[cv])
+    }
+
+    fn sam() -> Vec<u8> {
+        let mut b = Builder::new();
+        let ann = b.value("", 1001, &[]);
+        let bob = b.value("", 1002, &[]);
+        let names = [b.key("Ann", &[], &[ann]), b.key("Robert", &[], &[bob])];
+        let names = b.key("Names", &names, &[]);
+        let users = b.key("Users", &[names], &[]);
+        let mut v = vec![0u8; 0x20];
+        for n in [1u32, 2, 3] {
+            v.extend(n.to_le_bytes());
+        }
+        let v = b.value("V", 3, &v);
+        let account = b.key("Account", &[users], &[v]);
+        b.path("SAM\\Domains", &[account])
+    }
+
+    #[test]
+    fn reads_profiles_and_account_names() {
+        let mut software = Hive::open(Cursor::new(software())).unwrap();
+        let mut sam = Hive::open(Cursor::new(sam())).unwrap();
+        let accounts = local_accounts(&mut sam).unwrap();
+        assert_eq!(accounts[0], (format!("{}-1001", SID), "Ann".to_string()));
+
+        let found = read_profiles(&mut software, &accounts).unwrap();
+        let names: Vec<&str> = found.iter().map(WindowsProfile::name).collect();
+        // most recently used first, services left out
+        assert_eq!(names, vec!["Robert", "carol", "Ann"]);
+        assert_eq!(found[0].folder.as_deref(), Some("Users/bob.LAPTOP"));
+        assert_eq!(found[1].account, None);
+        assert_eq!(found[1].folder, None, "on another drive");
+        assert_eq!(found[2].profile_path, "C:\\Users\\ann");
+        assert_eq!(found[2].folder.as_deref(), Some("Users/ann"));
+
+        assert_eq!(profile_folder(&found, "robert"), Some("Users/bob.LAPTOP"));
+        assert_eq!(
+            profile_folder(&found, "bob.LAPTOP"),
+            Some("Users/bob.LAPTOP")
+        );
+        assert_eq!(profile_folder(&found, "dave"), None);
+    }
+
+    #[test]
+    fn reads_shell_folders_and_drive_letters() {
+        let mut b = Builder::new();
+        let values = [
+            b.string("Personal", "%USERPROFILE%\\OneDrive\\Documents"),
+            b.string("My Pictures", "D:\\Pictures"),
+        ];
+        let folders = b.key("User Shell Folders", &[], &values);
+        let ntuser = b.path(
+            "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer",
+            &[folders],
+        );
+        let dir = std::env::temp_dir().join(format!("lindy_registry_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("NTUSER.DAT"), ntuser).unwrap();
+        assert_eq!(
+            shell_folders(&dir.join("NTUSER.DAT")).unwrap(),
+            vec![
+                (
+                    "Personal".to_string(),
+                    "%USERPROFILE%\\OneDrive\\Documents".to_string()
+                ),
+                ("My Pictures".to_string(), "D:\\Pictures".to_string()),
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut b = Builder::new();
+        let mut gpt = GPT_PREFIX.to_vec();
+        gpt.extend([
+            0x78, 0x56, 0x34, 0x12, 0xbc, 0x9a, 0xf0, 0xde, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            0x07, 0x08,
+        ]);
+        let mut mbr = 0xa1b2c3d4u32.to_le_bytes().to_vec();
+        mbr.extend((1_048_576u64).to_le_bytes());
+        let values = [
+            b.value("\\DosDevices\\C:", 3, &gpt),
+            b.value("\\DosDevices\\d:", 3, &mbr),
+            b.value("\\??\\Volume{0000}", 3, &gpt),
+        ];
+        let devices = b.key("MountedDevices", &[], &values);
+        let mut system = Hive::open(Cursor::new(b.root(&[devices]))).unwrap();
+        let drives = read_drive_letters(&mut system).unwrap();
+        assert_eq!(drives.len(), 2);
+        let (c, d) = (&drives[0].1, &drives[1].1);
+        assert_eq!(drives[1].0, 'D');
+        assert!(c.matches(Some("12345678-9ABC-DEF0-0102-030405060708"), None));
+        assert!(d.matches(Some("a1b2c3d4-02"), Some(1_048_576)));
+        assert!(!d.matches(Some("a1b2c3d4-03"), Some(2_097_152)));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Hive::open(Cursor::new(vec![0u8; 8192])).is_err());
+        let mut hive = software();
+        hive[BINS_START as usize + 32..][..4].copy_from_slice(&0x7fff_ffffu32.to_le_bytes());
+        let mut hive = Hive::open(Cursor::new(hive)).unwrap();
+        assert!(hive.cell(32).is_err());
+    }
+}
@@ -0,0 +1,422 @@
+// Values returned to the frontend by the Tauri commands.
+//
+// Commands that change something resolve to a `LindyResponse` and every
+// command rejects with a `LindyError`. Both are tagged by `code`, so the
+// frontend switches on a single field instead of parsing JSON strings. The
+// TypeScript definitions in src/bindings are generated from these types by
+// ts-rs when the tests run (`cargo test export_bindings`).
+
+use crate::bundle::ImportOutcome;
+use crate::consistency::RepairOutcome;
+use crate::helper;
+use crate::plan::Plan;
+use crate::validate::Diagnostic;
+use crate::{FolderMapping, WindowsPartition};
+use serde::Serialize;
+use ts_rs::TS;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(tag = "code", rename_all = "snake_case")]
+#[ts(export)]
+pub enum LindyResponse {
+    /// A block was appended to /etc/fstab and activated.
+    Applied {
+        message: String,
+        log: Vec<String>,
+        backup: Option<String>,
+    },
+    /// A block was rewritten in place.
+    Updated {
+        message: String,
+        log: Vec<String>,
+        backup: Option<String>,
+        block: String,
+        targets: Vec<String>,
+    },
+    /// A block was unmounted and removed from /etc/fstab.
+    Removed {
+        message: String,
+        log: Vec<String>,
+        backup: Option<String>,
+        id: Option<String>,
+    },
+    /// /etc/fstab was replaced by a backup.
+    Restored {
+        message: String,
+        log: Vec<String>,
+        backup: Option<String>,
+    },
+    /// Backups outside the retention policy were deleted.
+    Pruned {
+        message: String,
+        log: Vec<String>,
+        removed: Vec<String>,
+    },
+    NothingToPrune {
+        message: String,
+    },
+    Mounted {
+        message: String,
+        log: Vec<String>,
+    },
+    Unmounted {
+        message: String,
+        log: Vec<String>,
+    },
+    /// The partition holds a hibernated or unclean Windows volume and was
+    /// mounted read-only instead.
+    WindowsHibernated {
+        message: String,
+        log: Vec<String>,
+    },
+    /// A BitLocker volume was opened; `device` is its /dev/mapper device,
+    /// which holds the NTFS filesystem.
+    Unlocked {
+        message: String,
+        log: Vec<String>,
+        device: String,
+    },
+    /// Metadata was written for a block that already was in /etc/fstab.
+    Adopted {
+        message: String,
+        id: String,
+    },
+    /// A requested target is already bound by a lindy block that has no
+    /// metadata; the caller can offer `adopt_block` instead of applying.
+    AdoptableExistingBlock {
+        message: String,
+        id: String,
+        block: String,
+        targets: Vec<String>,
+    },
+    AutoMapSuccess {
+        message: String,
+        windows_partition: Box<WindowsPartition>,
+        mappings: Vec<FolderMapping>,
+        mount_point: String,
+        username: String,
+        /// The partition was mounted read-only because Windows is hibernated.
+        read_only: bool,
+    },
+    /// The block already matches what was asked for; nothing was changed.
+    Unchanged {
+        message: String,
+        id: String,
+    },
+    /// Result of a dry run; nothing was changed.
+    Planned {
+        message: String,
+        plan: Plan,
+    },
+    /// Result of `import_mappings`, one outcome per bundled block.
+    Imported {
+        message: String,
+        outcomes: Vec<ImportOutcome>,
+    },
+    /// Result of `repair_consistency`, one outcome per requested action.
+    Repaired {
+        message: String,
+        outcomes: Vec<RepairOutcome>,
+    },
+}
+
+impl LindyResponse {
+    pub fn message(&self) -> &str {
+        match self {
+            LindyResponse::Applied { message, .. }
+            | LindyResponse::Updated { message, .. }
+            | LindyResponse::Removed { message, .. }
+            | LindyResponse::Restored { message, .. }
+            | LindyResponse::Pruned { message, .. }
+            | LindyResponse::NothingToPrune { message }
+            | LindyResponse::Mounted { message, .. }
+            | LindyResponse::Unmounted { message, .. }
+            | LindyResponse::WindowsHibernated { message, .. }
+            | LindyResponse::Unlocked { message, .. }
+            | LindyResponse::Adopted { message, .. }
+            | LindyResponse::AdoptableExistingBlock { message, .. }
+            | LindyResponse::AutoMapSuccess { message, .. }
+            | LindyResponse::Unchanged { message, .. }
+            | LindyResponse::Imported { message, .. }
+            | LindyResponse::Planned { message, .. }
+            | LindyResponse::Repaired { message, .. } => message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(tag = "code", rename_all = "snake_case")]
+#[ts(export)]
+pub enum LindyError {
+    /// Missing or malformed arguments.
+    InvalidRequest {
+        message: String,
+    },
+    /// The block, backup, path or user asked for does not exist.
+    NotFound {
+        message: String,
+    },
+    /// A block with the same id already exists.
+    Conflict {
+        message: String,
+    },
+    /// A requested target already belongs to a block the app manages.
+    AlreadyManaged {
+        message: String,
+        target: String,
+    },
+    /// A requested target is already mounted by a line outside lindy blocks.
+    TargetInFstab {
+        message: String,
+        target: String,
+        line: String,
+    },
+    /// The candidate /etc/fstab failed validation; nothing was changed.
+    ValidationFailed {
+        message: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// A target could not be unmounted because it is in use.
+    Busy {
+        message: String,
+        log: Vec<String>,
+    },
+    /// Activation failed and /etc/fstab was put back.
+    RolledBack {
+        message: String,
+        log: Vec<String>,
+        reason: Option<String>,
+        restored: bool,
+        backup: Option<String>,
+    },
+    MountFailed {
+        message: String,
+        log: Vec<String>,
+    },
+    UmountFailed {
+        message: String,
+        log: Vec<String>,
+    },
+    /// A file the helper was asked to install failed its ownership checks.
+    UntrustedFile {
+        message: String,
+    },
+    IoError {
+        message: String,
+    },
+    SpawnPkexecFailed {
+        message: String,
+    },
+    /// pkexec ran but the helper produced no response (prompt dismissed,
+    /// helper missing, ...).
+    PkexecFailed {
+        message: String,
+        stdout: String,
+        stderr: String,
+    },
+    NoWindowsPartitions {
+        message: String,
+    },
+    /// The only Windows partition is a BitLocker volume that is not
+    /// unlocked; `unlock_bitlocker` opens it.
+    BitlockerLocked {
+        message: String,
+        partition: Box<WindowsPartition>,
+    },
+    /// Several partitions have the same top score (see score.rs); the
+    /// user chooses one and passes its UUID to `auto_mount_and_map`.
+    AmbiguousWindowsPartition {
+        message: String,
+        candidates: Vec<WindowsPartition>,
+    },
+    /// cryptsetup refused the password or recovery key.
+    UnlockFailed {
+        message: String,
+        log: Vec<String>,
+    },
+    NoUsersDetected {
+        message: String,
+        mount_point: String,
+        partition_info: Box<WindowsPartition>,
+    },
+    MappingFailed {
+        message: String,
+        mount_point: String,
+        username: String,
+        partition_info: Box<WindowsPartition>,
+    },
+}
+
+impl LindyError {
+    pub fn invalid(message: impl Into<String>) -> Self {
+        LindyError::InvalidRequest {
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        LindyError::NotFound {
+            message: message.into(),
+        }
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        LindyError::IoError {
+            message: message.into(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            LindyError::InvalidRequest { message }
+            | LindyError::NotFound { message }
+            | LindyError::Conflict { message }
+            | LindyError::AlreadyManaged { message, .. }
+            | LindyError::TargetInFstab { message, .. }
+            | LindyError::ValidationFailed { message, .. }
+            | LindyError::Busy { message, .. }
+            | LindyError::RolledBack { message, .. }
+            | LindyError::MountFailed { message, .. }
+            | LindyError::UmountFailed { message, .. }
+            | LindyError::UntrustedFile { message }
+            | LindyError::IoError { message }
+            | LindyError::SpawnPkexecFailed { message }
+            | LindyError::PkexecFailed { message, .. }
+            | LindyError::NoWindowsPartitions { message }
+            | LindyError::BitlockerLocked { message, .. }
+            | LindyError::AmbiguousWindowsPartition { message, .. }
+            | LindyError::UnlockFailed { message, .. }
+            | LindyError::NoUsersDetected { message, .. }
+            | LindyError::MappingFailed { message, .. } => message,
+        }
+    }
+}
+
+impl std::fmt::Display for LindyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl From<helper::Failure> for LindyError {
+    fn from(f: helper::Failure) -> Self {
+        from_helper(f.into()).unwrap_err()
+    }
+}
+
+/// Map a response from the privileged helper onto the command result.
+pub fn from_helper(resp: helper::Response) -> Result<LindyResponse, LindyError> {
+    let helper::Response {
+        ok,
+        code,
+        message,
+        log,
+        backup,
+        reason,
+        restored,
+        id,
+        block,
+        targets,
+        pruned,
+        device,
+        // read by auto_mount_and_map, which calls the helper itself
+        markers: _,
+    } = resp;
+    if ok {
+        return Ok(match code.as_str() {
+            "applied" => LindyResponse::Applied {
+                message,
+                log,
+                backup,
+            },
+            "updated" => LindyResponse::Updated {
+                message,
+                log,
+                backup,
+                block: block.unwrap_or_default(),
+                targets,
+            },
+            "removed" => LindyResponse::Removed {
+                message,
+                log,
+                backup,
+                id,
+            },
+            "restored" => LindyResponse::Restored {
+                message,
+                log,
+                backup,
+            },
+            "pruned" => LindyResponse::Pruned {
+                message,
+                log,
+                removed: pruned,
+            },
+            "unmounted" => LindyResponse::Unmounted { message, log },
+            "windows_hibernated" => LindyResponse::WindowsHibernated { message, log },
+            "unlocked" => LindyResponse::Unlocked {
+                message,
+                log,
+                device: device.unwrap_or_default(),
+            },
+            "mounted" => LindyResponse::Mounted { message, log },
+            // a code this app does not know must not pass for another one
+            other => {
+                return Err(LindyError::io(format!(
+                    "unexpected helper response code {}: {}",
+                    other, message
+                )))
+            }
+        });
+    }
+    Err(match code.as_str() {
+        "invalid_request" => LindyError::InvalidRequest { message },
+        "not_found" => LindyError::NotFound { message },
+        "conflict" => LindyError::Conflict { message },
+        "busy" => LindyError::Busy { message, log },
+        "rolled_back" => LindyError::RolledBack {
+            message,
+            log,
+            reason,
+            restored: restored.unwrap_or(false),
+            backup,
+        },
+        "mount_failed" => LindyError::MountFailed { message, log },
+        "umount_failed" => LindyError::UmountFailed { message, log },
+        "untrusted_file" => LindyError::UntrustedFile { message },
+        "unlock_failed" => LindyError::UnlockFailed { message, log },
+        _ => LindyError::IoError { message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_helper_responses_by_code() {
+        let resp = helper::Response {
+            reason: Some("mount_failed".into()),
+            restored: Some(true),
+            backup: Some("/etc/fstab.lindy.bak.1".into()),
+            log: vec!["Attempting mount -a".into()],
+            ..helper::Response::error("rolled_back", "activation failed")
+        };
+        let v = serde_json::to_value(from_helper(resp).unwrap_err()).unwrap();
+        assert_eq!(v["code"], "rolled_back");
+        assert_eq!(v["reason"], "mount_failed");
+        assert_eq!(v["restored"], true);
+        assert_eq!(v["log"][0], "Attempting mount -a");
+
+        let resp = helper::Response {
+            id: Some("x".into()),
+            ..helper::Response::ok("removed", "removed block x")
+        };
+        let v = serde_json::to_value(from_helper(resp).unwrap()).unwrap();
+        assert_eq!(v["code"], "removed");
+        assert_eq!(v["id"], "x");
+
+        let err = from_helper(helper::Response::ok("inspected", "inspected 1 volume(s)"));
+        assert!(matches!(err, Err(LindyError::IoError { .. })));
+    }
+}
@@ -0,0 +1,145 @@
+// How much a partition looks like the Windows system partition (C:).
+//
+// A mounted data disk with a Users folder used to win over the unmounted
+// C: drive, because unmounted partitions were never looked into. Each
+// partition now gets points for what only a system volume has
+// (Windows\System32, Users, pagefile.sys, a hibernation file), read from
+// the mounted tree or from the MFT of the raw device, and for its partition
+// type. The reasons are kept so the user can see why one partition was
+// preferred, and partitions with the same top score are offered to the user
+// instead of picking the first.
+
+use crate::lsblk::PartitionKind;
+use crate::ntfs::SystemMarkers;
+
+const SYSTEM32: i32 = 50;
+const USERS: i32 = 20;
+const USER_PROFILES: i32 = 10;
+const PAGEFILE: i32 = 15;
+const HIBERNATED: i32 = 5;
+const BASIC_DATA: i32 = 5;
+const REMOVABLE: i32 = -10;
+const RECOVERY: i32 = -100;
+
+/// What is known about a partition.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Evidence {
+    /// None when its contents could not be read: not mounted, and the
+    /// device is only readable by root.
+    pub markers: Option<SystemMarkers>,
+    /// User profiles found under Users.
+    pub users: usize,
+    pub kind: PartitionKind,
+    /// hiberfil.sys holds a saved session; a dirty flag alone does not count.
+    pub hibernated: Option<bool>,
+    pub removable: bool,
+}
+
+/// Score of a partition and the reasons for it, in the order checked.
+pub fn score(e: &Evidence) -> (i32, Vec<String>) {
+    // (points, reason); notes that change nothing have no points
+    let mut found: Vec<(i32, String)> = Vec::new();
+    match &e.markers {
+        Some(m) => {
+            if m.system32 {
+                found.push((SYSTEM32, "has Windows\\System32".into()));
+            }
+            if m.users {
+                found.push((USERS, "has a Users folder".into()));
+            }
+            if m.pagefile {
+                found.push((PAGEFILE, "has pagefile.sys".into()));
+            }
+            if !(m.system32 || m.users || m.pagefile) {
+                found.push((0, "no Windows system folders".into()));
+            }
+        }
+        None => found.push((
+            0,
+            "contents not checked (not mounted and not readable)".into(),
+        )),
+    }
+    if e.users > 0 {
+        found.push((USER_PROFILES, format!("{} user profile(s)", e.users)));
+    }
+    if e.hibernated == Some(true) {
+        found.push((HIBERNATED, "holds a hibernated Windows session".into()));
+    }
+    match e.kind {
+        PartitionKind::BasicData => {
+            found.push((BASIC_DATA, "Microsoft basic data partition".into()))
+        }
+        PartitionKind::Recovery => found.push((RECOVERY, "Windows recovery partition".into())),
+        _ => {}
+    }
+    if e.removable {
+        found.push((REMOVABLE, "on a removable disk".into()));
+    }
+    let total = found.iter().map(|(points, _)| points).sum();
+    let reasons = found
+        .into_iter()
+        .map(|(points, reason)| match points {
+            0 => reason,
+            _ => format!("{:+} {}", points, reason),
+        })
+        .collect();
+    (total, reasons)
+}
+
+/// Indexes of the items with the highest score; more than one is a tie.
+pub fn top(scores: &[i32]) -> Vec<usize> {
+    let Some(best) = scores.iter().max() else {
+        return Vec::new();
+    };
+    (0..scores.len()).filter(|i| scores[*i] == *best).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_partition_beats_mounted_data_disk() {
+        let system = Evidence {
+            markers: Some(SystemMarkers {
+                system32: true,
+                users: true,
+                pagefile: true,
+            }),
+            kind: PartitionKind::BasicData,
+            ..Default::default()
+        };
+        // a data disk that happens to have a Users folder with a profile
+        let data = Evidence {
+            markers: Some(SystemMarkers {
+                users: true,
+                ..Default::default()
+            }),
+            users: 1,
+            kind: PartitionKind::BasicData,
+            ..Default::default()
+        };
+        let (s, reasons) = score(&system);
+        assert_eq!(s, 90);
+        assert_eq!(reasons[0], "+50 has Windows\\System32");
+        assert_eq!(score(&data).0, 35);
+
+        let unknown = Evidence::default();
+        assert_eq!(
+            score(&unknown),
+            (
+                0,
+                vec!["contents not checked (not mounted and not readable)".to_string()]
+            )
+        );
+        let recovery = Evidence {
+            kind: PartitionKind::Recovery,
+            ..system
+        };
+        assert!(score(&recovery).0 < 0);
+
+        assert_eq!(top(&[35, 90, 5]), vec![1]);
+        assert_eq!(top(&[5, 5, -10]), vec![0, 1]);
+        assert!(top(&[]).is_empty());
+    }
+}
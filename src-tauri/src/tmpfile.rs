@@ -0,0 +1,104 @@
+// Private temporary files.
+//
+// Files under /tmp with predictable names can be pre-created or replaced by a
+// symlink by another local user before we write them. A `PrivateTemp` lives
+// in a fresh directory created with mode 0700 (mkdir fails instead of
+// following an existing entry), and the file itself is created with O_EXCL
+// and mode 0600. Both are removed when the value is dropped.
+
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub struct PrivateTemp {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl PrivateTemp {
+    /// Write `contents` to a new file called `name` in a private directory.
+    pub fn create(name: &str, contents: &[u8]) -> io::Result<PrivateTemp> {
+        let dir = private_dir()?;
+        let path = dir.join(name);
+        let written = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .and_then(|mut f| f.write_all(contents));
+        let tmp = PrivateTemp { dir, path };
+        written.map(|_| tmp)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PrivateTemp {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = fs::remove_dir(&self.dir);
+    }
+}
+
+// $XDG_RUNTIME_DIR is already private to the user; fall back to the system
+// temp dir, where the 0700 directory provides the isolation.
+fn base_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|p| p.is_dir())
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+fn private_dir() -> io::Result<PathBuf> {
+    let base = base_dir();
+    let mut last_err = None;
+    for _ in 0..16 {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let dir = base.join(format!(
+            "lindy-{}-{}-{}",
+            std::process::id(),
+            nanos,
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => last_err = Some(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| io::Error::other("no free temp directory name")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn creates_private_file_and_cleans_up() {
+        let tmp = PrivateTemp::create("fstab", b"/a /b none bind 0 0\n").unwrap();
+        let path = tmp.path().to_path_buf();
+        let dir = path.parent().unwrap().to_path_buf();
+        assert_eq!(
+            fs::metadata(&dir).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "/a /b none bind 0 0\n");
+        drop(tmp);
+        assert!(!path.exists());
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,353 @@
+// Pre-flight validation of a candidate /etc/fstab.
+//
+// Before anything is persisted the full candidate file is written to a
+// private temp file; `run` checks it with `findmnt --verify --tab-file` and
+// with our own rules for the lines that are about to be added. Any
+// error-level diagnostic means the candidate must not be written to
+// /etc/fstab.
+
+use crate::fstab::FstabFile;
+use crate::tmpfile::PrivateTemp;
+use std::ops::Range;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, ts_rs::TS)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in the candidate file.
+#[derive(Debug, Clone, serde::Serialize, ts_rs::TS)]
+pub struct Diagnostic {
+    /// 1-based line number in the candidate file, when the problem maps to a line.
+    #[ts(as = "Option<u32>")]
+    pub line: Option<usize>,
+    /// Text of that line.
+    pub text: Option<String>,
+    pub severity: Severity,
+    /// Which checker reported it: `findmnt` or `lindy`.
+    pub source: &'static str,
+    pub message: String,
+}
+
+/// A candidate fstab: the current file plus the lines being added.
+pub struct Candidate {
+    pub file: FstabFile,
+    /// Indexes (0-based) of the new lines within `file`.
+    pub new_lines: Range<usize>,
+}
+
+impl Candidate {
+    /// The current fstab (empty when it could not be read) with `block` appended.
+    pub fn with_appended(current: Option<&FstabFile>, block: &str) -> Candidate {
+        let mut text = current.map(|f| f.to_string()).unwrap_or_default();
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let start = FstabFile::parse(&text).lines.len();
+        text.push_str(block);
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let file = FstabFile::parse(&text);
+        let end = file.lines.len();
+        Candidate {
+            file,
+            new_lines: start..end,
+        }
+    }
+
+    /// `file` with block `id` already rewritten in it; the lines of that
+    /// block are the new ones. None when the block is not there.
+    pub fn with_replaced(file: FstabFile, id: &str) -> Option<Candidate> {
+        let block = file.block(id)?;
+        Some(Candidate {
+            new_lines: block.start..block.end,
+            file,
+        })
+    }
+
+    fn diag(
+        &self,
+        idx: Option<usize>,
+        severity: Severity,
+        source: &'static str,
+        message: String,
+    ) -> Diagnostic {
+        Diagnostic {
+            line: idx.map(|i| i + 1),
+            text: idx.map(|i| self.file.lines[i].raw.clone()),
+            severity,
+            source,
+            message,
+        }
+    }
+}
+
+pub fn has_errors(diags: &[Diagnostic]) -> bool {
+    diags.iter().any(|d| d.severity == Severity::Error)
+}
+
+/// Validate the candidate. Its text is written to a private temp file for
+/// findmnt, which only reads tab files from disk.
+pub fn check(candidate: &Candidate) -> std::io::Result<Vec<Diagnostic>> {
+    let tmp = PrivateTemp::create("fstab", candidate.file.to_string().as_bytes())?;
+    Ok(run(candidate, &tmp.path().to_string_lossy()))
+}
+
+/// Validate the candidate, whose full text has been written to `tab_file`.
+pub fn run(candidate: &Candidate, tab_file: &str) -> Vec<Diagnostic> {
+    let mut diags = check_new_lines(candidate);
+    match Command::new("findmnt")
+        .args(["--verify", "--verbose", "--tab-file", tab_file])
+        .output()
+    {
+        Ok(out) => {
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            diags.extend(parse_findmnt(candidate, &format!("{}{}", stderr, stdout)));
+        }
+        Err(e) => diags.push(candidate.diag(
+            None,
+            Severity::Warning,
+            "findmnt",
+            format!("findmnt --verify could not be run: {}", e),
+        )),
+    }
+    diags
+}
+
+/// Our own rules, applied to the lines being added.
+pub fn check_new_lines(candidate: &Candidate) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    let lines = &candidate.file.lines;
+    let entries: Vec<(usize, &crate::fstab::FstabEntry)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, l)| l.entry().map(|e| (i, e)))
+        .collect();
+
+    for &(i, e) in entries
+        .iter()
+        .filter(|(i, _)| candidate.new_lines.contains(i))
+    {
+        let err = |m: String| candidate.diag(Some(i), Severity::Error, "lindy", m);
+
+        if e.file != "none" && e.vfstype != "swap" && !e.file.starts_with('/') {
+            diags.push(err(format!(
+                "mountpoint {} is not an absolute path",
+                e.file
+            )));
+        }
+
+        if e.is_bind() {
+            if !e.spec.starts_with('/') {
+                diags.push(err(format!(
+                    "bind source {} is not an absolute path",
+                    e.spec
+                )));
+            } else if !Path::new(&e.spec).exists() {
+                // A source on a partition that is declared but not mounted yet
+                // only appears once that partition is mounted.
+                let provider = entries
+                    .iter()
+                    .filter(|(_, p)| !p.is_bind() && p.file.starts_with('/'))
+                    .filter(|(_, p)| Path::new(&e.spec).starts_with(&p.file))
+                    .max_by_key(|(_, p)| p.file.len());
+                match provider {
+                    Some((_, p)) => diags.push(candidate.diag(
+                        Some(i),
+                        Severity::Warning,
+                        "lindy",
+                        format!(
+                            "bind source {} does not exist yet; expected once {} is mounted",
+                            e.spec, p.file
+                        ),
+                    )),
+                    None => diags.push(err(format!("bind source {} does not exist", e.spec))),
+                }
+            }
+        }
+
+        for &(j, other) in entries.iter().filter(|(j, _)| *j != i) {
+            if e.file != "none"
+                && other.file == e.file
+                && (j < i || !candidate.new_lines.contains(&j))
+            {
+                diags.push(err(format!(
+                    "mountpoint {} is already used on line {}",
+                    e.file,
+                    j + 1
+                )));
+            }
+            if e.is_bind()
+                && other.is_bind()
+                && Path::new(&e.file).starts_with(&other.file)
+                && e.file != other.file
+            {
+                diags.push(err(format!(
+                    "target {} is nested inside bind target {} (line {})",
+                    e.file,
+                    other.file,
+                    j + 1
+                )));
+            }
+            if e.is_bind()
+                && other.is_bind()
+                && !candidate.new_lines.contains(&j)
+                && Path::new(&other.file).starts_with(&e.file)
+                && e.file != other.file
+            {
+                diags.push(err(format!(
+                    "existing bind target {} (line {}) is nested inside target {}",
+                    other.file,
+                    j + 1,
+                    e.file
+                )));
+            }
+        }
+    }
+    diags
+}
+
+/// Turn `findmnt --verify --verbose` output into diagnostics. Findings on
+/// lines that already exist in /etc/fstab are reported as warnings: they are
+/// not caused by this change and must not block it.
+pub fn parse_findmnt(candidate: &Candidate, output: &str) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    let lines = &candidate.file.lines;
+    let mut cursor = 0;
+    let mut current: Option<usize> = None;
+
+    for out_line in output.lines() {
+        if let Some(pos) = out_line.find("parse error at line ") {
+            let n: Option<usize> = out_line[pos + "parse error at line ".len()..]
+                .split(|c: char| !c.is_ascii_digit())
+                .next()
+                .and_then(|d| d.parse().ok());
+            let idx = n.filter(|n| *n >= 1 && *n <= lines.len()).map(|n| n - 1);
+            let severity = match idx {
+                Some(i) if !candidate.new_lines.contains(&i) => Severity::Warning,
+                _ => Severity::Error,
+            };
+            diags.push(candidate.diag(idx, severity, "findmnt", "parse error".into()));
+            continue;
+        }
+        let trimmed = out_line.trim();
+        if trimmed.is_empty()
+            || out_line.starts_with("findmnt:")
+            || trimmed.starts_with("Success")
+            || trimmed.starts_with(|c: char| c.is_ascii_digit())
+        {
+            continue;
+        }
+        if !out_line.starts_with(char::is_whitespace) {
+            // target header: match the next entry with this mountpoint
+            current = (cursor..lines.len())
+                .find(|&i| lines[i].entry().is_some_and(|e| e.file == trimmed));
+            if let Some(i) = current {
+                cursor = i + 1;
+            }
+            continue;
+        }
+        let (severity, message) = if let Some(m) = trimmed.strip_prefix("[E]") {
+            (Severity::Error, m.trim())
+        } else if let Some(m) = trimmed.strip_prefix("[W]") {
+            (Severity::Warning, m.trim())
+        } else {
+            continue;
+        };
+        let severity = match current {
+            Some(i) if !candidate.new_lines.contains(&i) => Severity::Warning,
+            _ => severity,
+        };
+        diags.push(candidate.diag(current, severity, "findmnt", message.to_string()));
+    }
+    diags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(current: &str, block: &str) -> Candidate {
+        Candidate::with_appended(Some(&FstabFile::parse(current)), block)
+    }
+
+    #[test]
+    fn appends_block_after_current_file() {
+        let c = candidate(
+            "/a /b none bind",
+            "# lindy BEGIN: x\n/c /d none bind 0 0\n# lindy END: x",
+        );
+        assert_eq!(c.new_lines, 1..4);
+        assert_eq!(c.file.lines[1].raw, "# lindy BEGIN: x");
+        assert!(c.file.trailing_newline);
+    }
+
+    #[test]
+    fn flags_relative_duplicate_and_nested_targets() {
+        let c = candidate(
+            "/tmp /srv/share none bind 0 0\n",
+            "rel /srv/other none bind 0 0\n/tmp /srv/share none bind 0 0\n/tmp /srv/share/inner none bind 0 0\n",
+        );
+        let diags = check_new_lines(&c);
+        let msgs: Vec<(Option<usize>, &str)> =
+            diags.iter().map(|d| (d.line, d.message.as_str())).collect();
+        assert!(msgs.contains(&(Some(2), "bind source rel is not an absolute path")));
+        assert!(msgs.contains(&(Some(3), "mountpoint /srv/share is already used on line 1")));
+        assert!(msgs
+            .iter()
+            .any(|(l, m)| *l == Some(4) && m.starts_with("target /srv/share/inner is nested")));
+        assert!(has_errors(&diags));
+    }
+
+    #[test]
+    fn checks_the_lines_of_a_replaced_block() {
+        let mut file = FstabFile::parse(
+            "/tmp /srv/a none bind 0 0\n# lindy BEGIN: x\n/tmp /srv/b none bind 0 0\n# lindy END: x\nUUID=1 /mnt ntfs3 defaults 0 0\n",
+        );
+        let lines = crate::fstab::block_lines("x", "/tmp /srv/a none bind 0 0\n").unwrap();
+        file.replace_block("x", lines);
+        let c = Candidate::with_replaced(file, "x").unwrap();
+        assert_eq!(c.new_lines, 1..4);
+        let msgs: Vec<String> = check_new_lines(&c).into_iter().map(|d| d.message).collect();
+        assert_eq!(msgs, vec!["mountpoint /srv/a is already used on line 1"]);
+        assert!(Candidate::with_replaced(FstabFile::default(), "x").is_none());
+    }
+
+    #[test]
+    fn missing_source_on_declared_partition_is_a_warning() {
+        let c = candidate(
+            "",
+            "UUID=1 /mnt/lindy-test-win auto nofail 0 2\n/mnt/lindy-test-win/Users/me /home/me/Docs none bind 0 0\n",
+        );
+        let diags = check_new_lines(&c);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn maps_findmnt_output_to_lines() {
+        let c = candidate(
+            "UUID=old /mnt/old ext4 defaults 0 2\n",
+            "/tmp /srv/new none bind 0 0\n",
+        );
+        let out = "/mnt/old\n   [E] unreachable on boot required source: UUID=old\n\
+/srv/new\n   [E] unreachable on boot required target: No such file or directory\n   [ ] VFS options: bind\n\
+0 parse errors, 2 errors, 0 warnings\n";
+        let diags = parse_findmnt(&c, out);
+        assert_eq!(diags.len(), 2);
+        assert_eq!(
+            (diags[0].line, diags[0].severity),
+            (Some(1), Severity::Warning)
+        );
+        assert_eq!(
+            (diags[1].line, diags[1].severity),
+            (Some(2), Severity::Error)
+        );
+    }
+}
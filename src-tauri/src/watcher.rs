@@ -0,0 +1,248 @@
+// Background watcher for /etc/fstab and the mount table.
+//
+// /etc/fstab is watched through inotify on /etc (editors and the helper
+// replace the file by renaming, which a watch on the file itself would lose
+// track of). The kernel signals mount table changes by raising POLLPRI on an
+// open /proc/self/mountinfo. On every wakeup both are re-read and compared
+// with the last snapshot, so only real changes produce an event.
+
+use crate::fstab::FstabFile;
+use crate::mountinfo::{MountInfo, MountTable, MOUNTINFO_PATH};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+use ts_rs::TS;
+
+pub const FSTAB_CHANGED: &str = "lindy://fstab-changed";
+pub const MOUNT_CHANGED: &str = "lindy://mount-changed";
+
+const FSTAB_PATH: &str = "/etc/fstab";
+
+// Writers touch /etc/fstab several times in a row; wait this long after a
+// wakeup before re-reading so one edit produces one event.
+const SETTLE: Duration = Duration::from_millis(150);
+
+// How often to re-read everything when inotify is unavailable.
+const FALLBACK_POLL_MS: i32 = 5000;
+
+/// Payload of `lindy://fstab-changed`: lindy block ids by kind of change.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, TS)]
+#[ts(export)]
+pub struct FstabChange {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    /// Whether lines outside lindy blocks changed.
+    pub other_lines: bool,
+}
+
+/// Payload of `lindy://mount-changed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, TS)]
+#[ts(export)]
+pub struct MountChange {
+    pub mounted: Vec<MountInfo>,
+    pub unmounted: Vec<MountInfo>,
+}
+
+pub enum Event {
+    Fstab(FstabChange),
+    Mount(MountChange),
+}
+
+/// What changed between two versions of /etc/fstab, or None if nothing did.
+pub fn diff_fstab(old: &FstabFile, new: &FstabFile) -> Option<FstabChange> {
+    let (old_blocks, new_blocks) = (old.blocks(), new.blocks());
+    let mut change = FstabChange::default();
+    for b in &new_blocks {
+        match old_blocks.iter().find(|o| o.id == b.id) {
+            None => change.added.push(b.id.clone()),
+            Some(o) if o.text() != b.text() => change.changed.push(b.id.clone()),
+            Some(_) => {}
+        }
+    }
+    change.removed = old_blocks
+        .iter()
+        .filter(|o| !new_blocks.iter().any(|b| b.id == o.id))
+        .map(|o| o.id.clone())
+        .collect();
+    change.other_lines = outside_blocks(old) != outside_blocks(new);
+    (change != FstabChange::default()).then_some(change)
+}
+
+// The raw lines of `fstab` that are not part of a lindy block.
+fn outside_blocks(fstab: &FstabFile) -> Vec<String> {
+    let mut text = fstab.to_string();
+    for b in fstab.blocks() {
+        text = text.replace(&b.text(), "");
+    }
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Mounts added and removed between two snapshots, matched by mount id.
+pub fn diff_mounts(old: &MountTable, new: &MountTable) -> Option<MountChange> {
+    let missing_from = |a: &MountTable, b: &MountTable| -> Vec<MountInfo> {
+        a.mounts
+            .iter()
+            .filter(|m| !b.mounts.iter().any(|n| n.mount_id == m.mount_id))
+            .cloned()
+            .collect()
+    };
+    let change = MountChange {
+        mounted: missing_from(new, old),
+        unmounted: missing_from(old, new),
+    };
+    (change != MountChange::default()).then_some(change)
+}
+
+fn read_fstab() -> FstabFile {
+    FstabFile::read(FSTAB_PATH).unwrap_or_else(|_| FstabFile::parse(""))
+}
+
+// Reading mountinfo to the end through the polled descriptor is what
+// re-arms POLLPRI, so it must be re-read from that same file.
+fn read_mounts(file: &mut File) -> MountTable {
+    let mut text = String::new();
+    let _ = file.rewind().and_then(|_| file.read_to_string(&mut text));
+    MountTable::parse(&text)
+}
+
+fn watch_etc() -> Option<OwnedFd> {
+    // SAFETY: plain syscalls; the descriptor is owned by the returned OwnedFd.
+    unsafe {
+        let fd = libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK);
+        if fd < 0 {
+            return None;
+        }
+        let fd = OwnedFd::from_raw_fd(fd);
+        let mask = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE | libc::IN_DELETE;
+        if libc::inotify_add_watch(fd.as_raw_fd(), c"/etc".as_ptr(), mask) < 0 {
+            return None;
+        }
+        Some(fd)
+    }
+}
+
+// Discard queued inotify events; only the fact that something happened
+// matters since the file is compared with the last snapshot.
+fn drain(fd: &OwnedFd) {
+    let mut buf = [0u8; 4096];
+    // SAFETY: reads into a buffer we own; the fd is non-blocking.
+    while unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) } > 0 {}
+}
+
+/// Start the watcher thread. `emit` is called from that thread.
+pub fn spawn<F>(emit: F) -> std::io::Result<()>
+where
+    F: Fn(Event) + Send + 'static,
+{
+    let mut mountinfo = File::open(MOUNTINFO_PATH)?;
+    std::thread::Builder::new()
+        .name("lindy-watcher".into())
+        .spawn(move || {
+            let inotify = watch_etc();
+            if inotify.is_none() {
+                eprintln!("lindy: inotify unavailable, polling /etc/fstab");
+            }
+            let mut fstab = read_fstab();
+            let mut mounts = read_mounts(&mut mountinfo);
+            loop {
+                let mut fds = vec![libc::pollfd {
+                    fd: mountinfo.as_raw_fd(),
+                    events: libc::POLLPRI,
+                    revents: 0,
+                }];
+                if let Some(fd) = &inotify {
+                    fds.push(libc::pollfd {
+                        fd: fd.as_raw_fd(),
+                        events: libc::POLLIN,
+                        revents: 0,
+                    });
+                }
+                let timeout = if inotify.is_some() {
+                    -1
+                } else {
+                    FALLBACK_POLL_MS
+                };
+                // SAFETY: `fds` outlives the call and its length is passed.
+                let n = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+                if n < 0 {
+                    let e = std::io::Error::last_os_error();
+                    if e.kind() != std::io::ErrorKind::Interrupted {
+                        eprintln!("lindy: watcher stopped: {}", e);
+                        return;
+                    }
+                }
+                std::thread::sleep(SETTLE);
+                if let Some(fd) = &inotify {
+                    drain(fd);
+                }
+
+                let new_fstab = read_fstab();
+                if let Some(change) = diff_fstab(&fstab, &new_fstab) {
+                    emit(Event::Fstab(change));
+                }
+                fstab = new_fstab;
+
+                let new_mounts = read_mounts(&mut mountinfo);
+                if let Some(change) = diff_mounts(&mounts, &new_mounts) {
+                    emit(Event::Mount(change));
+                }
+                mounts = new_mounts;
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_blocks_and_other_lines() {
+        let old = FstabFile::parse(
+            "UUID=r / ext4 defaults 0 1\n\
+             # lindy BEGIN: a\n/a /b none bind 0 0\n# lindy END: a\n\
+             # lindy BEGIN: b\n/c /d none bind 0 0\n# lindy END: b\n",
+        );
+        assert_eq!(diff_fstab(&old, &old), None);
+
+        let new = FstabFile::parse(
+            "UUID=r / ext4 defaults 0 1\n\
+             # lindy BEGIN: a\n/a /x none bind 0 0\n# lindy END: a\n\
+             # lindy BEGIN: c\n/e /f none bind 0 0\n# lindy END: c\n",
+        );
+        assert_eq!(
+            diff_fstab(&old, &new),
+            Some(FstabChange {
+                added: vec!["c".into()],
+                removed: vec!["b".into()],
+                changed: vec!["a".into()],
+                other_lines: false,
+            })
+        );
+
+        let edited = FstabFile::parse(&format!("{}/dev/sdb1 /data ext4 defaults 0 2\n", old));
+        assert_eq!(diff_fstab(&old, &edited).map(|c| c.other_lines), Some(true));
+    }
+
+    #[test]
+    fn diffs_mounts_by_id() {
+        let old = MountTable::parse(
+            "22 1 259:2 / / rw - ext4 /dev/sda2 rw\n\
+             40 22 259:3 / /mnt/win rw - fuseblk /dev/sda3 rw\n",
+        );
+        let new = MountTable::parse(
+            "22 1 259:2 / / rw - ext4 /dev/sda2 rw\n\
+             41 22 259:3 /Docs /home/me/Docs rw - fuseblk /dev/sda3 rw\n",
+        );
+        assert_eq!(diff_mounts(&old, &old), None);
+        let change = diff_mounts(&old, &new).unwrap();
+        assert_eq!(change.mounted[0].mount_point, "/home/me/Docs");
+        assert_eq!(change.unmounted[0].mount_point, "/mnt/win");
+    }
+}
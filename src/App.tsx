@@ -1,5 +1,6 @@
 import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-dialog";
 import "./App.css";
 
@@ -36,6 +37,20 @@ import DialogContent from '@mui/material/DialogContent';
 import DialogActions from '@mui/material/DialogActions';
 import CircularProgress from '@mui/material/CircularProgress';
 import SwipeableViews from "react-swipeable-views";
+import PlanView from "./components/PlanView";
+import type { ConsistencyEntry } from "./bindings/ConsistencyEntry";
+import type { DriverInfo } from "./bindings/DriverInfo";
+import type { FolderMapping } from "./bindings/FolderMapping";
+import type { FstabBackup } from "./bindings/FstabBackup";
+import type { FstabBlock } from "./bindings/FstabBlock";
+import type { FstabChange } from "./bindings/FstabChange";
+import type { MappingBundle } from "./bindings/MappingBundle";
+import type { MountChange } from "./bindings/MountChange";
+import type { Plan } from "./bindings/Plan";
+import type { Resolution } from "./bindings/Resolution";
+import type { UserFolder } from "./bindings/UserFolder";
+import type { WindowsPartition } from "./bindings/WindowsPartition";
+import { asLindyError, bindStateLabel, driversFor, errorOutput, operationLabel, partitionDetails, type LindyResponse } from "./lindy";
 
 
 type Mapping = {
@@ -44,34 +59,6 @@ type Mapping = {
   target?: string;
 };
 
-type UserFolder = {
-  name: string;
-  linux_path: string;
-  windows_path?: string;
-  exists_linux: boolean;
-  exists_windows: boolean;
-};
-
-type FolderMapping = {
-  linux_path: string;
-  windows_path: string;
-  folder_type: string;
-};
-
-
-
-interface FstabBind {
-  src: string;
-  target: string;
-}
-
-interface FstabBlock {
-  id: string;
-  text: string;
-  targets: string[];
-  binds?: FstabBind[];
-  managed: boolean;
-}
 
 function App() {
   const [rows, setRows] = useState<Mapping[]>([]);
@@ -79,6 +66,9 @@ function App() {
   const [skipPartition, setSkipPartition] = useState(false);
   const [baseMount, setBaseMount] = useState("/mnt/shared");
   const [parts, setParts] = useState<any[]>([]);
+  // fstab type for the partition line; '' lets the backend pick one
+  const [driver, setDriver] = useState("");
+  const [drivers, setDrivers] = useState<DriverInfo[]>([]);
   const [copied, setCopied] = useState(false);
   const [tab, setTab] = useState(0);
   // Track which rows are currently being mounted (single or multiple)
@@ -197,6 +187,7 @@ function App() {
             srcInsidePartition: r.src,
             targetLocal: r.target,
             skipPartitionMount: skipPartition,
+            driver: driver || null,
           });
           lines.push(text);
         } catch (e) {
@@ -206,7 +197,7 @@ function App() {
       if (!cancelled) setPreview(lines);
     })();
     return () => { cancelled = true; };
-  }, [partitionUuid, baseMount, rows, skipPartition]);
+  }, [partitionUuid, baseMount, rows, skipPartition, driver]);
 
   useEffect(() => {
     (async () => {
@@ -232,9 +223,40 @@ function App() {
       } catch (e) {
         console.warn("lsblk not available or failed", e);
       }
+      try {
+        setDrivers(await invoke<DriverInfo[]>("filesystem_drivers"));
+      } catch (e) {
+        console.warn("filesystem_drivers failed", e);
+      }
     })();
     // refresh installed persistent blocks on startup
     refreshInstalledBlocks(true);
+    refreshFstabBackups();
+    refreshConsistency();
+  }, []);
+
+  // changes made outside the app, pushed by the watcher in src-tauri/src/watcher.rs
+  useEffect(() => {
+    const unlisten = [
+      listen<FstabChange>('lindy://fstab-changed', ({ payload: c }) => {
+        const parts = [
+          c.added.length ? `added ${c.added.join(', ')}` : '',
+          c.removed.length ? `removed ${c.removed.join(', ')}` : '',
+          c.changed.length ? `changed ${c.changed.join(', ')}` : '',
+          c.other_lines ? 'other entries edited' : '',
+        ].filter(Boolean);
+        pushLog(`/etc/fstab changed: ${parts.join('; ')}`);
+        refreshInstalledBlocks();
+        refreshConsistency();
+        refreshFstabBackups();
+      }),
+      listen<MountChange>('lindy://mount-changed', ({ payload: c }) => {
+        c.mounted.forEach(m => pushLog(`Mounted ${m.source} on ${m.mount_point}`));
+        c.unmounted.forEach(m => pushLog(`Unmounted ${m.mount_point}`));
+        refreshInstalledBlocks();
+      }),
+    ];
+    return () => { unlisten.forEach(p => p.then(f => f())); };
   }, []);
 
   const removeRow = (id: number) => setRows(r => r.filter(x => x.id !== id));
@@ -274,6 +296,64 @@ function App() {
   const [applyResultOpen, setApplyResultOpen] = useState(false);
   const [applyResultMessage, setApplyResultMessage] = useState('');
   const [operationsLog, setOperationsLog] = useState<string[]>([]);
+  const [fstabBackups, setFstabBackups] = useState<FstabBackup[]>([]);
+  const [consistency, setConsistency] = useState<ConsistencyEntry[]>([]);
+  const [removePlan, setRemovePlan] = useState<Plan | null>(null);
+  const [mountPlan, setMountPlan] = useState<Plan | null>(null);
+  const [pendingBlock, setPendingBlock] = useState<{ id: string, block: string, targets: string[] } | null>(null);
+
+  // fstab block for the rows in the mount confirmation dialog
+  const buildPendingBlock = () => {
+    const id = Math.random().toString(36).slice(2, 10);
+    const lines: string[] = [];
+    // perform_mounts adds the partition line (addPartitionLine below)
+    lines.push(`# lindy BEGIN: ${id}`);
+    const targets: string[] = [];
+    // Use pendingMountRows instead of all rows
+    pendingMountRows.filter(r => r.src && r.target).forEach(r => {
+      lines.push(`${r.src} ${r.target} none bind 0 0`);
+      targets.push(r.target as string);
+    });
+    lines.push(`# lindy END: ${id}`);
+    return { id, block: lines.join('\n') + '\n', targets };
+  };
+
+  const performMountsArgs = (p: { id: string, block: string, targets: string[] }) => ({
+    block: p.block,
+    id: p.id,
+    targets: p.targets,
+    partitionUuid: skipPartition ? null : (partitionUuid || null),
+    baseMount: baseMount || null,
+    addPartitionLine: (!skipPartition && !!partitionUuid && partitionUuid.trim() !== ''),
+    driver: driver || null,
+  });
+
+  // Dry runs for the confirmation dialogs: what "Make permanent" and
+  // "Remove" would do, computed without asking for a password.
+  useEffect(() => {
+    setMountPlan(null);
+    if (!dialogOpen) return;
+    const pending = buildPendingBlock();
+    setPendingBlock(pending);
+    let cancelled = false;
+    invoke<LindyResponse>('perform_mounts', { ...performMountsArgs(pending), dryRun: true })
+      .then(res => { if (!cancelled && res.code === 'planned') setMountPlan(res.plan); })
+      .catch(e => console.warn('perform_mounts dry run failed', e));
+    return () => { cancelled = true; };
+  }, [dialogOpen]);
+
+  useEffect(() => {
+    setRemovePlan(null);
+    if (!removeDialogOpen || !removeDialogId) return;
+    let cancelled = false;
+    const request = removeDialogTarget
+      ? invoke<LindyResponse>('remove_block_for_target', { target: removeDialogTarget, force: removeDialogForce, dryRun: true })
+      : invoke<LindyResponse>('remove_fstab_block', { id: removeDialogId, force: removeDialogForce, dryRun: true });
+    request
+      .then(res => { if (!cancelled && res.code === 'planned') setRemovePlan(res.plan); })
+      .catch(e => console.warn('remove dry run failed', e));
+    return () => { cancelled = true; };
+  }, [removeDialogOpen, removeDialogId, removeDialogTarget, removeDialogForce]);
 
   // Auto-mapping state
   const [autoMappingOpen, setAutoMappingOpen] = useState(false);
@@ -282,6 +362,14 @@ function App() {
   const [windowsUsername, setWindowsUsername] = useState("");
   const [autoMappingLoading, setAutoMappingLoading] = useState(false);
   const [smartAutoMapLoading, setSmartAutoMapLoading] = useState(false);
+  // BitLocker volume Smart Auto-Map asked to unlock
+  const [unlockPartition, setUnlockPartition] = useState<WindowsPartition | null>(null);
+  const [unlockKey, setUnlockKey] = useState("");
+  const [unlockPersist, setUnlockPersist] = useState<'' | 'prompt' | 'key_file'>('');
+  const [unlockError, setUnlockError] = useState<string | null>(null);
+  const [unlocking, setUnlocking] = useState(false);
+  // Partitions that looked equally likely to hold Windows
+  const [partitionChoices, setPartitionChoices] = useState<WindowsPartition[]>([]);
   const [errorDialogOpen, setErrorDialogOpen] = useState(false);
   const [errorDetails, setErrorDetails] = useState<{ title: string, message: string, solution: string, technical?: string } | null>(null);
   const [autoMapSuccessOpen, setAutoMapSuccessOpen] = useState(false);
@@ -317,11 +405,10 @@ function App() {
       if (mappings.length === 0) {
         alert('No matching folders found. Make sure the Windows partition is mounted and contains user folders.');
       }
-    } catch (e: any) {
+    } catch (e) {
       console.error('Auto-mapping error:', e);
       // Show user-friendly error message
-      const errorMsg = typeof e === 'string' ? e : 'Failed to scan for folder mappings';
-      alert(`Error: ${errorMsg}`);
+      alert(`Error: ${asLindyError(e).message}`);
     } finally {
       setAutoMappingLoading(false);
     }
@@ -421,32 +508,44 @@ Smart Auto-Map can't handle this automatically. Try "Manual Auto-Map" instead, o
     };
   };
 
-  const smartAutoMap = async () => {
+  // `uuid` is the partition the user chose when detection could not decide.
+  const smartAutoMap = async (uuid?: string) => {
     setSmartAutoMapLoading(true);
     pushLog('Starting Smart Auto-Map: Detecting Windows partitions...');
 
     try {
-      const resultStr = await invoke<string>('auto_mount_and_map', {
-        preferredMountBase: null,
-        username: null,
-      });
-
-      // Parse the JSON response
-      let result: any;
+      // Mounting the partition needs a password; say what will happen first.
       try {
-        result = JSON.parse(resultStr);
+        const dry = await invoke<LindyResponse>('auto_mount_and_map', {
+          preferredMountBase: null,
+          username: null,
+          uuid: uuid ?? null,
+          dryRun: true,
+        });
+        if (dry.code === 'planned' && (dry.plan.operations.length > 0 || dry.plan.conflicts.length > 0)
+          && !confirm(`Smart Auto-Map will run:\n\n${[...dry.plan.operations.map(operationLabel), ...dry.plan.conflicts].join('\n')}\n\nContinue?`)) {
+          pushLog('Smart Auto-Map cancelled');
+          return;
+        }
       } catch (e) {
-        pushLog('Smart Auto-Map failed: Invalid response format');
-        throw new Error('Invalid response from auto-mapping service');
+        // the real call below reports the same problem with the full handling
+        console.warn('auto_mount_and_map dry run failed', e);
       }
-
-      if (result.status === 'error') {
+      let result: LindyResponse;
+      try {
+        result = await invoke<LindyResponse>('auto_mount_and_map', {
+          preferredMountBase: null,
+          username: null,
+          uuid: uuid ?? null,
+        });
+      } catch (e) {
+        const err = asLindyError(e);
         // Log the specific error
-        pushLog(`Smart Auto-Map failed: ${result.code} - ${result.message}`);
+        pushLog(`Smart Auto-Map failed: ${err.code} - ${err.message}`);
 
         // Handle different error types with specific messages
 
-        if (result.code === 'spawn_pkexec_failed') {
+        if (err.code === 'spawn_pkexec_failed') {
           setErrorDetails({
             title: '🔐 Permission Helper Not Available',
             message: 'The system permission helper isn\'t working right now.',
@@ -454,12 +553,12 @@ Smart Auto-Map can't handle this automatically. Try "Manual Auto-Map" instead, o
 • Restart the app
 • Try "Manual Auto-Map" instead
 • Restart your computer if the problem continues`,
-            technical: result.message
+            technical: err.message
           });
           setErrorDialogOpen(true);
           return;
-        } else if (result.code === 'mount_failed') {
-          const parsedError = parseWindowsPartitionError(result.stderr || '', result.stdout || '');
+        } else if (err.code === 'mount_failed') {
+          const parsedError = parseWindowsPartitionError(err.message, err.log.join('\n'));
 
           // Show user-friendly error dialog instead of alert
           setErrorDetails(parsedError);
@@ -468,7 +567,15 @@ Smart Auto-Map can't handle this automatically. Try "Manual Auto-Map" instead, o
           // Log technical details for troubleshooting
           pushLog(`Mount failed - Technical details: ${parsedError.technical}`);
           return; // Don't show the generic alert
-        } else if (result.code === 'no_windows_partitions') {
+        } else if (err.code === 'bitlocker_locked') {
+          setUnlockKey('');
+          setUnlockError(null);
+          setUnlockPartition(err.partition);
+          return;
+        } else if (err.code === 'ambiguous_windows_partition') {
+          setPartitionChoices(err.candidates);
+          return;
+        } else if (err.code === 'no_windows_partitions') {
           setErrorDetails({
             title: '💿 No Windows Found',
             message: 'Smart Auto-Map couldn\'t find Windows on your computer.',
@@ -478,11 +585,11 @@ Smart Auto-Map can't handle this automatically. Try "Manual Auto-Map" instead, o
 • Windows is already mounted somewhere else
 
 Try "Manual Auto-Map" if you know where Windows is located.`,
-            technical: 'No NTFS or exFAT partitions detected via lsblk'
+            technical: 'No NTFS, exFAT or BitLocker partitions detected via lsblk'
           });
           setErrorDialogOpen(true);
           return;
-        } else if (result.code === 'no_users_detected') {
+        } else if (err.code === 'no_users_detected') {
           setErrorDetails({
             title: '👤 No Windows Users Found',
             message: 'Found Windows, but couldn\'t find any user folders.',
@@ -492,21 +599,21 @@ Try "Manual Auto-Map" if you know where Windows is located.`,
 • The Windows installation is incomplete
 
 Try "Manual Auto-Map" and specify your Windows username manually.`,
-            technical: `Mounted at: ${result.mount_point}, but no Users folder found`
+            technical: `Mounted at: ${err.mount_point}, but no Users folder found`
           });
           setErrorDialogOpen(true);
           return;
-        } else if (result.code === 'no_mappings_found') {
+        } else if (err.code === 'mapping_failed') {
           setErrorDetails({
             title: '📁 No Folders to Map',
-            message: `Found Windows user '${result.username}', but no folders to sync.`,
+            message: `Found Windows user '${err.username}', but no folders to sync.`,
             solution: `This might be because:
 • The Windows user doesn't have Desktop, Documents, etc. folders yet
 • The folders have different names
 • You need to create the folders in Windows first
 
 Try logging into Windows and creating the standard folders, then try again.`,
-            technical: `User: ${result.username}, Mount: ${result.mount_point}`
+            technical: `User: ${err.username}, Mount: ${err.mount_point}\n${err.message}`
           });
           setErrorDialogOpen(true);
           return;
@@ -522,13 +629,13 @@ Try logging into Windows and creating the standard folders, then try again.`,
 • Restart your computer
 
 If this keeps happening, it might be a bug.`,
-          technical: JSON.stringify(result, null, 2)
+          technical: JSON.stringify(err, null, 2)
         });
         setErrorDialogOpen(true);
         return;
       }
 
-      if (result.status === 'ok') {
+      if (result.code === 'auto_map_success') {
         // Log success
         pushLog(`Smart Auto-Map success: Mounted ${result.windows_partition.label || 'Windows partition'} at ${result.mount_point}, found ${result.mappings.length} mappings for user ${result.username}`);
 
@@ -536,12 +643,12 @@ If this keeps happening, it might be a bug.`,
         setBaseMount(result.mount_point);
 
         // Set the partition UUID if available
-        if (result.windows_partition?.uuid) {
+        if (result.windows_partition.uuid) {
           setPartitionUuid(result.windows_partition.uuid);
         }
 
         // Add the mappings
-        const newMappings = result.mappings.map((mapping: any) => ({
+        const newMappings = result.mappings.map((mapping) => ({
           id: Date.now() + Math.random(),
           src: mapping.windows_path,
           target: mapping.linux_path,
@@ -557,8 +664,12 @@ If this keeps happening, it might be a bug.`,
           mountPoint: result.mount_point,
           username: result.username,
           count: result.mappings.length,
+          readOnly: result.read_only,
           newMappings: newMappings // Pass these so we can "Mount All Now" immediately
         });
+        if (result.read_only) {
+          pushLog(`Smart Auto-Map: ${result.mount_point} was mounted read-only because Windows is hibernated`);
+        }
         setAutoMapSuccessOpen(true);
       }
 
@@ -571,6 +682,108 @@ If this keeps happening, it might be a bug.`,
       setSmartAutoMapLoading(false);
     }
   };
+  const refreshFstabBackups = async () => {
+    try {
+      setFstabBackups(await invoke<FstabBackup[]>('list_fstab_backups'));
+    } catch (e) {
+      console.warn('list_fstab_backups failed', e);
+    }
+  };
+
+  const showBackupDiff = async (b: FstabBackup) => {
+    try {
+      const diff = await invoke<string>('diff_fstab_backup', { ts: b.ts });
+      setOpResultMessage(diff ? `Restoring ${b.path} would make these changes to /etc/fstab:` : `${b.path} is identical to the current /etc/fstab.`);
+      setOpResultHint(diff || null);
+    } catch (e) {
+      setOpResultMessage(`Failed to diff ${b.path}: ${asLindyError(e).message}`);
+      setOpResultHint(null);
+    }
+    setOpResultOpen(true);
+  };
+
+  const restoreBackup = async (b: FstabBackup) => {
+    if (!confirm(`Replace /etc/fstab with ${b.path}? The current file is backed up first.`)) return;
+    try {
+      const res = await invoke<LindyResponse>('restore_fstab_backup', { ts: b.ts });
+      setOpResultMessage(res.message);
+      setOpResultHint(null);
+      pushLog(`Restored /etc/fstab from ${b.path}`);
+    } catch (e) {
+      const err = asLindyError(e);
+      setOpResultMessage(`Failed to restore ${b.path}: ${err.message}`);
+      setOpResultHint(errorOutput(err));
+    }
+    setOpResultOpen(true);
+    refreshInstalledBlocks();
+    refreshFstabBackups();
+  };
+
+  const refreshConsistency = async () => {
+    try {
+      const entries = await invoke<ConsistencyEntry[]>('check_consistency');
+      setConsistency(entries.filter(e => e.state !== 'in_sync'));
+    } catch (e) {
+      console.warn('check_consistency failed', e);
+    }
+  };
+
+  const repair = async (entry: ConsistencyEntry, resolution: Resolution) => {
+    try {
+      const res = await invoke<LindyResponse>('repair_consistency', { actions: [{ id: entry.id, resolution }] });
+      const failed = res.code === 'repaired' ? res.outcomes.find(o => o.error) : undefined;
+      setOpResultMessage(failed ? `Failed to repair ${entry.id}: ${failed.message}` : res.message);
+      setOpResultHint(failed?.error ? errorOutput(failed.error) : null);
+      pushLog(`Repair ${entry.id} (${resolution}): ${failed ? failed.message : 'ok'}`);
+    } catch (e) {
+      const err = asLindyError(e);
+      setOpResultMessage(`Failed to repair ${entry.id}: ${err.message}`);
+      setOpResultHint(errorOutput(err));
+    }
+    setOpResultOpen(true);
+    refreshInstalledBlocks();
+    refreshConsistency();
+  };
+
+  const exportMappings = async () => {
+    try {
+      const bundle = await invoke<MappingBundle>('export_mappings');
+      const blob = new Blob([JSON.stringify(bundle, null, 2)], { type: 'application/json' });
+      const url = URL.createObjectURL(blob);
+      const a = document.createElement('a');
+      a.href = url;
+      a.download = 'lindy-mappings.json';
+      document.body.appendChild(a);
+      a.click();
+      a.remove();
+      URL.revokeObjectURL(url);
+      pushLog(`Exported ${bundle.blocks.length} mapping(s)`);
+    } catch (e) {
+      const err = asLindyError(e);
+      setOpResultMessage(`Export failed: ${err.message}`);
+      setOpResultHint(errorOutput(err));
+      setOpResultOpen(true);
+    }
+  };
+
+  const importMappings = async () => {
+    const path = await open({ multiple: false, filters: [{ name: 'lindy bundle', extensions: ['json'] }] });
+    if (typeof path !== 'string') return;
+    try {
+      const res = await invoke<LindyResponse>('import_mappings', { path });
+      setOpResultMessage(res.message);
+      setOpResultHint(res.code === 'imported' ? res.outcomes.map(o => `${o.id}: ${o.message}`).join('\n') : null);
+      pushLog(`Import ${path}: ${res.message}`);
+    } catch (e) {
+      const err = asLindyError(e);
+      setOpResultMessage(`Import failed: ${err.message}`);
+      setOpResultHint(errorOutput(err));
+    }
+    setOpResultOpen(true);
+    refreshInstalledBlocks();
+    refreshConsistency();
+  };
+
   const refreshInstalledBlocks = async (isInitial = false) => {
     try {
       const res = await invoke<FstabBlock[]>('list_fstab_blocks');
@@ -643,6 +856,7 @@ If this keeps happening, it might be a bug.`,
                           onChange={(e) => {
                             const v = (e.target.value as string).trim().toLowerCase();
                             setPartitionUuid(v);
+                            setDriver('');
                             // Suggest a base mount point when user picks a partition and baseMount is the default
                             try {
                               const p = parts.find((pp: any) => pp.uuid === v);
@@ -669,8 +883,11 @@ If this keeps happening, it might be a bug.`,
                           {parts
                             .filter(p => p.uuid)
                             .map((p) => (
-                              <MenuItem key={p.uuid} value={p.uuid}>
+                              <MenuItem key={p.uuid} value={p.uuid} sx={{ display: 'block' }}>
                                 {p.uuid} {p.label ? `(${p.label})` : ""} {p.fstype ? `· ${p.fstype}` : ""}
+                                <Typography variant="caption" color="text.secondary" component="div">
+                                  {partitionDetails(p)}
+                                </Typography>
                               </MenuItem>
                             ))}
                         </Select>
@@ -685,6 +902,34 @@ If this keeps happening, it might be a bug.`,
                         error={partitionUuid.trim() !== '' && !isValidPartitionId(partitionUuid.trim())}
                         helperText={partitionUuid.trim() !== '' && !isValidPartitionId(partitionUuid.trim()) ? 'Unrecognized partition id format (accepted: UUID, 4-4 FAT id, 16/32 hex)' : ''}
                       />
+                      {(() => {
+                        const choices = driversFor(parts.find(p => p.uuid === partitionUuid)?.fstype, drivers);
+                        const chosen = drivers.find(d => d.driver === driver);
+                        return choices.length > 0 && (
+                          <FormControl fullWidth sx={{ mt: 1 }}>
+                            <InputLabel id="driver-label" shrink>Filesystem driver</InputLabel>
+                            <Select
+                              labelId="driver-label"
+                              label="Filesystem driver"
+                              value={driver}
+                              displayEmpty
+                              onChange={(e) => setDriver(e.target.value as string)}
+                            >
+                              <MenuItem value="">Automatic</MenuItem>
+                              {choices.map(d => (
+                                <MenuItem key={d.driver} value={d.driver} disabled={!d.available}>
+                                  {d.driver} ({d.fuse ? 'FUSE' : 'kernel'}{d.available ? '' : ', not installed'})
+                                </MenuItem>
+                              ))}
+                            </Select>
+                            {chosen && chosen.options && (
+                              <Typography variant="caption" color="text.secondary" sx={{ mt: 0.5, fontFamily: 'monospace' }}>
+                                {chosen.options}
+                              </Typography>
+                            )}
+                          </FormControl>
+                        );
+                      })()}
                     </>
                   )}
                 </Grid>
@@ -776,7 +1021,7 @@ If this keeps happening, it might be a bug.`,
                     <Button
                       variant="contained"
                       color="success"
-                      onClick={smartAutoMap}
+                      onClick={() => smartAutoMap()}
                       disabled={smartAutoMapLoading}
                     >
                       {smartAutoMapLoading ? (
@@ -871,22 +1116,20 @@ If this keeps happening, it might be a bug.`,
                           if (row.target) {
                             try {
                               setOpResultHint(null);
-                              const res = await invoke<string>('remove_block_for_target', { target: row.target, force: true });
-                              let parsed: any = null;
-                              try { parsed = JSON.parse(res); } catch (_) { parsed = null; }
-                              if (parsed && parsed.status === 'ok') {
-                                setOpResultMessage(`Removed mapping for ${row.target}.`);
-                                refreshInstalledBlocks();
-                              } else if (parsed && parsed.status === 'error') {
-                                setOpResultMessage(`Failed to remove mapping: ${parsed.message || parsed.code}`);
-                                setOpResultHint((parsed.stderr || parsed.stdout) || null);
-                              } else {
-                                setOpResultMessage(`Remove result:\n${res}`);
-                              }
+                              await invoke<LindyResponse>('remove_block_for_target', { target: row.target, force: true });
+                              setOpResultMessage(`Removed mapping for ${row.target}.`);
+                              refreshInstalledBlocks();
                               setOpResultOpen(true);
                               return;
-                            } catch (e: any) {
-                              console.warn('remove_block_for_target failed', e);
+                            } catch (e) {
+                              const err = asLindyError(e);
+                              if (err.code !== 'not_found') {
+                                setOpResultMessage(`Failed to remove mapping: ${err.message}`);
+                                setOpResultHint(errorOutput(err));
+                                setOpResultOpen(true);
+                                return;
+                              }
+                              console.warn('remove_block_for_target failed', err);
                               // fallback to previous behavior: check installedBlocks and show removal dialog
                               const installed = installedBlocks.find(b => b.targets && b.targets.includes(row.target!));
                               if (installed) {
@@ -965,6 +1208,8 @@ If this keeps happening, it might be a bug.`,
                 <pre className="fstab-preview" style={{ maxHeight: 360, overflow: 'auto' }}>
                   <code>{dialogScript}</code>
                 </pre>
+                <Typography variant="subtitle1" sx={{ mt: 2 }}>Make permanent</Typography>
+                {mountPlan ? <PlanView plan={mountPlan} /> : <CircularProgress size={18} />}
               </DialogContent>
               <DialogActions>
                 <Button onClick={() => setDialogOpen(false)}>Close</Button>
@@ -976,64 +1221,42 @@ If this keeps happening, it might be a bug.`,
                   color="primary"
                   onClick={async () => {
                     setOpResultHint(null);
-                    // Build fstab block for all mappings
-                    const id = Math.random().toString(36).slice(2, 10);
-                    const lines: string[] = [];
-                    lines.push(`# lindy BEGIN: ${id}`);
-                    if (!skipPartition && partitionUuid && partitionUuid.trim() !== '') {
-                      lines.push(`UUID=${partitionUuid} ${baseMount} auto defaults,noatime,nofail,x-systemd.automount,x-systemd.device-timeout=10 0 2`);
-                    }
-                    const targets: string[] = [];
-                    // Use pendingMountRows instead of all rows
-                    pendingMountRows.filter(r => r.src && r.target).forEach(r => {
-                      lines.push(`${r.src} ${r.target} none bind 0 0`);
-                      targets.push(r.target as string);
-                    });
-                    lines.push(`# lindy END: ${id}`);
-                    const block = lines.join('\n') + '\n';
+                    // the block the plan above was computed for
+                    const pending = pendingBlock ?? buildPendingBlock();
+                    const { id, block } = pending;
                     try {
                       setApplyInProgress(true);
-                      const res = await invoke<string>('perform_mounts', {
-                        block,
-                        id,
-                        targets,
-                        partitionUuid: skipPartition ? null : (partitionUuid || null),
-                        baseMount: baseMount || null,
-                        addPartitionLine: (!skipPartition && !!partitionUuid && partitionUuid.trim() !== ''),
-                      });
-                      let parsed: any = null;
-                      try { parsed = JSON.parse(res); } catch (err) { parsed = null; }
-                      if (parsed && parsed.status === 'adoptable' && parsed.code === 'adoptable_existing_block') {
+                      const res = await invoke<LindyResponse>('perform_mounts', performMountsArgs(pending));
+                      if (res.code === 'adoptable_existing_block') {
                         // Show adopt confirmation dialog
-                        setAdoptInfo({ id: parsed.id, block: parsed.block, targets: parsed.targets || [] });
+                        setAdoptInfo({ id: res.id, block: res.block, targets: res.targets });
                         setAdoptDialogOpen(true);
-                      } else if (parsed && parsed.status === 'ok') {
+                      } else {
                         setApplyResultMessage(`Mapping created and activated (id: ${id}).`);
-                        pushLog(`Applied fstab block ${id}: ${parsed.message || parsed.code}`);
+                        pushLog(`Applied fstab block ${id}: ${res.message}`);
                         refreshInstalledBlocks();
-                      } else if (parsed && parsed.status === 'error') {
-                        // Friendly handling of known codes
-                        if (parsed.code === 'spawn_pkexec_failed') {
-                          setApplyResultMessage('Elevation helper (pkexec) not available or failed to start. Run the following sudo command in a terminal:');
-                          setOpResultHint(`sudo sh -c "cat > /tmp/new_block <<'EOF'\n${block.replace(/\$/g, '\\$')}\nEOF\ncat /tmp/new_block >> /etc/fstab && mount -a"`);
-                        } else if (parsed.code === 'pkexec_failed') {
-                          setApplyResultMessage('Privileged operation failed while applying block. See details below. You can try the sudo fallback:');
-                          setOpResultHint(`sudo sh -c "cat > /tmp/new_block <<'EOF'\n${block.replace(/\$/g, '\\$')}\nEOF\ncat /tmp/new_block >> /etc/fstab && mount -a"`);
-                        } else {
-                          setApplyResultMessage(`Failed to apply mapping: ${parsed.message || parsed.code || 'unknown error'}`);
-                          setOpResultHint((parsed && (parsed.stderr || parsed.stdout)) || null);
-                        }
-                      } else {
-                        // Fallback: unknown response format
-                        setApplyResultMessage(`Apply fstab result:\n${res}`);
                       }
                       setApplyResultOpen(true);
                       setDialogOpen(false);
-                    } catch (e: any) {
-                      const text = String(e);
-                      setApplyResultMessage(`Failed to apply fstab block:\n${text}`);
-                      pushLog(`Failed apply fstab block ${id}: ${String(e)}`);
+                    } catch (e) {
+                      const err = asLindyError(e);
+                      // Friendly handling of known codes
+                      if (err.code === 'spawn_pkexec_failed') {
+                        setApplyResultMessage('Elevation helper (pkexec) not available or failed to start. Run the following sudo command in a terminal:');
+                        setOpResultHint(`sudo sh -c "cat > /tmp/new_block <<'EOF'\n${block.replace(/\$/g, '\\$')}\nEOF\ncat /tmp/new_block >> /etc/fstab && mount -a"`);
+                      } else if (err.code === 'validation_failed') {
+                        setApplyResultMessage('Mapping was not applied: validation of the new fstab found problems.');
+                        setOpResultHint(errorOutput(err));
+                      } else if (err.code === 'pkexec_failed') {
+                        setApplyResultMessage('Privileged operation failed while applying block. See details below. You can try the sudo fallback:');
+                        setOpResultHint(`sudo sh -c "cat > /tmp/new_block <<'EOF'\n${block.replace(/\$/g, '\\$')}\nEOF\ncat /tmp/new_block >> /etc/fstab && mount -a"`);
+                      } else {
+                        setApplyResultMessage(`Failed to apply mapping: ${err.message}`);
+                        setOpResultHint(errorOutput(err));
+                      }
+                      pushLog(`Failed apply fstab block ${id}: ${err.code}: ${err.message}`);
                       setApplyResultOpen(true);
+                      setDialogOpen(false);
                     } finally {
                       setApplyInProgress(false);
                     }
@@ -1055,6 +1278,8 @@ If this keeps happening, it might be a bug.`,
             <Grid container alignItems="center" justifyContent="space-between" sx={{ mb: 2 }}>
               <Grid item><Typography variant="h6">Persistent Mounts (installed)</Typography></Grid>
               <Grid item>
+                <Button size="small" onClick={() => exportMappings()}>Export</Button>
+                <Button size="small" onClick={() => importMappings()}>Import</Button>
                 <Button size="small" onClick={() => refreshInstalledBlocks()}>Refresh</Button>
               </Grid>
             </Grid>
@@ -1067,6 +1292,14 @@ If this keeps happening, it might be a bug.`,
                   <Grid item xs={8}>
                     <Typography variant="body2"><code>{b.id}</code> {b.managed ? <span style={{ fontSize: 12, marginLeft: 8, color: '#1976d2' }}>(managed)</span> : null}</Typography>
                     <Typography variant="caption" sx={{ whiteSpace: 'pre-wrap' }}>{b.text}</Typography>
+                    {b.binds.map(bind => {
+                      const st = bindStateLabel(bind.mount);
+                      return (
+                        <Typography key={bind.target} variant="caption" component="div">
+                          <code>{bind.target}</code> <span style={{ color: st.color, marginLeft: 8 }}>{st.label}</span>
+                        </Typography>
+                      );
+                    })}
                   </Grid>
                   <Grid item xs={4}>
                     <Button size="small" color="error" onClick={() => {
@@ -1080,6 +1313,58 @@ If this keeps happening, it might be a bug.`,
               </Paper>
             ))}
 
+            <Paper variant="outlined" sx={{ p: 2, mt: 2 }}>
+              <Grid container alignItems="center" justifyContent="space-between">
+                <Grid item><Typography variant="subtitle1">fstab backups</Typography></Grid>
+                <Grid item>
+                  <Button size="small" onClick={() => refreshFstabBackups()}>Refresh</Button>
+                </Grid>
+              </Grid>
+              {fstabBackups.length === 0 && (
+                <Typography variant="body2" color="text.secondary">No backups in /etc.</Typography>
+              )}
+              {fstabBackups.map(b => (
+                <Grid container key={b.ts} alignItems="center" spacing={1}>
+                  <Grid item xs={8}>
+                    <Typography variant="body2">
+                      <code>{b.path}</code> {new Date(b.ts * 1000).toLocaleString()}
+                      {b.blocks && b.blocks.length > 0 ? <span style={{ fontSize: 12, marginLeft: 8 }}>({b.blocks.join(', ')})</span> : null}
+                    </Typography>
+                  </Grid>
+                  <Grid item xs={4}>
+                    <Button size="small" onClick={() => showBackupDiff(b)}>Diff</Button>
+                    <Button size="small" color="warning" onClick={() => restoreBackup(b)}>Restore</Button>
+                  </Grid>
+                </Grid>
+              ))}
+            </Paper>
+
+            <Paper variant="outlined" sx={{ p: 2, mt: 2 }}>
+              <Grid container alignItems="center" justifyContent="space-between">
+                <Grid item><Typography variant="subtitle1">Consistency</Typography></Grid>
+                <Grid item>
+                  <Button size="small" onClick={() => refreshConsistency()}>Check</Button>
+                </Grid>
+              </Grid>
+              {consistency.length === 0 && (
+                <Typography variant="body2" color="text.secondary">/etc/fstab and the saved metadata agree.</Typography>
+              )}
+              {consistency.map(c => (
+                <Grid container key={c.id} alignItems="center" spacing={1}>
+                  <Grid item xs={6}>
+                    <Typography variant="body2">
+                      <code>{c.id}</code> <span style={{ fontSize: 12, marginLeft: 8 }}>{c.state.replace('_', ' ')}</span>
+                    </Typography>
+                  </Grid>
+                  <Grid item xs={6}>
+                    {c.resolutions.map(r => (
+                      <Button key={r} size="small" color={r === 'remove_block' ? 'error' : 'primary'} onClick={() => repair(c, r)}>{r.replace('_', ' ')}</Button>
+                    ))}
+                  </Grid>
+                </Grid>
+              ))}
+            </Paper>
+
             <Paper variant="outlined" sx={{ p: 2, mt: 2 }}>
               <Typography variant="subtitle1">Recent operations</Typography>
               {operationsLog.length === 0 && (
@@ -1096,6 +1381,9 @@ If this keeps happening, it might be a bug.`,
               <DialogTitle>Confirm remove persistent mount</DialogTitle>
               <DialogContent>
                 <Typography>Are you sure you want to remove the persistent mount <code>{removeDialogId}</code>? This will attempt to unmount targets and remove the fstab block.</Typography>
+                <div style={{ marginTop: 8 }}>
+                  {removePlan ? <PlanView plan={removePlan} /> : <CircularProgress size={18} />}
+                </div>
                 <Grid container alignItems="center" spacing={1} sx={{ mt: 1 }}>
                   <Grid item>
                     <input id="force-unmount" type="checkbox" checked={removeDialogForce} onChange={e => setRemoveDialogForce(e.target.checked)} />
@@ -1112,51 +1400,33 @@ If this keeps happening, it might be a bug.`,
                   if (!removeDialogId) return;
                   try {
                     // Prefer single-step removal by target when we have a representative target.
-                    let res: string;
                     if (removeDialogTarget) {
-                      res = await invoke<string>('remove_block_for_target', { target: removeDialogTarget, force: removeDialogForce });
+                      await invoke<LindyResponse>('remove_block_for_target', { target: removeDialogTarget, force: removeDialogForce });
                     } else {
-                      res = await invoke<string>('remove_fstab_block', { id: removeDialogId, force: removeDialogForce });
+                      await invoke<LindyResponse>('remove_fstab_block', { id: removeDialogId, force: removeDialogForce });
                     }
-                    let parsed: any = null;
-                    try { parsed = JSON.parse(res); } catch (_) { parsed = null; }
-                    if (parsed && parsed.status === 'ok') {
-                      setOpResultMessage(`Removed mapping ${removeDialogId || removeDialogTarget}.`);
-                      setPendingForceId(null);
-                      refreshInstalledBlocks();
-                    } else if (parsed && parsed.status === 'error') {
-                      if (parsed.code === 'spawn_pkexec_failed') {
+                    setOpResultMessage(`Removed mapping ${removeDialogId || removeDialogTarget}.`);
+                    setPendingForceId(null);
+                    refreshInstalledBlocks();
+                    setOpResultOpen(true);
+                    setRemoveDialogOpen(false);
+                  } catch (e) {
+                    const err = asLindyError(e);
+                    if (err.code === 'spawn_pkexec_failed') {
                         setOpResultMessage('Elevation helper (pkexec) not available. Run the following sudo command as root:');
                         setOpResultHint(`sudo sh -c "cp /etc/fstab /etc/fstab.lindy.manual.bak.$(date +%s) && sed -e '/^# lindy BEGIN: ${removeDialogId}/, /^# lindy END: ${removeDialogId}/d' /etc/fstab > /tmp/fstab.clean.$$ && cp /tmp/fstab.clean.$$ /etc/fstab && sync && mount -a"`);
-                        setPendingForceId(null);
-                      } else if (parsed.code === 'pkexec_failed') {
-                        // pkexec ran but returned non-zero; it may have printed stderr with hints (busy etc)
-                        const out = (parsed.stderr || parsed.stdout || parsed.message || '').toString();
-                        if (/busy/i.test(out)) {
-                          setOpResultMessage(`Unmount reported device busy for ${removeDialogId}.`);
-                          setOpResultHint('Use `sudo fuser -mv <target>` to list processes holding the mount, or retry with Force (lazy unmount).');
-                          setPendingForceId(removeDialogId);
-                        } else {
-                          setOpResultMessage(`Failed to remove mapping: ${parsed.message || parsed.code}`);
-                          setOpResultHint(out || null);
-                          setPendingForceId(null);
-                        }
-                      } else {
-                        setOpResultMessage(`Failed to remove mapping: ${parsed.message || parsed.code}`);
-                        setOpResultHint((parsed.stderr || parsed.stdout) || null);
-                        setPendingForceId(null);
-                      }
+                      setPendingForceId(null);
+                    } else if (err.code === 'busy') {
+                      setOpResultMessage(`Unmount reported device busy for ${removeDialogId}.`);
+                      setOpResultHint(errorOutput(err) || 'Use `sudo fuser -mv <target>` to list processes holding the mount, or retry with Force (lazy unmount).');
+                      setPendingForceId(removeDialogId);
                     } else {
-                      // unknown response
-                      setOpResultMessage(`Remove result:\n${res}`);
+                      setOpResultMessage(`Failed to remove mapping: ${err.message}`);
+                      setOpResultHint(errorOutput(err));
+                      setPendingForceId(null);
                     }
                     setOpResultOpen(true);
                     setRemoveDialogOpen(false);
-                  } catch (e: any) {
-                    const text = String(e);
-                    setOpResultMessage(`Failed to remove:\n${text}`);
-                    setOpResultOpen(true);
-                    setRemoveDialogOpen(false);
                   }
                 }}>Remove</Button>
               </DialogActions>
@@ -1177,18 +1447,12 @@ If this keeps happening, it might be a bug.`,
                 <Button variant="contained" onClick={async () => {
                   if (!adoptInfo) return;
                   try {
-                    const resp = await invoke<string>('adopt_block', { id: adoptInfo.id });
-                    let p: any = null;
-                    try { p = JSON.parse(resp); } catch (_) { p = null; }
-                    if (p && p.status === 'ok') {
-                      setApplyResultMessage(`Adopted block ${adoptInfo.id}.`);
-                      pushLog(`Adopted existing block ${adoptInfo.id}`);
-                      refreshInstalledBlocks();
-                    } else {
-                      setApplyResultMessage(`Failed to adopt: ${resp}`);
-                    }
-                  } catch (e: any) {
-                    setApplyResultMessage(`Adopt failed: ${String(e)}`);
+                    await invoke<LindyResponse>('adopt_block', { id: adoptInfo.id });
+                    setApplyResultMessage(`Adopted block ${adoptInfo.id}.`);
+                    pushLog(`Adopted existing block ${adoptInfo.id}`);
+                    refreshInstalledBlocks();
+                  } catch (e) {
+                    setApplyResultMessage(`Adopt failed: ${asLindyError(e).message}`);
                   } finally {
                     setAdoptDialogOpen(false);
                     setAdoptInfo(null);
@@ -1216,14 +1480,16 @@ If this keeps happening, it might be a bug.`,
                     try {
                       const id = pendingForceId;
                       setOpResultOpen(false);
-                      const res = await invoke<string>('remove_fstab_block', { id, force: true });
-                      setOpResultMessage(`Force remove result:\n${res}`);
+                      const res = await invoke<LindyResponse>('remove_fstab_block', { id, force: true });
+                      setOpResultMessage(`Force remove result:\n${res.message}`);
                       setOpResultHint(null);
                       setPendingForceId(null);
                       setOpResultOpen(true);
                       refreshInstalledBlocks();
-                    } catch (e: any) {
-                      setOpResultMessage(`Force remove failed:\n${String(e)}`);
+                    } catch (e) {
+                      const err = asLindyError(e);
+                      setOpResultMessage(`Force remove failed:\n${err.message}`);
+                      setOpResultHint(errorOutput(err));
                       setPendingForceId(null);
                       setOpResultOpen(true);
                     }
@@ -1278,7 +1544,7 @@ If this keeps happening, it might be a bug.`,
                   placeholder="e.g., John, Administrator"
                   value={windowsUsername}
                   onChange={(e) => setWindowsUsername(e.target.value)}
-                  helperText="Leave empty to auto-detect. Required if auto-detection fails."
+                  helperText="Account or profile folder name. Leave empty to use the most recently used Windows profile."
                   sx={{ mt: 2, mb: 2 }}
                 />
 
@@ -1400,6 +1666,96 @@ If this keeps happening, it might be a bug.`,
               </DialogActions>
             </Dialog>
 
+            {/* BitLocker unlock, then Smart Auto-Map again */}
+            <Dialog open={!!unlockPartition} onClose={() => !unlocking && setUnlockPartition(null)} fullWidth maxWidth="sm">
+              <DialogTitle>🔒 Unlock BitLocker</DialogTitle>
+              <DialogContent>
+                <Typography variant="body2" gutterBottom>
+                  Windows on {unlockPartition?.device}{unlockPartition?.label ? ` (${unlockPartition.label})` : ''} is encrypted
+                  with BitLocker. Enter its password or the 48-digit recovery key (shown at
+                  account.microsoft.com/devices/recoverykey for Microsoft accounts).
+                </Typography>
+                <TextField
+                  sx={{ mt: 1 }}
+                  fullWidth
+                  autoFocus
+                  type="password"
+                  label="Password or recovery key"
+                  value={unlockKey}
+                  onChange={(e) => setUnlockKey(e.currentTarget.value)}
+                  error={!!unlockError}
+                  helperText={unlockError ?? ''}
+                />
+                <FormControl fullWidth sx={{ mt: 2 }}>
+                  <InputLabel id="unlock-persist-label">At boot</InputLabel>
+                  <Select
+                    labelId="unlock-persist-label"
+                    label="At boot"
+                    value={unlockPersist}
+                    onChange={(e) => setUnlockPersist(e.target.value as '' | 'prompt' | 'key_file')}
+                  >
+                    <MenuItem value="">Leave it locked</MenuItem>
+                    <MenuItem value="prompt">Ask for the key while booting</MenuItem>
+                    <MenuItem value="key_file">Unlock with a key stored on this computer (root only)</MenuItem>
+                  </Select>
+                </FormControl>
+              </DialogContent>
+              <DialogActions>
+                <Button disabled={unlocking} onClick={() => setUnlockPartition(null)}>Cancel</Button>
+                <Button variant="contained" disabled={unlocking || !unlockKey.trim()} onClick={async () => {
+                  if (!unlockPartition) return;
+                  setUnlocking(true);
+                  setUnlockError(null);
+                  try {
+                    const res = await invoke<LindyResponse>('unlock_bitlocker', {
+                      device: unlockPartition.device,
+                      key: unlockKey,
+                      persist: unlockPersist || null,
+                    });
+                    pushLog(res.message);
+                    setUnlockPartition(null);
+                    setUnlockKey('');
+                    smartAutoMap();
+                  } catch (e) {
+                    const err = asLindyError(e);
+                    pushLog(`Unlock failed: ${err.code} - ${err.message}`);
+                    setUnlockError(err.code === 'unlock_failed' ? 'Wrong password or recovery key' : err.message);
+                  } finally {
+                    setUnlocking(false);
+                  }
+                }}>
+                  {unlocking ? <CircularProgress size={20} /> : 'Unlock'}
+                </Button>
+              </DialogActions>
+            </Dialog>
+
+            {/* Several partitions look like Windows: the user picks one */}
+            <Dialog open={partitionChoices.length > 0} onClose={() => setPartitionChoices([])} fullWidth maxWidth="sm">
+              <DialogTitle>💿 Which partition is Windows?</DialogTitle>
+              <DialogContent>
+                <Typography variant="body2" gutterBottom>
+                  These partitions look equally likely to hold Windows. Choose the one to map.
+                </Typography>
+                {partitionChoices.map(p => (
+                  <MenuItem key={p.uuid} sx={{ display: 'block' }} onClick={() => {
+                    setPartitionChoices([]);
+                    smartAutoMap(p.uuid);
+                  }}>
+                    {p.device} {p.label ? `(${p.label})` : ''} · score {p.score}
+                    <Typography variant="caption" color="text.secondary" component="div">
+                      {partitionDetails(p)}
+                    </Typography>
+                    <Typography variant="caption" color="text.secondary" component="div" sx={{ whiteSpace: 'normal' }}>
+                      {p.reasons.join(' · ')}
+                    </Typography>
+                  </MenuItem>
+                ))}
+              </DialogContent>
+              <DialogActions>
+                <Button onClick={() => setPartitionChoices([])}>Cancel</Button>
+              </DialogActions>
+            </Dialog>
+
             {/* Smart Auto-Map Success Dialog */}
             <Dialog open={autoMapSuccessOpen} onClose={() => setAutoMapSuccessOpen(false)} fullWidth maxWidth="sm">
               <DialogTitle>✅ Smart Auto-Map Successful!</DialogTitle>
@@ -1407,6 +1763,12 @@ If this keeps happening, it might be a bug.`,
                 <Typography variant="body1" gutterBottom>
                   Detected and mapped your Windows folders successfully.
                 </Typography>
+                {autoMapResult?.readOnly && (
+                  <Alert severity="warning" sx={{ mt: 2 }}>
+                    Windows is hibernated (Fast Startup) or was not shut down cleanly, so the partition was mounted
+                    read-only. Shut Windows down fully, or turn off Fast Startup in its power settings, to write to it.
+                  </Alert>
+                )}
                 <Paper variant="outlined" sx={{ p: 2, mt: 2, mb: 2, backgroundColor: 'action.hover' }}>
                   <Grid container spacing={1}>
                     <Grid item xs={5}><Typography variant="body2" color="text.secondary">Partition:</Typography></Grid>
@@ -1515,7 +1877,7 @@ If this keeps happening, it might be a bug.`,
                       <Button
                         variant="contained"
                         color="success"
-                        onClick={smartAutoMap}
+                        onClick={() => smartAutoMap()}
                         disabled={smartAutoMapLoading}
                       >
                         {smartAutoMapLoading ? (
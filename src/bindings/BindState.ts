@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Live state of one bind from fstab.
+ */
+export type BindState = { "state": "mounted" } | { "state": "not_mounted" } | { "state": "shadowed", source: string, root: string, } | { "state": "mismatch", source: string, root: string, expected_root: string, } | { "state": "stale", reason: string, };
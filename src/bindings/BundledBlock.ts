@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type BundledBlock = { id: string, 
+/**
+ * Block text with its markers; partitions are given as `UUID=`.
+ */
+block: string, 
+/**
+ * UUIDs of the partitions the block mounts.
+ */
+partitions: Array<string>, };
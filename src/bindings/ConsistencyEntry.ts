@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ConsistencyState } from "./ConsistencyState";
+import type { Resolution } from "./Resolution";
+
+export type ConsistencyEntry = { id: string, state: ConsistencyState, 
+/**
+ * Block text in /etc/fstab, when present.
+ */
+fstab_block: string | null, 
+/**
+ * Block text recorded in metadata, when present.
+ */
+metadata_block: string | null, 
+/**
+ * Whether metadata says the block was persisted.
+ */
+persisted: boolean | null, 
+/**
+ * Resolutions `plan` accepts for this state.
+ */
+resolutions: Array<Resolution>, };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Severity } from "./Severity";
+
+/**
+ * A problem found in the candidate file.
+ */
+export type Diagnostic = { 
+/**
+ * 1-based line number in the candidate file, when the problem maps to a line.
+ */
+line: number | null, 
+/**
+ * Text of that line.
+ */
+text: string | null, severity: Severity, 
+/**
+ * Which checker reported it: `findmnt` or `lindy`.
+ */
+source: string, message: string, };
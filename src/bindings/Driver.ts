@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Driver written as the fstab type of the partition line.
+ */
+export type Driver = "ntfs3" | "ntfs-3g" | "exfat" | "vfat" | "ext4" | "btrfs";
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Driver } from "./Driver";
+
+/**
+ * A driver and whether this system can use it.
+ */
+export type DriverInfo = { driver: Driver, available: boolean, fuse: boolean, 
+/**
+ * Options the partition line gets with this driver.
+ */
+options: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FstabBackup = { ts: number, path: string, size: number, 
+/**
+ * Ids of the lindy blocks in the backup, or None when it is not readable.
+ */
+blocks: Array<string> | null, };
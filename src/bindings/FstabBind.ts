@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BindKind } from "./BindKind";
+import type { BindState } from "./BindState";
+
+export type FstabBind = { src: string, target: string, kind: BindKind | null, propagation: string | null, mount: BindState | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FstabBind } from "./FstabBind";
+
+export type FstabBlock = { id: string, text: string, targets: Array<string>, binds: Array<FstabBind>, managed: boolean, };
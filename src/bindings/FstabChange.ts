@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Payload of `lindy://fstab-changed`: lindy block ids by kind of change.
+ */
+export type FstabChange = { added: Array<string>, removed: Array<string>, changed: Array<string>, 
+/**
+ * Whether lines outside lindy blocks changed.
+ */
+other_lines: boolean, };
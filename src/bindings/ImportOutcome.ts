@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ImportStatus } from "./ImportStatus";
+import type { LindyError } from "./LindyError";
+
+export type ImportOutcome = { id: string, status: ImportStatus, message: string, error: LindyError | null, };
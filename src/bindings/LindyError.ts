@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Diagnostic } from "./Diagnostic";
+import type { WindowsPartition } from "./WindowsPartition";
+
+export type LindyError = { "code": "invalid_request", message: string, } | { "code": "not_found", message: string, } | { "code": "conflict", message: string, } | { "code": "already_managed", message: string, target: string, } | { "code": "target_in_fstab", message: string, target: string, line: string, } | { "code": "validation_failed", message: string, diagnostics: Array<Diagnostic>, } | { "code": "busy", message: string, log: Array<string>, } | { "code": "rolled_back", message: string, log: Array<string>, reason: string | null, restored: boolean, backup: string | null, } | { "code": "mount_failed", message: string, log: Array<string>, } | { "code": "umount_failed", message: string, log: Array<string>, } | { "code": "untrusted_file", message: string, } | { "code": "io_error", message: string, } | { "code": "spawn_pkexec_failed", message: string, } | { "code": "pkexec_failed", message: string, stdout: string, stderr: string, } | { "code": "no_windows_partitions", message: string, } | { "code": "bitlocker_locked", message: string, partition: WindowsPartition, } | { "code": "ambiguous_windows_partition", message: string, candidates: Array<WindowsPartition>, } | { "code": "unlock_failed", message: string, log: Array<string>, } | { "code": "no_users_detected", message: string, mount_point: string, partition_info: WindowsPartition, } | { "code": "mapping_failed", message: string, mount_point: string, username: string, partition_info: WindowsPartition, };
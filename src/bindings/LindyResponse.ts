@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FolderMapping } from "./FolderMapping";
+import type { ImportOutcome } from "./ImportOutcome";
+import type { Plan } from "./Plan";
+import type { RepairOutcome } from "./RepairOutcome";
+import type { WindowsPartition } from "./WindowsPartition";
+
+export type LindyResponse = { "code": "applied", message: string, log: Array<string>, backup: string | null, } | { "code": "updated", message: string, log: Array<string>, backup: string | null, block: string, targets: Array<string>, } | { "code": "removed", message: string, log: Array<string>, backup: string | null, id: string | null, } | { "code": "restored", message: string, log: Array<string>, backup: string | null, } | { "code": "pruned", message: string, log: Array<string>, removed: Array<string>, } | { "code": "nothing_to_prune", message: string, } | { "code": "mounted", message: string, log: Array<string>, } | { "code": "unmounted", message: string, log: Array<string>, } | { "code": "windows_hibernated", message: string, log: Array<string>, } | { "code": "unlocked", message: string, log: Array<string>, device: string, } | { "code": "adopted", message: string, id: string, } | { "code": "adoptable_existing_block", message: string, id: string, block: string, targets: Array<string>, } | { "code": "auto_map_success", message: string, windows_partition: WindowsPartition, mappings: Array<FolderMapping>, mount_point: string, username: string, 
+/**
+ * The partition was mounted read-only because Windows is hibernated.
+ */
+read_only: boolean, } | { "code": "unchanged", message: string, id: string, } | { "code": "planned", message: string, plan: Plan, } | { "code": "imported", message: string, outcomes: Array<ImportOutcome>, } | { "code": "repaired", message: string, outcomes: Array<RepairOutcome>, };
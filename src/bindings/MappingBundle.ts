@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BundledBlock } from "./BundledBlock";
+
+export type MappingBundle = { version: number, exported_at: number, 
+/**
+ * Home directory of the exporting user.
+ */
+home: string, blocks: Array<BundledBlock>, };
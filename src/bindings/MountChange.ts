@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MountInfo } from "./MountInfo";
+
+/**
+ * Payload of `lindy://mount-changed`.
+ */
+export type MountChange = { mounted: Array<MountInfo>, unmounted: Array<MountInfo>, };
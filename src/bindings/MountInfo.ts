@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * One line of mountinfo (see proc(5)). Paths are decoded.
+ */
+export type MountInfo = { mount_id: number, parent_id: number, 
+/**
+ * `major:minor` of the mounted filesystem.
+ */
+dev: string, 
+/**
+ * Path inside the filesystem that forms the root of this mount.
+ */
+root: string, mount_point: string, fs_type: string, source: string, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * One step the helper would run, in order.
+ */
+export type Operation = { "op": "mkdir", path: string, } | { "op": "mount", source: string, target: string, read_only: boolean, } | { "op": "umount", target: string, lazy_if_busy: boolean, } | { "op": "mount_all", mounts: Array<string>, };
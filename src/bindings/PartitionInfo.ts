@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type PartitionInfo = { name: string, path: string, fstype: string | null, uuid: string | null, label: string | null, mountpoint: string | null, 
+/**
+ * Every mount point; `mountpoint` is the first.
+ */
+mountpoints: Array<string>, size: string | null, fsused: string | null, fsavail: string | null, 
+/**
+ * GPT type GUID or MBR type byte.
+ */
+parttype: string | null, partlabel: string | null, partuuid: string | null, 
+/**
+ * Model, serial and transport of the disk the partition is on.
+ */
+model: string | null, serial: string | null, transport: string | null, removable: boolean, rotational: boolean, };
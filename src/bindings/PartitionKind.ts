@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What a partition is for, from its type GUID (GPT) or type byte (MBR).
+ */
+export type PartitionKind = "basic_data" | "recovery" | "efi_system" | "microsoft_reserved" | "other";
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Operation } from "./Operation";
+
+/**
+ * What a mutating command would do. Nothing is changed to compute it.
+ */
+export type Plan = { 
+/**
+ * Unified diff of /etc/fstab; empty when the file stays as it is.
+ */
+fstab_diff: string, operations: Array<Operation>, 
+/**
+ * Backups of /etc/fstab that would be written.
+ */
+backups: Array<string>, 
+/**
+ * Reasons the command is expected to fail or to need attention.
+ */
+conflicts: Array<string>, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Resolution } from "./Resolution";
+
+/**
+ * A resolution chosen for one id.
+ */
+export type RepairAction = { id: string, resolution: Resolution, };
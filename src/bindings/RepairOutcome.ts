@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LindyError } from "./LindyError";
+import type { Resolution } from "./Resolution";
+
+/**
+ * How applying a `RepairAction` went.
+ */
+export type RepairOutcome = { id: string, resolution: Resolution, message: string, 
+/**
+ * Why the action failed; None when it was applied.
+ */
+error: LindyError | null, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What a Windows system volume has in its root.
+ */
+export type SystemMarkers = { 
+/**
+ * Windows\System32
+ */
+system32: boolean, users: boolean, pagefile: boolean, };
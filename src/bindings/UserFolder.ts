@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type UserFolder = { name: string, linux_path: string, windows_path: string | null, exists_linux: boolean, exists_windows: boolean, };
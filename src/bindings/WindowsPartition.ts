@@ -0,0 +1,51 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PartitionKind } from "./PartitionKind";
+import type { SystemMarkers } from "./SystemMarkers";
+import type { WindowsProfile } from "./WindowsProfile";
+
+export type WindowsPartition = { uuid: string, label: string | null, device: string, mount_point: string | null, size: string | null, is_mounted: boolean, has_users_folder: boolean, 
+/**
+ * Names to pass as `username`, most recently used first.
+ */
+detected_users: Array<string>, 
+/**
+ * User profiles from the registry of a mounted Windows; empty when it
+ * is not mounted or its registry cannot be read.
+ */
+profiles: Array<WindowsProfile>, 
+/**
+ * Windows is hibernated (Fast Startup) or did not shut down cleanly, so
+ * the partition can only be mounted read-only. None when it could not
+ * be checked.
+ */
+hibernated: boolean | null, 
+/**
+ * hiberfil.sys holds a saved session. Unlike `hibernated` not set for
+ * a volume that is only dirty, which any data partition can be; this
+ * is what counts towards `score`.
+ */
+hiberfil: boolean | null, 
+/**
+ * BitLocker-encrypted. Unlocked volumes are listed by their
+ * /dev/mapper device.
+ */
+encrypted: boolean, 
+/**
+ * A BitLocker volume that has to be unlocked (`unlock_bitlocker`)
+ * before it can be mounted; `uuid` is the BitLocker volume's.
+ */
+locked: boolean, 
+/**
+ * From the partition type; recovery partitions are never auto-mapped.
+ */
+kind: PartitionKind, parttype: string | null, partlabel: string | null, partuuid: string | null, mount_points: Array<string>, fsused: string | null, fsavail: string | null, model: string | null, serial: string | null, transport: string | null, removable: boolean, rotational: boolean, 
+/**
+ * Windows system folders found on it; None when its contents could not
+ * be read.
+ */
+markers: SystemMarkers | null, 
+/**
+ * How much it looks like the Windows system partition, and why (see
+ * score.rs). Partitions are listed highest first.
+ */
+score: number, reasons: Array<string>, };
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A user profile of the Windows installation.
+ */
+export type WindowsProfile = { sid: string, 
+/**
+ * Account name from the SAM hive; None for accounts it does not hold
+ * (domain and Microsoft Entra ID accounts) or when it cannot be read.
+ */
+account: string | null, 
+/**
+ * ProfileImagePath as Windows has it, e.g. `C:\Users\ann`.
+ */
+profile_path: string, 
+/**
+ * The profile folder relative to the partition root (`Users/ann`);
+ * None when it is on another drive or no longer exists.
+ */
+folder: string | null, };
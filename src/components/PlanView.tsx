@@ -0,0 +1,33 @@
+import Alert from "@mui/material/Alert";
+import Typography from "@mui/material/Typography";
+import type { Plan } from "../bindings/Plan";
+import { operationLabel } from "../lindy";
+
+// Dry-run result of a mutating command, shown before anything asks for a password.
+export default function PlanView({ plan }: { plan: Plan }) {
+  return (
+    <>
+      {plan.conflicts.map((c) => (
+        <Alert key={c} severity="warning" sx={{ mb: 1 }}>{c}</Alert>
+      ))}
+      <Typography variant="subtitle2">Operations</Typography>
+      <ul style={{ marginTop: 4 }}>
+        {plan.backups.map((b) => (
+          <li key={b}>back up /etc/fstab to <code>{b}</code></li>
+        ))}
+        {plan.operations.map((op, idx) => (
+          <li key={idx}><code>{operationLabel(op)}</code></li>
+        ))}
+        {plan.operations.length === 0 && <li>nothing to do</li>}
+      </ul>
+      {plan.fstab_diff && (
+        <>
+          <Typography variant="subtitle2">Changes to /etc/fstab</Typography>
+          <pre className="fstab-preview" style={{ maxHeight: 240, overflow: 'auto' }}>
+            <code>{plan.fstab_diff}</code>
+          </pre>
+        </>
+      )}
+    </>
+  );
+}
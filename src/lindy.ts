@@ -0,0 +1,84 @@
+// Helpers for the results of the Tauri commands. Commands resolve to their
+// value (a LindyResponse for anything that changes the system) and reject
+// with a LindyError; both types are generated from src-tauri/src/response.rs.
+import type { BindState } from "./bindings/BindState";
+import type { Driver } from "./bindings/Driver";
+import type { DriverInfo } from "./bindings/DriverInfo";
+import type { LindyError } from "./bindings/LindyError";
+import type { Operation } from "./bindings/Operation";
+import type { PartitionInfo } from "./bindings/PartitionInfo";
+
+export type { LindyError } from "./bindings/LindyError";
+export type { LindyResponse } from "./bindings/LindyResponse";
+
+/** The LindyError a command rejected with; anything else becomes an io_error. */
+export function asLindyError(e: unknown): LindyError {
+  if (e && typeof e === 'object' && 'code' in e && 'message' in e) return e as LindyError;
+  return { code: 'io_error', message: String(e) };
+}
+
+/** Output attached to an error (helper log, pkexec output, diagnostics), if any. */
+export function errorOutput(err: LindyError): string | null {
+  switch (err.code) {
+    case 'validation_failed':
+      return err.diagnostics
+        .map((d) => `${d.severity}${d.line ? ` (line ${d.line})` : ''}: ${d.message}`)
+        .join('\n') || null;
+    case 'pkexec_failed':
+      return err.stderr || err.stdout || null;
+    default:
+      return 'log' in err && err.log.length ? err.log.join('\n') : null;
+  }
+}
+
+/** Short label and colour for the live state of a bind. */
+export function bindStateLabel(s: BindState | null): { label: string, color: string } {
+  switch (s?.state) {
+    case 'mounted':
+      return { label: 'mounted', color: '#2e7d32' };
+    case 'not_mounted':
+      return { label: 'not mounted', color: '#757575' };
+    case 'shadowed':
+      return { label: `covered by ${s.source}`, color: '#ed6c02' };
+    case 'mismatch':
+      return { label: `mounted from ${s.source}:${s.root}`, color: '#d32f2f' };
+    case 'stale':
+      return { label: `stale: ${s.reason}`, color: '#d32f2f' };
+    default:
+      return { label: 'unknown', color: '#757575' };
+  }
+}
+
+/** Drivers that can mount a filesystem as lsblk reports it (see mountopts.rs). */
+export function driversFor(fstype: string | null | undefined, drivers: DriverInfo[]): DriverInfo[] {
+  const names: Driver[] = fstype === 'ntfs' ? ['ntfs3', 'ntfs-3g'] : [fstype as Driver];
+  return drivers.filter(d => names.includes(d.driver));
+}
+
+/** Partition label, disk and free space of a partition, for pickers. */
+export function partitionDetails(
+  p: Pick<PartitionInfo, 'partlabel' | 'model' | 'transport' | 'removable' | 'size' | 'fsavail'>,
+): string {
+  return [
+    p.partlabel,
+    p.model,
+    p.transport,
+    p.removable ? 'removable' : null,
+    p.size,
+    p.fsavail ? `${p.fsavail} free` : null,
+  ].filter(Boolean).join(' · ');
+}
+
+/** One step of a dry-run plan, written as the command it stands for. */
+export function operationLabel(op: Operation): string {
+  switch (op.op) {
+    case 'mkdir':
+      return `mkdir -p ${op.path}`;
+    case 'mount':
+      return `mount${op.read_only ? ' -o ro' : ''} ${op.source} ${op.target}`;
+    case 'umount':
+      return `umount ${op.target}${op.lazy_if_busy ? ' (lazy if busy)' : ''}`;
+    case 'mount_all':
+      return op.mounts.length ? `mount -a (${op.mounts.join(', ')})` : 'mount -a';
+  }
+}